@@ -1,245 +1,583 @@
-use glam::{DMat3, DVec2, DVec3, Vec3};
+use std::f64::consts::PI;
 
-use crate::AABB;
+use glam::{DVec2, DVec3};
+
+pub(crate) mod cdt;
+
+use cdt::Cdt;
 
 #[cfg(test)]
 mod algo_tests;
 
+#[derive(Debug)]
 pub(crate) enum TriTriIntersectStatus {
     NotIntersect,
     Intersect(IPoint, Option<IPoint>),
     Coplanar(Vec<IPoint>),
 }
 
+/// intersection point of two triangles, `t1` and `t2` tell where the point sits on each triangle
+#[derive(Debug, Clone, Copy)]
 pub(crate) struct IPoint {
-    p: DVec3,
-    t1: IntersectTopo,
-    t2: IntersectTopo,
+    pub(crate) p: DVec3,
+    pub(crate) t1: IntersectTopo,
+    pub(crate) t2: IntersectTopo,
 }
 
-#[derive(Clone, Copy)]
+/// local topology of a point on a triangle, edges are always stored as (small, big)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub(crate) enum IntersectTopo {
     Edge(u8, u8),
     Vertex(u8),
     Face,
 }
 
-// TODO reference: https://web.stanford.edu/class/cs277/resources/papers/Moller1997b.pdf
+impl IntersectTopo {
+    fn edge(a: u8, b: u8) -> Self {
+        IntersectTopo::Edge(a.min(b), a.max(b))
+    }
+}
+
+const EDGES: [(u8, u8); 3] = [(0, 1), (1, 2), (2, 0)];
+
+// reference: https://web.stanford.edu/class/cs277/resources/papers/Moller1997b.pdf
+// instead of intersecting the two intervals on the plane-plane line, every edge of one triangle
+// is clipped against the other triangle. the result only depends on the edge and the other
+// triangle, so two triangles sharing an edge always get the same point on that edge.
 /// include aabb check, no need to check aabb in the caller
 pub(crate) fn tri_tri_intersect(
     tri1: [DVec3; 3],
     tri2: [DVec3; 3],
     err: f64,
 ) -> TriTriIntersectStatus {
-    use TriTriIntersectStatus::{Coplanar, NotIntersect};
+    use TriTriIntersectStatus::{Intersect, NotIntersect};
 
     // AABB test
-    let mut aabb1 = AABB::new();
-    let mut aabb2 = AABB::new();
-
-    for i in 0..3 {
-        aabb1.grow(&tri1[i].as_vec3().to_array());
-        aabb2.grow(&tri2[i].as_vec3().to_array());
+    for axis in 0..3 {
+        let min1 = tri1.iter().map(|v| v[axis]).fold(f64::MAX, f64::min);
+        let max1 = tri1.iter().map(|v| v[axis]).fold(f64::MIN, f64::max);
+        let min2 = tri2.iter().map(|v| v[axis]).fold(f64::MAX, f64::min);
+        let max2 = tri2.iter().map(|v| v[axis]).fold(f64::MIN, f64::max);
+        if max1 + err < min2 || max2 + err < min1 {
+            return NotIntersect;
+        }
     }
 
-    if !aabb1.intersect_aabb(&aabb2, err as f32) {
+    // degenerated triangles have no plane
+    let (Some(n1), Some(n2)) = (plane_normal(&tri1), plane_normal(&tri2)) else {
         return NotIntersect;
-    }
+    };
 
     // plane test
-    let tri2_plane_normal = (tri2[0] - tri2[1]).cross(tri2[0] - tri2[2]).normalize();
-    let d_tri1_0 = tri2_plane_normal.dot(tri1[0] - tri2[0]);
-    let d_tri1_1 = tri2_plane_normal.dot(tri1[1] - tri2[0]);
-    let d_tri1_2 = tri2_plane_normal.dot(tri1[2] - tri2[0]);
-
-    let has_same_sign =
-        (d_tri1_0.is_sign_negative() && d_tri1_1.is_sign_negative() && d_tri1_2.is_sign_negative())
-            || (d_tri1_0.is_sign_positive()
-                && d_tri1_1.is_sign_positive()
-                && d_tri1_2.is_sign_positive());
-    let on_plane_status = (
-        d_tri1_0.abs() < err,
-        d_tri1_1.abs() < err,
-        d_tri1_2.abs() < err,
-    );
-    if has_same_sign {
-        // if vertex on plane
-        match on_plane_status {
-            (true, true, true) => {
-                // co-planer case
-                todo!("co-planer case")
-            }
-            (true, true, false) => {
-                // edge on plane
-                return edge_on_plane(&tri1, &tri2, (0, 1));
-            }
-            (true, false, true) => {
-                // edge on plane
-                return edge_on_plane(&tri1, &tri2, (0, 2));
-            }
-            (true, false, false) => {
-                // vertex on plane
-                return vertex_on_plane(&tri2, tri1[0], 0);
-            }
-            (false, true, true) => {
-                // edge on plane
-                return edge_on_plane(&tri1, &tri2, (1, 2));
-            }
-            (false, true, false) => {
-                // vertex on plane
-                return vertex_on_plane(&tri2, tri1[1], 1);
-            }
-            (false, false, true) => {
-                // vertex on plane
-                return vertex_on_plane(&tri2, tri1[2], 2);
+    let d1 = plane_distances(&tri1, tri2[0], n2, err);
+    let d2 = plane_distances(&tri2, tri1[0], n1, err);
+    if has_same_sign(&d1) || has_same_sign(&d2) {
+        return NotIntersect;
+    }
+    if d1.iter().all(|d| *d == 0.) || d2.iter().all(|d| *d == 0.) {
+        return handle_coplaner(&tri1, &tri2, (n1, n2), err);
+    }
+
+    let mut points = vec![];
+    for (t1, p) in vertex_on_plane(&tri1, &d1).chain(edge_on_plane(&tri1, &d1)) {
+        if let Some(t2) = locate_on_triangle(&tri2, n2, p, err) {
+            push_unique(&mut points, IPoint { p, t1, t2 }, err);
+        }
+    }
+    for (t2, p) in vertex_on_plane(&tri2, &d2).chain(edge_on_plane(&tri2, &d2)) {
+        if let Some(t1) = locate_on_triangle(&tri1, n1, p, err) {
+            push_unique(&mut points, IPoint { p, t1, t2 }, err);
+        }
+    }
+
+    match points.len() {
+        0 => NotIntersect,
+        1 => Intersect(points[0], None),
+        2 => Intersect(points[0], Some(points[1])),
+        // only happens when err is not consistent with the geometry, keep the longest segment
+        _ => {
+            let mut pair = (0, 1);
+            let mut max = 0.;
+            for i in 0..points.len() {
+                for j in i + 1..points.len() {
+                    let d = points[i].p.distance_squared(points[j].p);
+                    if d > max {
+                        max = d;
+                        pair = (i, j);
+                    }
+                }
             }
-            (false, false, false) => return NotIntersect,
+            Intersect(points[pair.0], Some(points[pair.1]))
         }
     }
+}
 
-    let alone_vertex = 0;
-    let pair_vertex = (0, 0);
+fn plane_normal(tri: &[DVec3; 3]) -> Option<DVec3> {
+    (tri[1] - tri[0]).cross(tri[2] - tri[0]).try_normalize()
+}
 
-    // l_dir * t + O = l
-    let tri1_plane_normal = (tri1[0] - tri1[1]).cross(tri1[0] - tri1[2]).normalize();
-    let l_dir = tri1_plane_normal.cross(tri2_plane_normal);
-    if d_tri1_0.is_sign_negative() {}
-    todo!()
+/// signed distances from the vertices to the plane, distances smaller than err are snapped to 0
+fn plane_distances(tri: &[DVec3; 3], origin: DVec3, normal: DVec3, err: f64) -> [f64; 3] {
+    tri.map(|v| {
+        let d = normal.dot(v - origin);
+        if d.abs() < err {
+            0.
+        } else {
+            d
+        }
+    })
 }
 
-enum EdgeOnPlaneStatus {
-    NotIntersect,
-    IntersectVertex(u8),
-    IntersectVertexEdge(u8, (u8, u8, DVec3)),
-    IntersectEdgeEdge((u8, u8, DVec3), (u8, u8, DVec3)),
-    IntersectVertexVertex(u8, u8),
-}
-
-impl EdgeOnPlaneStatus {
-    fn to_status(
-        &self,
-        tri1: &[DVec3; 3],
-        tri2: &[DVec3; 3],
-        on_plane_vertex: (u8, u8),
-    ) -> TriTriIntersectStatus {
-        let t1 = IntersectTopo::Edge(on_plane_vertex.0, on_plane_vertex.1);
-        match self {
-            Self::NotIntersect => TriTriIntersectStatus::NotIntersect,
-            Self::IntersectVertex(v) => TriTriIntersectStatus::Intersect(
-                IPoint {
-                    p: tri2[*v as usize],
-                    t1,
-                    t2: IntersectTopo::Vertex(*v),
-                },
-                None,
-            ),
-            Self::IntersectVertexEdge(v, e) => TriTriIntersectStatus::Intersect(
-                IPoint {
-                    p: tri2[*v as usize],
-                    t1,
-                    t2: IntersectTopo::Vertex(*v),
-                },
-                Some(IPoint {
-                    p: e.2,
-                    t1,
-                    t2: IntersectTopo::Edge(e.0, e.1),
-                }),
-            ),
-            Self::IntersectEdgeEdge(e1, e2) => TriTriIntersectStatus::Intersect(
-                IPoint {
-                    p: e1.2,
-                    t1,
-                    t2: IntersectTopo::Edge(e1.0, e1.1),
-                },
-                Some(IPoint {
-                    p: e2.2,
-                    t1,
-                    t2: IntersectTopo::Edge(e2.0, e2.1),
-                }),
-            ),
-            Self::IntersectVertexVertex(v1, v2) => TriTriIntersectStatus::Intersect(
-                IPoint {
-                    p: tri1[*v1 as usize],
-                    t1,
-                    t2: IntersectTopo::Vertex(*v1),
-                },
-                Some(IPoint {
-                    p: tri2[*v2 as usize],
-                    t1,
-                    t2: IntersectTopo::Vertex(*v2),
-                }),
-            ),
+fn has_same_sign(d: &[f64; 3]) -> bool {
+    d.iter().all(|d| *d > 0.) || d.iter().all(|d| *d < 0.)
+}
+
+/// vertices lying on the plane of the other triangle
+fn vertex_on_plane<'a>(
+    tri: &'a [DVec3; 3],
+    d: &'a [f64; 3],
+) -> impl Iterator<Item = (IntersectTopo, DVec3)> + 'a {
+    (0..3u8)
+        .filter(|i| d[*i as usize] == 0.)
+        .map(|i| (IntersectTopo::Vertex(i), tri[i as usize]))
+}
+
+/// edges crossing the plane of the other triangle, with the crossing point
+fn edge_on_plane<'a>(
+    tri: &'a [DVec3; 3],
+    d: &'a [f64; 3],
+) -> impl Iterator<Item = (IntersectTopo, DVec3)> + 'a {
+    EDGES.iter().filter_map(|(i, j)| {
+        let (a, b) = (*i as usize, *j as usize);
+        if d[a] * d[b] < 0. {
+            let p = lerp_by_distance(tri[a], tri[b], d[a], d[b]);
+            Some((IntersectTopo::edge(*i, *j), p))
+        } else {
+            None
         }
+    })
+}
+
+/// point between a and b where the (signed) distance goes to 0,
+/// the end points are sorted first so the result does not depend on the edge direction
+fn lerp_by_distance(a: DVec3, b: DVec3, da: f64, db: f64) -> DVec3 {
+    let (a, b, da, db) = if a.to_array() > b.to_array() {
+        (b, a, db, da)
+    } else {
+        (a, b, da, db)
+    };
+    a + (b - a) * (da / (da - db))
+}
+
+/// locate a point lying on the plane of a triangle, return None if the point is outside
+pub(crate) fn locate_on_triangle(
+    tri: &[DVec3; 3],
+    normal: DVec3,
+    p: DVec3,
+    err: f64,
+) -> Option<IntersectTopo> {
+    let mut on_edge = [false; 3];
+    for (k, (i, j)) in EDGES.iter().enumerate() {
+        let d = edge_distance(tri[*i as usize], tri[*j as usize], normal, p);
+        if d < -err {
+            return None;
+        }
+        on_edge[k] = d <= err;
+    }
+    match on_edge {
+        [false, false, false] => Some(IntersectTopo::Face),
+        [true, false, false] => Some(IntersectTopo::edge(0, 1)),
+        [false, true, false] => Some(IntersectTopo::edge(1, 2)),
+        [false, false, true] => Some(IntersectTopo::edge(2, 0)),
+        [true, true, false] => Some(IntersectTopo::Vertex(1)),
+        [false, true, true] => Some(IntersectTopo::Vertex(2)),
+        [true, false, true] => Some(IntersectTopo::Vertex(0)),
+        // triangle is smaller than err
+        [true, true, true] => None,
     }
 }
 
-fn edge_on_plane(
-    tri1: &[DVec3; 3],
-    tri2: &[DVec3; 3],
-    on_plane_vertex: (u8, u8),
-) -> TriTriIntersectStatus {
-    // TODO
-    let s = EdgeOnPlaneStatus::NotIntersect;
-    s.to_status(tri1, tri2, on_plane_vertex)
+/// signed distance from p to the line ab, measured in the plane with the given normal,
+/// positive on the left side of ab
+fn edge_distance(a: DVec3, b: DVec3, normal: DVec3, p: DVec3) -> f64 {
+    let e = b - a;
+    e.cross(p - a).dot(normal) / e.length()
 }
 
-enum VertexOnPlaneStatus {
-    NotIntersect,
-    IntersectVertex(u8),
-    IntersectEdge(u8, u8),
-    IntersectFace,
+fn push_unique(points: &mut Vec<IPoint>, point: IPoint, err: f64) {
+    let exist = points.iter().any(|p| {
+        (p.t1 == point.t1 && p.t2 == point.t2) || p.p.distance_squared(point.p) < err * err
+    });
+    if !exist {
+        points.push(point);
+    }
 }
 
-impl VertexOnPlaneStatus {
-    fn to_status(&self, tri: &[DVec3; 3], p: DVec3, index: u8) -> TriTriIntersectStatus {
-        let t1 = IntersectTopo::Vertex(index);
-        match self {
-            VertexOnPlaneStatus::NotIntersect => TriTriIntersectStatus::NotIntersect,
-            VertexOnPlaneStatus::IntersectVertex(v) => TriTriIntersectStatus::Intersect(
+/// both triangles lie on the same plane, return the overlapping convex polygon
+fn handle_coplaner(
+    tri1: &[DVec3; 3],
+    tri2: &[DVec3; 3],
+    normals: (DVec3, DVec3),
+    err: f64,
+) -> TriTriIntersectStatus {
+    let (n1, n2) = normals;
+    let mut points = vec![];
+    for i in 0..3u8 {
+        let p = tri1[i as usize];
+        if let Some(t2) = locate_on_triangle(tri2, n2, p, err) {
+            push_unique(
+                &mut points,
                 IPoint {
                     p,
-                    t1,
-                    t2: IntersectTopo::Vertex(*v),
+                    t1: IntersectTopo::Vertex(i),
+                    t2,
                 },
-                None,
-            ),
-            VertexOnPlaneStatus::IntersectEdge(v1, v2) => TriTriIntersectStatus::Intersect(
+                err,
+            );
+        }
+    }
+    for i in 0..3u8 {
+        let p = tri2[i as usize];
+        if let Some(t1) = locate_on_triangle(tri1, n1, p, err) {
+            push_unique(
+                &mut points,
                 IPoint {
                     p,
                     t1,
-                    t2: IntersectTopo::Edge(*v1, *v2),
+                    t2: IntersectTopo::Vertex(i),
                 },
-                None,
-            ),
-            VertexOnPlaneStatus::IntersectFace => TriTriIntersectStatus::Intersect(
-                IPoint {
+                err,
+            );
+        }
+    }
+    for (i1, j1) in EDGES {
+        for (i2, j2) in EDGES {
+            let (a, b) = (tri1[i1 as usize], tri1[j1 as usize]);
+            let (c, d) = (tri2[i2 as usize], tri2[j2 as usize]);
+            if let Some(p) = edge_edge_crossing(a, b, c, d, n1, err) {
+                let point = IPoint {
                     p,
-                    t1,
-                    t2: IntersectTopo::Face,
-                },
-                None,
-            ),
+                    t1: IntersectTopo::edge(i1, j1),
+                    t2: IntersectTopo::edge(i2, j2),
+                };
+                push_unique(&mut points, point, err);
+            }
         }
     }
+    if points.is_empty() {
+        return TriTriIntersectStatus::NotIntersect;
+    }
+
+    // sort the convex polygon counter clockwise around the normal of tri1
+    let center = points.iter().fold(DVec3::ZERO, |c, p| c + p.p) / points.len() as f64;
+    let u = (tri1[1] - tri1[0]).normalize();
+    let v = n1.cross(u);
+    points.sort_by(|a, b| {
+        let a = (a.p - center).dot(v).atan2((a.p - center).dot(u));
+        let b = (b.p - center).dot(v).atan2((b.p - center).dot(u));
+        a.partial_cmp(&b).unwrap()
+    });
+    TriTriIntersectStatus::Coplanar(points)
 }
 
-fn vertex_on_plane(tri: &[DVec3; 3], p: DVec3, index: u8) -> TriTriIntersectStatus {
-    todo!()
+/// proper crossing of two coplanar segments, touching at end points is not a crossing
+fn edge_edge_crossing(
+    a: DVec3,
+    b: DVec3,
+    c: DVec3,
+    d: DVec3,
+    normal: DVec3,
+    err: f64,
+) -> Option<DVec3> {
+    let crossed = |x: f64, y: f64| (x > err && y < -err) || (x < -err && y > err);
+    let (dc, dd) = (
+        edge_distance(a, b, normal, c),
+        edge_distance(a, b, normal, d),
+    );
+    if !crossed(dc, dd) {
+        return None;
+    }
+    let (da, db) = (
+        edge_distance(c, d, normal, a),
+        edge_distance(c, d, normal, b),
+    );
+    if !crossed(da, db) {
+        return None;
+    }
+    Some(lerp_by_distance(a, b, da, db))
 }
 
-// TODO
-// test line segment intersect
-fn handle_coplaner(
-    tri1: &[DVec3; 3],
-    tri2: &[DVec3; 3],
-    tri2_plane_normal: DVec3,
-) -> TriTriIntersectStatus {
-    let mat = DMat3::from_cols(tri2[0] - tri2[1], tri2[0] - tri2[2], tri2_plane_normal);
-    
-    let intersect_points = vec![IPoint {
-        p: todo!(),
-        t1: todo!(),
-        t2: todo!(),
-    }];
-    TriTriIntersectStatus::Coplanar(intersect_points)
+/// generalized winding number of a closed triangle mesh at point p,
+/// close to 1 inside the mesh and close to 0 outside
+///
+/// reference: https://www.dgp.toronto.edu/projects/fast-winding-numbers/
+pub(crate) fn winding_number<I>(p: DVec3, triangles: I) -> f64
+where
+    I: IntoIterator<Item = [DVec3; 3]>,
+{
+    let mut sum = 0.;
+    for tri in triangles {
+        sum += solid_angle(p, tri);
+    }
+    sum / (4. * PI)
+}
+
+/// signed solid angle of a triangle viewed from p
+///
+/// reference: Van Oosterom A, Strackee J. The Solid Angle of a Plane Triangle.
+pub(crate) fn solid_angle(p: DVec3, tri: [DVec3; 3]) -> f64 {
+    let a = tri[0] - p;
+    let b = tri[1] - p;
+    let c = tri[2] - p;
+    let (la, lb, lc) = (a.length(), b.length(), c.length());
+    let num = a.dot(b.cross(c));
+    let den = la * lb * lc + a.dot(b) * lc + b.dot(c) * la + c.dot(a) * lb;
+    2. * num.atan2(den)
+}
+
+/// split a planar polygon into triangles by ear clipping on the plane of its newell normal.
+/// the triangles index `points` and keep the winding of the polygon,
+/// none when the polygon has no area
+pub(crate) fn triangulate_polygon(points: &[DVec3]) -> Option<Vec<[usize; 3]>> {
+    let n = points.len();
+    if n < 3 {
+        return None;
+    }
+    let normal = (0..n).fold(DVec3::ZERO, |normal, i| {
+        normal + points[i].cross(points[(i + 1) % n])
+    });
+    let extent = points
+        .iter()
+        .fold(0f64, |extent, p| extent.max((*p - points[0]).length()));
+    if normal.length() <= 1e-12 * extent * extent {
+        return None;
+    }
+    let (u, v) = normal.normalize().any_orthonormal_pair();
+    let polygon = points
+        .iter()
+        .map(|p| DVec2::new(p.dot(u), p.dot(v)))
+        .collect::<Vec<_>>();
+    Some(ear_clip(&polygon))
+}
+
+/// constrained delaunay triangulation of a polygon with holes, loops in either orientation.
+/// the triangles are counter clockwise and index the outline followed by the holes,
+/// none when an edge of the loops could not be recovered
+pub(crate) fn triangulate_with_holes(
+    outline: &[DVec2],
+    holes: &[Vec<DVec2>],
+) -> Option<Vec<[usize; 3]>> {
+    let loops = std::iter::once(outline).chain(holes.iter().map(|h| h.as_slice()));
+    let mut points = vec![];
+    let mut edges = vec![];
+    for polygon in loops {
+        let start = points.len();
+        let n = polygon.len();
+        points.extend_from_slice(polygon);
+        edges.extend((0..n).map(|i| (start + i, start + (i + 1) % n)));
+    }
+    if points.len() < 3 {
+        return Some(vec![]);
+    }
+    let extent = points
+        .iter()
+        .fold(0f64, |extent, p| extent.max((*p - points[0]).length()));
+    let mut cdt = Cdt::new(&points, 1e-12 * extent);
+    for (a, b) in edges {
+        if !cdt.insert_constraint(a, b) {
+            return None;
+        }
+    }
+    cdt.restore_delaunay();
+    // inside the outline and outside the holes
+    Some(
+        cdt.triangles_with_depth()
+            .into_iter()
+            .filter(|(_, depth)| depth % 2 == 1)
+            .map(|(triangle, _)| triangle)
+            .collect(),
+    )
+}
+
+/// q lies in the interior angle at p of a counter clockwise polygon going prev, p, next
+fn in_corner(prev: DVec2, p: DVec2, next: DVec2, q: DVec2) -> bool {
+    let left_of_prev = (p - prev).perp_dot(q - p) >= 0.;
+    let left_of_next = (next - p).perp_dot(q - p) >= 0.;
+    if (p - prev).perp_dot(next - p) >= 0. {
+        left_of_prev && left_of_next
+    } else {
+        left_of_prev || left_of_next
+    }
+}
+
+/// ear clipping of a polygon with holes, loops in either orientation. every hole is joined to the
+/// outline by a bridge from its rightmost vertex to a visible vertex on its right (Eberly 2002).
+/// the triangles are counter clockwise and index the outline followed by the holes
+pub(crate) fn ear_clip_with_holes(outline: &[DVec2], holes: &[Vec<DVec2>]) -> Vec<[usize; 3]> {
+    let mut points = outline.to_vec();
+    let ccw = |polygon: &[DVec2]| {
+        (0..polygon.len()).fold(0., |area, i| {
+            area + polygon[i].perp_dot(polygon[(i + 1) % polygon.len()])
+        }) >= 0.
+    };
+    // the merged polygon goes counter clockwise around the outline and clockwise around holes
+    let mut merged = (0..outline.len()).collect::<Vec<_>>();
+    if !ccw(outline) {
+        merged.reverse();
+    }
+    let mut loops = vec![];
+    for hole in holes.iter().filter(|h| h.len() >= 3) {
+        let start = points.len();
+        points.extend_from_slice(hole);
+        let mut indices = (start..points.len()).collect::<Vec<_>>();
+        if ccw(hole) {
+            indices.reverse();
+        }
+        loops.push(indices);
+    }
+    // holes further right are bridged first, so their bridges can not cross those of later holes
+    let rightmost = |indices: &[usize]| {
+        (0..indices.len())
+            .max_by(|&i, &j| {
+                let (p, q) = (points[indices[i]], points[indices[j]]);
+                p.x.total_cmp(&q.x).then(q.y.total_cmp(&p.y))
+            })
+            .unwrap()
+    };
+    loops.sort_by(|a, b| {
+        let (p, q) = (points[a[rightmost(a)]], points[b[rightmost(b)]]);
+        q.x.total_cmp(&p.x)
+    });
+
+    for hole in loops {
+        let k = rightmost(&hole);
+        let m = points[hole[k]];
+        let n = merged.len();
+        let at = |i: usize| points[merged[i % n]];
+        // closest edge crossed by the ray from m along +x, and the crossing
+        let mut hit: Option<(usize, f64)> = None;
+        for i in 0..n {
+            let (a, b) = (at(i), at(i + 1));
+            if (a.y <= m.y) == (b.y <= m.y) {
+                continue;
+            }
+            let x = a.x + (m.y - a.y) * (b.x - a.x) / (b.y - a.y);
+            if x >= m.x && hit.is_none_or(|(_, best)| x < best) {
+                hit = Some((i, x));
+            }
+        }
+        let Some((edge, x)) = hit else {
+            // the hole is not inside the outline
+            continue;
+        };
+        let crossing = DVec2::new(x, m.y);
+        let mut p = if at(edge).x > at(edge + 1).x {
+            edge % n
+        } else {
+            (edge + 1) % n
+        };
+        // a vertex inside the triangle m, crossing, p would hide p, take the one closest in angle
+        // to the ray instead
+        if points[merged[p]] != crossing {
+            let corner = points[merged[p]];
+            let sign = (crossing - m).perp_dot(corner - m).signum();
+            let inside = |q: DVec2| {
+                sign * (crossing - m).perp_dot(q - m) >= 0.
+                    && sign * (corner - crossing).perp_dot(q - crossing) >= 0.
+                    && sign * (m - corner).perp_dot(q - corner) >= 0.
+            };
+            let angle = |q: DVec2| {
+                let d = q - m;
+                (d.y.abs() / d.length(), d.length_squared())
+            };
+            for i in 0..n {
+                let q = at(i);
+                if q != corner && q.x >= m.x && inside(q) {
+                    let best = points[merged[p]];
+                    if angle(q).partial_cmp(&angle(best)) == Some(std::cmp::Ordering::Less) {
+                        p = i;
+                    }
+                }
+            }
+        }
+        // a vertex already used by a bridge appears twice, take the copy whose corner faces m
+        let target = points[merged[p]];
+        if let Some(i) =
+            (0..n).find(|&i| at(i) == target && in_corner(at(i + n - 1), at(i), at(i + 1), m))
+        {
+            p = i;
+        }
+        let mut bridged = Vec::with_capacity(n + hole.len() + 2);
+        bridged.extend_from_slice(&merged[..=p]);
+        bridged.extend((0..=hole.len()).map(|i| hole[(k + i) % hole.len()]));
+        bridged.extend_from_slice(&merged[p..]);
+        merged = bridged;
+    }
+
+    let polygon = merged.iter().map(|&i| points[i]).collect::<Vec<_>>();
+    ear_clip(&polygon)
+        .into_iter()
+        .map(|t| t.map(|i| merged[i]))
+        .collect()
+}
+
+/// ear clipping of a simple polygon in either orientation, the triangles keep its winding.
+/// self intersecting polygons still give n - 2 triangles, but they may overlap
+pub(crate) fn ear_clip(polygon: &[DVec2]) -> Vec<[usize; 3]> {
+    let n = polygon.len();
+    if n < 3 {
+        return vec![];
+    }
+    let area = (0..n).fold(0., |area, i| {
+        area + polygon[i].perp_dot(polygon[(i + 1) % n])
+    });
+    // positive for convex corners
+    let sign = if area < 0. { -1. } else { 1. };
+    let corner = |a: usize, b: usize, c: usize| {
+        sign * (polygon[b] - polygon[a]).perp_dot(polygon[c] - polygon[a])
+    };
+    let inside = |a: usize, b: usize, c: usize, p: usize| {
+        corner(a, b, p) >= 0. && corner(b, c, p) >= 0. && corner(c, a, p) >= 0.
+    };
+
+    let mut remaining = (0..n).collect::<Vec<_>>();
+    let mut triangles = Vec::with_capacity(n - 2);
+    while remaining.len() > 3 {
+        let m = remaining.len();
+        let around = |i: usize| {
+            (
+                remaining[(i + m - 1) % m],
+                remaining[i],
+                remaining[(i + 1) % m],
+            )
+        };
+        let is_ear = |i: usize| {
+            let (a, b, c) = around(i);
+            corner(a, b, c) > 0.
+                && remaining.iter().all(|&p| {
+                    p == a
+                        || p == b
+                        || p == c
+                        || polygon[p] == polygon[a]
+                        || polygon[p] == polygon[b]
+                        || polygon[p] == polygon[c]
+                        || !inside(a, b, c, p)
+                })
+        };
+        // without an ear the polygon is not simple, cut the most convex corner
+        let i = (0..m).find(|&i| is_ear(i)).unwrap_or_else(|| {
+            (0..m)
+                .max_by(|&i, &j| {
+                    let (a, b, c) = around(i);
+                    let (d, e, f) = around(j);
+                    corner(a, b, c).total_cmp(&corner(d, e, f))
+                })
+                .unwrap()
+        });
+        let (a, b, c) = around(i);
+        triangles.push([a, b, c]);
+        remaining.remove(i);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
 }
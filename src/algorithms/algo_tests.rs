@@ -1,2 +1,448 @@
+use glam::{DVec2, DVec3};
+use rand::{distributions::Uniform, prelude::Distribution};
+
+use super::{
+    ear_clip, locate_on_triangle, tri_tri_intersect, triangulate_polygon, winding_number, IPoint,
+    IntersectTopo, TriTriIntersectStatus,
+};
+
+const ERR: f64 = 1e-9;
+
+fn tri(a: [f64; 3], b: [f64; 3], c: [f64; 3]) -> [DVec3; 3] {
+    [DVec3::from(a), DVec3::from(b), DVec3::from(c)]
+}
+
+/// triangle on z = 0 plane
+fn base() -> [DVec3; 3] {
+    tri([0., 0., 0.], [2., 0., 0.], [0., 2., 0.])
+}
+
+fn segment(status: TriTriIntersectStatus) -> (IPoint, IPoint) {
+    match status {
+        TriTriIntersectStatus::Intersect(p, Some(q)) => (p, q),
+        s => panic!("expect a segment, got {:?}", s),
+    }
+}
+
+fn point(status: TriTriIntersectStatus) -> IPoint {
+    match status {
+        TriTriIntersectStatus::Intersect(p, None) => p,
+        s => panic!("expect a point, got {:?}", s),
+    }
+}
+
+fn polygon(status: TriTriIntersectStatus) -> Vec<IPoint> {
+    match status {
+        TriTriIntersectStatus::Coplanar(points) => points,
+        s => panic!("expect a polygon, got {:?}", s),
+    }
+}
+
+fn is_not_intersect(status: TriTriIntersectStatus) -> bool {
+    matches!(status, TriTriIntersectStatus::NotIntersect)
+}
+
+/// find the point at position p, the order of the returned points is not specified
+fn find(points: &[IPoint], p: [f64; 3]) -> IPoint {
+    *points
+        .iter()
+        .find(|ip| ip.p.distance(DVec3::from(p)) < 1e-9)
+        .unwrap_or_else(|| panic!("no point at {:?} in {:?}", p, points))
+}
+
 #[test]
-fn test_tri_tri_intersect() {}
+fn test_tri_tri_intersect() {
+    // tri2 stands on tri1 and crosses it through the interior
+    let tri2 = tri([0.5, 0.5, -1.], [0.5, 0.5, 1.], [1., 0.5, 1.]);
+    let (p, q) = segment(tri_tri_intersect(base(), tri2, ERR));
+    let points = [p, q];
+    let a = find(&points, [0.5, 0.5, 0.]);
+    assert_eq!(a.t1, IntersectTopo::Face);
+    assert_eq!(a.t2, IntersectTopo::Edge(0, 1));
+    let b = find(&points, [0.75, 0.5, 0.]);
+    assert_eq!(b.t1, IntersectTopo::Face);
+    assert_eq!(b.t2, IntersectTopo::Edge(0, 2));
+}
+
+#[test]
+fn test_aabb_rejection() {
+    let tri2 = tri([5., 5., -1.], [5., 6., 1.], [6., 5., 1.]);
+    assert!(is_not_intersect(tri_tri_intersect(base(), tri2, ERR)));
+}
+
+#[test]
+fn test_plane_rejection() {
+    // bounding boxes overlap but tri2 lies above the plane of tri1
+    let tri2 = tri([0., 0., 0.5], [2., 2., 0.1], [0., 2., 1.]);
+    assert!(is_not_intersect(tri_tri_intersect(base(), tri2, ERR)));
+    // tri2 crosses the plane of tri1 outside of tri1
+    let tri2 = tri([1.5, 1.5, -1.], [1.5, 1.5, 1.], [3., 3., 0.]);
+    assert!(is_not_intersect(tri_tri_intersect(base(), tri2, ERR)));
+}
+
+#[test]
+fn test_crossing_edges() {
+    // both triangles cross the interior of the other one, the segment ends on one edge of each
+    let tri2 = tri([0.5, -1., 0.5], [0.5, 1., 0.5], [0.5, 0., -1.5]);
+    let (p, q) = segment(tri_tri_intersect(base(), tri2, ERR));
+    let points = [p, q];
+    let a = find(&points, [0.5, 0., 0.]);
+    assert_eq!(a.t1, IntersectTopo::Edge(0, 1));
+    assert_eq!(a.t2, IntersectTopo::Face);
+    let b = find(&points, [0.5, 0.75, 0.]);
+    assert_eq!(b.t1, IntersectTopo::Face);
+    assert_eq!(b.t2, IntersectTopo::Edge(1, 2));
+}
+
+#[test]
+fn test_segment_through_vertex() {
+    // tri2 crosses tri1 from vertex 0 to the middle of edge (1, 2)
+    let tri2 = tri([-1., -1., -1.], [3., 3., -1.], [1., 1., 1.]);
+    let (p, q) = segment(tri_tri_intersect(base(), tri2, ERR));
+    let points = [p, q];
+    let a = find(&points, [0., 0., 0.]);
+    assert_eq!(a.t1, IntersectTopo::Vertex(0));
+    assert_eq!(a.t2, IntersectTopo::Edge(0, 2));
+    let b = find(&points, [1., 1., 0.]);
+    assert_eq!(b.t1, IntersectTopo::Edge(1, 2));
+    assert_eq!(b.t2, IntersectTopo::Face);
+}
+
+#[test]
+fn test_vertex_on_face() {
+    let tri2 = tri([0.5, 0.5, 0.], [0.5, 1., 1.], [1., 0.5, 1.]);
+    let p = point(tri_tri_intersect(base(), tri2, ERR));
+    assert_eq!(p.p, DVec3::new(0.5, 0.5, 0.));
+    assert_eq!(p.t1, IntersectTopo::Face);
+    assert_eq!(p.t2, IntersectTopo::Vertex(0));
+}
+
+#[test]
+fn test_vertex_on_edge() {
+    let tri2 = tri([1., 0., 1.], [1., 0., 0.], [1., -1., 1.]);
+    let p = point(tri_tri_intersect(base(), tri2, ERR));
+    assert_eq!(p.p, DVec3::new(1., 0., 0.));
+    assert_eq!(p.t1, IntersectTopo::Edge(0, 1));
+    assert_eq!(p.t2, IntersectTopo::Vertex(1));
+}
+
+#[test]
+fn test_vertex_on_vertex() {
+    let tri2 = tri([2., 0., 0.], [3., 0., 1.], [3., 1., 1.]);
+    let p = point(tri_tri_intersect(base(), tri2, ERR));
+    assert_eq!(p.p, DVec3::new(2., 0., 0.));
+    assert_eq!(p.t1, IntersectTopo::Vertex(1));
+    assert_eq!(p.t2, IntersectTopo::Vertex(0));
+}
+
+#[test]
+fn test_edge_touch_edge() {
+    // edge (0, 1) of tri2 lies on the plane of tri1, from (1, 0, 0) to (1, 1, 0)
+    let tri2 = tri([1., 0., 0.], [1., 1., 0.], [1., 0., 1.]);
+    let (p, q) = segment(tri_tri_intersect(base(), tri2, ERR));
+    let points = [p, q];
+    let a = find(&points, [1., 0., 0.]);
+    assert_eq!(a.t1, IntersectTopo::Edge(0, 1));
+    assert_eq!(a.t2, IntersectTopo::Vertex(0));
+    let b = find(&points, [1., 1., 0.]);
+    assert_eq!(b.t1, IntersectTopo::Edge(1, 2));
+    assert_eq!(b.t2, IntersectTopo::Vertex(1));
+
+    // a single touching point between two edges
+    let tri2 = tri([1., -1., 1.], [1., 1., -1.], [1., -2., -1.]);
+    let p = point(tri_tri_intersect(base(), tri2, ERR));
+    assert_eq!(p.p, DVec3::new(1., 0., 0.));
+    assert_eq!(p.t1, IntersectTopo::Edge(0, 1));
+    assert_eq!(p.t2, IntersectTopo::Edge(0, 1));
+}
+
+#[test]
+fn test_edge_on_plane() {
+    // edge (0, 1) of tri2 lies on the plane of tri1 and crosses it
+    let tri2 = tri([-1., 0.5, 0.], [3., 0.5, 0.], [1., 0.5, 1.]);
+    let (p, q) = segment(tri_tri_intersect(base(), tri2, ERR));
+    let points = [p, q];
+    let a = find(&points, [0., 0.5, 0.]);
+    assert_eq!(a.t1, IntersectTopo::Edge(0, 2));
+    assert_eq!(a.t2, IntersectTopo::Edge(0, 1));
+    let b = find(&points, [1.5, 0.5, 0.]);
+    assert_eq!(b.t1, IntersectTopo::Edge(1, 2));
+    assert_eq!(b.t2, IntersectTopo::Edge(0, 1));
+
+    // edge (1, 2) of tri2 lies inside tri1
+    let tri2 = tri([0.5, 0.5, 1.], [0.5, 0.5, 0.], [1., 0.5, 0.]);
+    let (p, q) = segment(tri_tri_intersect(base(), tri2, ERR));
+    let points = [p, q];
+    assert_eq!(find(&points, [0.5, 0.5, 0.]).t2, IntersectTopo::Vertex(1));
+    assert_eq!(find(&points, [1., 0.5, 0.]).t2, IntersectTopo::Vertex(2));
+    assert!(points.iter().all(|p| p.t1 == IntersectTopo::Face));
+}
+
+#[test]
+fn test_shared_edge() {
+    // two faces of a closed mesh sharing edge (0, 1) only touch along that edge
+    let tri2 = tri([2., 0., 0.], [0., 0., 0.], [1., 0., 1.]);
+    let (p, q) = segment(tri_tri_intersect(base(), tri2, ERR));
+    let points = [p, q];
+    let a = find(&points, [0., 0., 0.]);
+    assert_eq!(a.t1, IntersectTopo::Vertex(0));
+    assert_eq!(a.t2, IntersectTopo::Vertex(1));
+    let b = find(&points, [2., 0., 0.]);
+    assert_eq!(b.t1, IntersectTopo::Vertex(1));
+    assert_eq!(b.t2, IntersectTopo::Vertex(0));
+}
+
+#[test]
+fn test_coplanar_disjoint() {
+    let tri2 = tri([1.5, 1.5, 0.], [3., 1.5, 0.], [1.5, 3., 0.]);
+    assert!(is_not_intersect(tri_tri_intersect(base(), tri2, ERR)));
+}
+
+#[test]
+fn test_coplanar_contain() {
+    let tri2 = tri([0.2, 0.2, 0.], [1., 0.2, 0.], [0.2, 1., 0.]);
+    let points = polygon(tri_tri_intersect(base(), tri2, ERR));
+    assert_eq!(points.len(), 3);
+    assert!(points.iter().all(|p| p.t1 == IntersectTopo::Face));
+    assert_eq!(find(&points, [1., 0.2, 0.]).t2, IntersectTopo::Vertex(1));
+
+    // the other way around
+    let points = polygon(tri_tri_intersect(tri2, base(), ERR));
+    assert_eq!(points.len(), 3);
+    assert!(points.iter().all(|p| p.t2 == IntersectTopo::Face));
+}
+
+#[test]
+fn test_coplanar_star() {
+    // star of david, the overlap is a hexagon made of edge crossings only
+    let tri1 = tri([0., 0., 0.], [3., 0., 0.], [1.5, 3., 0.]);
+    let tri2 = tri([0., 2., 0.], [1.5, -1., 0.], [3., 2., 0.]);
+    let points = polygon(tri_tri_intersect(tri1, tri2, ERR));
+    assert_eq!(points.len(), 6);
+    for p in points.iter() {
+        assert!(matches!(p.t1, IntersectTopo::Edge(_, _)));
+        assert!(matches!(p.t2, IntersectTopo::Edge(_, _)));
+    }
+    // polygon is sorted, consecutive points share an edge of tri1 or tri2
+    for i in 0..6 {
+        let (a, b) = (points[i], points[(i + 1) % 6]);
+        assert!(a.t1 == b.t1 || a.t2 == b.t2);
+    }
+}
+
+#[test]
+fn test_coplanar_shared_edge() {
+    // neighbour triangle on the same plane, reversed orientation of the shared edge
+    let tri2 = tri([2., 0., 0.], [0., 2., 0.], [2., 2., 0.]);
+    let points = polygon(tri_tri_intersect(base(), tri2, ERR));
+    assert_eq!(points.len(), 2);
+    let a = find(&points, [2., 0., 0.]);
+    assert_eq!(a.t1, IntersectTopo::Vertex(1));
+    assert_eq!(a.t2, IntersectTopo::Vertex(0));
+    let b = find(&points, [0., 2., 0.]);
+    assert_eq!(b.t1, IntersectTopo::Vertex(2));
+    assert_eq!(b.t2, IntersectTopo::Vertex(1));
+}
+
+#[test]
+fn test_coplanar_identical() {
+    let points = polygon(tri_tri_intersect(base(), base(), ERR));
+    assert_eq!(points.len(), 3);
+    for p in points.iter() {
+        assert!(matches!(p.t1, IntersectTopo::Vertex(_)));
+        assert_eq!(p.t1, p.t2);
+    }
+    // opposite orientation is still coplanar
+    let [a, b, c] = base();
+    let points = polygon(tri_tri_intersect(base(), [a, c, b], ERR));
+    assert_eq!(points.len(), 3);
+}
+
+#[test]
+fn test_degenerated_triangle() {
+    let line = tri([0., 0., -1.], [1., 1., 1.], [0.5, 0.5, 0.]);
+    assert!(is_not_intersect(tri_tri_intersect(base(), line, ERR)));
+    assert!(is_not_intersect(tri_tri_intersect(line, base(), ERR)));
+}
+
+#[test]
+fn test_tolerance() {
+    // vertex slightly above the plane is snapped to it
+    let tri2 = tri([0.5, 0.5, 1e-12], [0.5, 1., 1.], [1., 0.5, 1.]);
+    let p = point(tri_tri_intersect(base(), tri2, ERR));
+    assert_eq!(p.t1, IntersectTopo::Face);
+    assert_eq!(p.t2, IntersectTopo::Vertex(0));
+    assert!(is_not_intersect(tri_tri_intersect(base(), tri2, 1e-13)));
+}
+
+#[test]
+fn test_symmetric() {
+    let cases = [
+        tri([0.5, 0.5, -1.], [0.5, 0.5, 1.], [1., 0.5, 1.]),
+        tri([0.5, -1., 0.5], [0.5, 1., 0.5], [0.5, 0., -1.5]),
+        tri([-1., -1., -1.], [3., 3., -1.], [1., 1., 1.]),
+        tri([1., 0., 1.], [1., 0., 0.], [1., -1., 1.]),
+        tri([-1., 0.5, 0.], [3., 0.5, 0.], [1., 0.5, 1.]),
+    ];
+    for tri2 in cases {
+        let (p, q) = match tri_tri_intersect(base(), tri2, ERR) {
+            TriTriIntersectStatus::Intersect(p, q) => (p, q),
+            s => panic!("{:?}", s),
+        };
+        let (r, s) = match tri_tri_intersect(tri2, base(), ERR) {
+            TriTriIntersectStatus::Intersect(r, s) => (r, s),
+            s => panic!("{:?}", s),
+        };
+        assert_eq!(q.is_some(), s.is_some());
+        let swapped = std::iter::once(r).chain(s).collect::<Vec<_>>();
+        for a in std::iter::once(p).chain(q) {
+            let b = find(&swapped, a.p.to_array());
+            assert_eq!(a.t1, b.t2);
+            assert_eq!(a.t2, b.t1);
+        }
+    }
+}
+
+#[test]
+fn test_shared_edge_consistency() {
+    // two triangles sharing the edge (a, b) get exactly the same point on it
+    let a = DVec3::new(0.1, -0.3, -0.7);
+    let b = DVec3::new(0.3, 0.2, 0.9);
+    let left = [a, b, DVec3::new(-1., 0.1, 0.2)];
+    let right = [b, a, DVec3::new(1., 0.3, 0.1)];
+    let other = tri([-2., -2., 0.13], [2., -2., 0.17], [0., 2., 0.11]);
+    let (p1, q1) = segment(tri_tri_intersect(left, other, ERR));
+    let (p2, q2) = segment(tri_tri_intersect(right, other, ERR));
+    let on_edge = |p: IPoint, q: IPoint| {
+        if matches!(p.t1, IntersectTopo::Edge(0, 1)) {
+            p
+        } else {
+            q
+        }
+    };
+    assert_eq!(on_edge(p1, q1).p, on_edge(p2, q2).p);
+}
+
+#[test]
+fn test_random() {
+    // every intersection point must lie on both triangles
+    let mut rng = rand::thread_rng();
+    let dist = Uniform::new(-1., 1.);
+    let mut random_tri = || {
+        [(); 3].map(|_| {
+            DVec3::new(
+                dist.sample(&mut rng),
+                dist.sample(&mut rng),
+                dist.sample(&mut rng),
+            )
+        })
+    };
+    let normal = |t: &[DVec3; 3]| (t[1] - t[0]).cross(t[2] - t[0]).normalize();
+    let mut count = 0;
+    for _ in 0..2000 {
+        let (tri1, tri2) = (random_tri(), random_tri());
+        let points = match tri_tri_intersect(tri1, tri2, ERR) {
+            TriTriIntersectStatus::NotIntersect => continue,
+            TriTriIntersectStatus::Intersect(p, q) => std::iter::once(p).chain(q).collect(),
+            TriTriIntersectStatus::Coplanar(points) => points,
+        };
+        count += 1;
+        for p in points {
+            assert!(normal(&tri1).dot(p.p - tri1[0]).abs() < 1e-6);
+            assert!(normal(&tri2).dot(p.p - tri2[0]).abs() < 1e-6);
+            assert_eq!(
+                locate_on_triangle(&tri1, normal(&tri1), p.p, 1e-6),
+                Some(p.t1)
+            );
+            assert_eq!(
+                locate_on_triangle(&tri2, normal(&tri2), p.p, 1e-6),
+                Some(p.t2)
+            );
+        }
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn test_winding_number() {
+    // tetrahedron with outward normals
+    let v = [
+        DVec3::new(0., 0., 0.),
+        DVec3::new(1., 0., 0.),
+        DVec3::new(0., 1., 0.),
+        DVec3::new(0., 0., 1.),
+    ];
+    let triangles = [[0, 2, 1], [0, 1, 3], [0, 3, 2], [1, 2, 3]].map(|t| t.map(|i| v[i]));
+    let inside = winding_number(DVec3::splat(0.1), triangles);
+    assert!((inside - 1.).abs() < 1e-9);
+    let outside = winding_number(DVec3::splat(1.), triangles);
+    assert!(outside.abs() < 1e-9);
+}
+
+fn signed_area(polygon: &[DVec2]) -> f64 {
+    (0..polygon.len())
+        .map(|i| polygon[i].perp_dot(polygon[(i + 1) % polygon.len()]))
+        .sum::<f64>()
+        / 2.
+}
+
+#[test]
+fn test_ear_clip() {
+    // a comb with three teeth, counter-clockwise
+    let mut comb = [
+        [0., 0.],
+        [5., 0.],
+        [5., 3.],
+        [4., 3.],
+        [4., 1.],
+        [3., 1.],
+        [3., 3.],
+        [2., 3.],
+        [2., 1.],
+        [1., 1.],
+        [1., 3.],
+        [0., 3.],
+    ]
+    .map(DVec2::from)
+    .to_vec();
+    for _ in 0..2 {
+        let triangles = ear_clip(&comb);
+        assert_eq!(triangles.len(), comb.len() - 2);
+        let area = signed_area(&comb);
+        let mut sum = 0.;
+        for t in triangles.iter() {
+            let triangle = t.map(|i| comb[i]);
+            // same winding as the polygon, no flipped triangle
+            assert!(signed_area(&triangle) * area.signum() > 0.);
+            sum += signed_area(&triangle);
+        }
+        assert!((sum - area).abs() < 1e-9);
+        comb.reverse();
+    }
+}
+
+#[test]
+fn test_triangulate_polygon() {
+    // a non-convex pentagon on a tilted plane
+    let (u, v) = DVec3::new(1., 2., 3.).normalize().any_orthonormal_pair();
+    let polygon = [[0., 0.], [2., 0.], [2., 2.], [1., 0.5], [0., 2.]]
+        .map(|[x, y]| u * x + v * y + DVec3::new(1., 1., 1.));
+    let triangles = triangulate_polygon(&polygon).unwrap();
+    assert_eq!(triangles.len(), 3);
+    let normal =
+        |t: &[usize; 3]| (polygon[t[1]] - polygon[t[0]]).cross(polygon[t[2]] - polygon[t[0]]);
+    let area = triangles
+        .iter()
+        .map(|t| normal(t).length() / 2.)
+        .sum::<f64>();
+    assert!((area - 2.5).abs() < 1e-9);
+    let expected = u.cross(v);
+    for t in triangles.iter() {
+        assert!(normal(t).normalize().dot(expected) > 0.99);
+    }
+    // the corner at [1, 0.5] is reflex and must not be cut off
+    assert!(!triangles.contains(&[2, 3, 4]));
+
+    let collinear = [0., 1., 2., 3.].map(|x| DVec3::new(x, x, 0.));
+    assert!(triangulate_polygon(&collinear).is_none());
+}
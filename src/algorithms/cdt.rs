@@ -0,0 +1,412 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use glam::DVec2;
+
+// give up flipping after this many steps per vertex, only reachable with broken input
+const MAX_FLIPS_PER_VERTEX: usize = 1000;
+
+/// incremental constrained delaunay triangulation in 2d
+///
+/// points are inserted into a super triangle with lawson flips, constraints are recovered by
+/// flipping the edges crossing them (Sloan 1993). triangles are kept counter clockwise.
+pub(crate) struct Cdt {
+    points: Vec<DVec2>,
+    /// number of input points, the 3 super triangle vertices follow them
+    len: usize,
+    /// input point index -> vertex index, duplicated points are merged
+    vertex_map: Vec<usize>,
+    triangles: Vec<[usize; 3]>,
+    /// directed edge -> triangle having this edge in counter clockwise order
+    edges: HashMap<(usize, usize), usize>,
+    /// undirected constrained edges, stored as (small, big)
+    constraints: HashSet<(usize, usize)>,
+    err: f64,
+    last: usize,
+}
+
+impl Cdt {
+    pub(crate) fn new(points: &[DVec2], err: f64) -> Self {
+        let len = points.len();
+        let (min, max) = points.iter().fold(
+            (DVec2::splat(f64::MAX), DVec2::splat(f64::MIN)),
+            |(min, max), p| (min.min(*p), max.max(*p)),
+        );
+        let (center, size) = if len == 0 {
+            (DVec2::ZERO, 1.)
+        } else {
+            (
+                (min + max) / 2.,
+                (max - min).max_element().max(err).max(f64::EPSILON),
+            )
+        };
+        let mut all = points.to_vec();
+        all.push(center + DVec2::new(-20. * size, -10. * size));
+        all.push(center + DVec2::new(20. * size, -10. * size));
+        all.push(center + DVec2::new(0., 20. * size));
+
+        let mut cdt = Cdt {
+            points: all,
+            len,
+            vertex_map: (0..len).collect(),
+            triangles: vec![],
+            edges: HashMap::new(),
+            constraints: HashSet::new(),
+            err,
+            last: 0,
+        };
+        cdt.push_triangle([len, len + 1, len + 2]);
+        for i in 0..len {
+            cdt.insert_point(i);
+        }
+        cdt
+    }
+
+    /// vertex index of an input point, differs from the input index for merged points
+    pub(crate) fn vertex(&self, i: usize) -> usize {
+        self.vertex_map[i]
+    }
+
+    fn orient(&self, a: usize, b: usize, p: usize) -> f64 {
+        orient(self.points[a], self.points[b], self.points[p])
+    }
+
+    fn push_triangle(&mut self, t: [usize; 3]) -> usize {
+        let index = self.triangles.len();
+        self.triangles.push(t);
+        for k in 0..3 {
+            self.edges.insert((t[k], t[(k + 1) % 3]), index);
+        }
+        index
+    }
+
+    fn set_triangle(&mut self, index: usize, t: [usize; 3]) {
+        let old = self.triangles[index];
+        for k in 0..3 {
+            let e = (old[k], old[(k + 1) % 3]);
+            if self.edges.get(&e) == Some(&index) {
+                self.edges.remove(&e);
+            }
+        }
+        self.triangles[index] = t;
+        for k in 0..3 {
+            self.edges.insert((t[k], t[(k + 1) % 3]), index);
+        }
+    }
+
+    /// the vertex of triangle t which is not on edge (a, b)
+    fn third(&self, t: usize, a: usize, b: usize) -> usize {
+        *self.triangles[t]
+            .iter()
+            .find(|v| **v != a && **v != b)
+            .unwrap()
+    }
+
+    fn is_constrained(&self, a: usize, b: usize) -> bool {
+        self.constraints.contains(&(a.min(b), a.max(b)))
+    }
+
+    fn insert_point(&mut self, i: usize) {
+        let p = self.points[i];
+        let Some(t) = self.locate(p) else {
+            // outside of the super triangle, can not happen with finite input
+            return;
+        };
+        let tri = self.triangles[t];
+        // merge with an existing vertex
+        if let Some(v) = tri
+            .iter()
+            .find(|v| self.points[**v].distance(p) <= self.err)
+        {
+            self.vertex_map[i] = *v;
+            return;
+        }
+        // on an edge
+        for k in 0..3 {
+            let (a, b) = (tri[k], tri[(k + 1) % 3]);
+            if self.orient(a, b, i).abs() <= self.err {
+                self.split_edge(t, a, b, i);
+                return;
+            }
+        }
+        let [a, b, c] = tri;
+        self.set_triangle(t, [a, b, i]);
+        self.push_triangle([b, c, i]);
+        self.push_triangle([c, a, i]);
+        self.legalize(vec![(a, b), (b, c), (c, a)]);
+    }
+
+    /// split edge (a, b) of triangle t = [a, b, c] and the triangle on the other side at p
+    fn split_edge(&mut self, t: usize, a: usize, b: usize, p: usize) {
+        let c = self.third(t, a, b);
+        let opposite = self.edges.get(&(b, a)).copied();
+        self.set_triangle(t, [a, p, c]);
+        self.push_triangle([p, b, c]);
+        let mut stack = vec![(c, a), (b, c)];
+        if let Some(t2) = opposite {
+            let d = self.third(t2, b, a);
+            self.set_triangle(t2, [b, p, d]);
+            self.push_triangle([p, a, d]);
+            stack.push((d, b));
+            stack.push((a, d));
+        }
+        if self.constraints.remove(&(a.min(b), a.max(b))) {
+            self.constraints.insert((a.min(p), a.max(p)));
+            self.constraints.insert((p.min(b), p.max(b)));
+        }
+        self.legalize(stack);
+    }
+
+    /// find the triangle containing p by walking from the last found triangle
+    fn locate(&mut self, p: DVec2) -> Option<usize> {
+        let mut t = self.last.min(self.triangles.len() - 1);
+        let mut steps = 0;
+        'walk: while steps < self.triangles.len() {
+            steps += 1;
+            let tri = self.triangles[t];
+            for k in 0..3 {
+                let (a, b) = (tri[k], tri[(k + 1) % 3]);
+                if orient(self.points[a], self.points[b], p) < -self.err {
+                    match self.edges.get(&(b, a)) {
+                        Some(next) => {
+                            t = *next;
+                            continue 'walk;
+                        }
+                        None => break 'walk,
+                    }
+                }
+            }
+            self.last = t;
+            return Some(t);
+        }
+        // walking may cycle on nearly degenerated triangles, fall back to a full search
+        let found = (0..self.triangles.len()).find(|t| {
+            let tri = self.triangles[*t];
+            (0..3)
+                .all(|k| orient(self.points[tri[k]], self.points[tri[(k + 1) % 3]], p) >= -self.err)
+        });
+        if let Some(t) = found {
+            self.last = t;
+        }
+        found
+    }
+
+    /// edges in the stack are directed edges (a, b) of triangles [a, b, p] where p is the new point
+    fn legalize(&mut self, mut stack: Vec<(usize, usize)>) {
+        let mut flips = 0;
+        while let Some((a, b)) = stack.pop() {
+            if flips > MAX_FLIPS_PER_VERTEX {
+                break;
+            }
+            if self.is_constrained(a, b) {
+                continue;
+            }
+            let (Some(&t1), Some(&t2)) = (self.edges.get(&(a, b)), self.edges.get(&(b, a))) else {
+                continue;
+            };
+            let p = self.third(t1, a, b);
+            let q = self.third(t2, b, a);
+            if self.in_circle(a, b, p, q) {
+                self.flip(a, b);
+                flips += 1;
+                stack.push((a, q));
+                stack.push((q, b));
+            }
+        }
+    }
+
+    fn in_circle(&self, a: usize, b: usize, c: usize, d: usize) -> bool {
+        // never move super triangle edges into the hull of the input points
+        if d >= self.len || (a >= self.len && b >= self.len) {
+            return false;
+        }
+        in_circle(
+            self.points[a],
+            self.points[b],
+            self.points[c],
+            self.points[d],
+        ) > 0.
+            && convex(
+                self.points[a],
+                self.points[d],
+                self.points[b],
+                self.points[c],
+            )
+    }
+
+    /// flip edge (a, b) shared by [a, b, p] and [b, a, q] to (p, q)
+    fn flip(&mut self, a: usize, b: usize) {
+        let t1 = self.edges[&(a, b)];
+        let t2 = self.edges[&(b, a)];
+        let p = self.third(t1, a, b);
+        let q = self.third(t2, b, a);
+        self.set_triangle(t1, [a, q, p]);
+        self.set_triangle(t2, [b, p, q]);
+    }
+
+    /// force an edge between two input points, return false if the edge could not be recovered
+    pub(crate) fn insert_constraint(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.vertex(a), self.vertex(b));
+        self.insert_vertex_constraint(a, b)
+    }
+
+    fn insert_vertex_constraint(&mut self, a: usize, b: usize) -> bool {
+        if a == b {
+            return true;
+        }
+        if self.edges.contains_key(&(a, b)) || self.edges.contains_key(&(b, a)) {
+            self.constraints.insert((a.min(b), a.max(b)));
+            return true;
+        }
+        // vertices lying on the segment split it into two constraints
+        let (pa, pb) = (self.points[a], self.points[b]);
+        let ab = pb - pa;
+        let on_segment = (0..self.len).find(|v| {
+            let t = (self.points[*v] - pa).dot(ab) / ab.length_squared();
+            *v != a
+                && *v != b
+                && self.vertex_map[*v] == *v
+                && orient(pa, pb, self.points[*v]).abs() <= self.err
+                && t > 0.
+                && t < 1.
+        });
+        if let Some(v) = on_segment {
+            let left = self.insert_vertex_constraint(a, v);
+            let right = self.insert_vertex_constraint(v, b);
+            return left && right;
+        }
+
+        let crossing = |cdt: &Self, u: usize, v: usize| {
+            u != a
+                && u != b
+                && v != a
+                && v != b
+                && segments_cross(pa, pb, cdt.points[u], cdt.points[v], cdt.err)
+        };
+        let mut queue = self
+            .edges
+            .keys()
+            .filter(|(u, v)| u < v && crossing(self, *u, *v))
+            .copied()
+            .collect::<VecDeque<_>>();
+        let max_steps = MAX_FLIPS_PER_VERTEX * (queue.len() + 1);
+        let mut steps = 0;
+        while let Some((u, v)) = queue.pop_front() {
+            steps += 1;
+            if steps > max_steps {
+                return false;
+            }
+            // flipping a constraint away would lose it, the two constraints cross
+            if self.is_constrained(u, v) {
+                return false;
+            }
+            let (Some(&t1), Some(&t2)) = (self.edges.get(&(u, v)), self.edges.get(&(v, u))) else {
+                continue;
+            };
+            let p = self.third(t1, u, v);
+            let q = self.third(t2, v, u);
+            if convex(
+                self.points[u],
+                self.points[q],
+                self.points[v],
+                self.points[p],
+            ) {
+                self.flip(u, v);
+                if crossing(self, p, q) {
+                    queue.push_back((p, q));
+                }
+            } else {
+                queue.push_back((u, v));
+            }
+        }
+        self.constraints.insert((a.min(b), a.max(b)));
+        true
+    }
+
+    /// flip unconstrained edges until the triangulation is constrained delaunay
+    pub(crate) fn restore_delaunay(&mut self) {
+        let mut stack = self.edges.keys().copied().collect::<Vec<_>>();
+        let mut flips = 0;
+        let max_flips = MAX_FLIPS_PER_VERTEX * self.points.len();
+        while let Some((a, b)) = stack.pop() {
+            if flips > max_flips {
+                break;
+            }
+            if self.is_constrained(a, b) {
+                continue;
+            }
+            let (Some(&t1), Some(&t2)) = (self.edges.get(&(a, b)), self.edges.get(&(b, a))) else {
+                continue;
+            };
+            let p = self.third(t1, a, b);
+            let q = self.third(t2, b, a);
+            if self.in_circle(a, b, p, q) {
+                self.flip(a, b);
+                flips += 1;
+                stack.extend([(a, q), (q, b), (b, p), (p, a)]);
+            }
+        }
+    }
+
+    /// all triangles not touching the super triangle, with the number of constraints crossed
+    /// on the shortest way from the outside
+    pub(crate) fn triangles_with_depth(&self) -> Vec<([usize; 3], usize)> {
+        let mut depth = vec![usize::MAX; self.triangles.len()];
+        let mut queue = VecDeque::new();
+        for (t, tri) in self.triangles.iter().enumerate() {
+            if tri.iter().any(|v| *v >= self.len) {
+                depth[t] = 0;
+                queue.push_back(t);
+            }
+        }
+        // 0-1 bfs, crossing a constraint costs 1
+        while let Some(t) = queue.pop_front() {
+            let tri = self.triangles[t];
+            for k in 0..3 {
+                let (a, b) = (tri[k], tri[(k + 1) % 3]);
+                let Some(&next) = self.edges.get(&(b, a)) else {
+                    continue;
+                };
+                let cost = self.is_constrained(a, b) as usize;
+                if depth[t] + cost < depth[next] {
+                    depth[next] = depth[t] + cost;
+                    if cost == 0 {
+                        queue.push_front(next);
+                    } else {
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        self.triangles
+            .iter()
+            .zip(depth)
+            .filter(|(tri, _)| tri.iter().all(|v| *v < self.len))
+            .map(|(tri, d)| (*tri, d))
+            .collect()
+    }
+}
+
+/// twice the signed area of abp divided by the length of ab,
+/// which is the signed distance from p to the line ab, positive on the left
+fn orient(a: DVec2, b: DVec2, p: DVec2) -> f64 {
+    let ab = b - a;
+    ab.perp_dot(p - a) / ab.length()
+}
+
+fn in_circle(a: DVec2, b: DVec2, c: DVec2, d: DVec2) -> f64 {
+    let (a, b, c) = (a - d, b - d, c - d);
+    let (la, lb, lc) = (a.length_squared(), b.length_squared(), c.length_squared());
+    a.x * (b.y * lc - lb * c.y) - a.y * (b.x * lc - lb * c.x) + la * (b.x * c.y - b.y * c.x)
+}
+
+/// quad abcd is strictly convex
+fn convex(a: DVec2, b: DVec2, c: DVec2, d: DVec2) -> bool {
+    let quad = [a, b, c, d];
+    (0..4).all(|k| (quad[(k + 1) % 4] - quad[k]).perp_dot(quad[(k + 2) % 4] - quad[k]) > 0.)
+}
+
+/// segments ab and cd cross at a point inside both of them
+fn segments_cross(a: DVec2, b: DVec2, c: DVec2, d: DVec2, err: f64) -> bool {
+    let crossed = |x: f64, y: f64| (x > err && y < -err) || (x < -err && y > err);
+    crossed(orient(a, b, c), orient(a, b, d)) && crossed(orient(c, d, a), orient(c, d, b))
+}
@@ -1,8 +1,15 @@
 use std::fmt::Debug;
 
+use glam::{Mat4, Vec3};
+
 pub trait BoundingVolume<const D: usize>: Default + Copy + PartialEq + Debug {
     fn merge(&mut self, other: &Self);
     fn grow(&mut self, point: &[f32; D]);
+    /// measure of the boundary, only ratios between volumes are meaningful, 0 if empty.
+    /// volumes without a measure keep the default 0, sah splits then fall back to midpoint splits
+    fn surface_area(&self) -> f32 {
+        0.
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -68,6 +75,13 @@ impl<const D: usize> BoundingVolume<D> for Bsphere<D> {
             self.radius = radius;
         }
     }
+
+    fn surface_area(&self) -> f32 {
+        if self.radius <= 0. {
+            return 0.;
+        }
+        4. * std::f32::consts::PI * self.radius.powi(D as i32 - 1)
+    }
 }
 
 impl<const D: usize> Bsphere<D> {
@@ -78,7 +92,7 @@ impl<const D: usize> Bsphere<D> {
         }
     }
 
-    fn center_distance(&self, other: &Self) -> f32 {
+    pub(crate) fn center_distance(&self, other: &Self) -> f32 {
         self.center
             .iter()
             .zip(other.center)
@@ -263,8 +277,7 @@ impl<const D: usize> AABB<D> {
 
     pub(crate) fn intersect_aabb(&self, other: &Self, err: f32) -> bool {
         for i in 0..D {
-            if (other.max[i] - self.min[i]).abs() < err || (self.max[i] - other.min[i]).abs() < err
-            {
+            if self.max[i] < other.min[i] + err || other.max[i] < self.min[i] + err {
                 return false;
             }
         }
@@ -293,14 +306,57 @@ impl<const D: usize> AABB<D> {
     }
 }
 
+impl AABB<3> {
+    /// box around the transformed corners of this box
+    pub(crate) fn transform(&self, mat: &Mat4) -> Self {
+        // empty box stays empty
+        if (0..3).any(|i| self.min[i] > self.max[i]) {
+            return *self;
+        }
+        let mut res = AABB::new();
+        for corner in 0..8 {
+            let pick = |i: usize| {
+                if corner & (1 << i) == 0 {
+                    self.min[i]
+                } else {
+                    self.max[i]
+                }
+            };
+            let p = Vec3::new(pick(0), pick(1), pick(2));
+            res.grow(&mat.transform_point3(p).to_array());
+        }
+        res
+    }
+}
+
 impl<const D: usize> BoundingVolume<D> for AABB<D> {
     fn merge(&mut self, other: &Self) {
+        // an empty box has min > max, growing by its corners would cover everything
+        if (0..D).any(|i| other.min[i] > other.max[i]) {
+            return;
+        }
         self.grow_from_aabb(other);
     }
 
     fn grow(&mut self, point: &[f32; D]) {
         self.grow(point);
     }
+
+    fn surface_area(&self) -> f32 {
+        if (0..D).any(|i| self.min[i] > self.max[i]) {
+            return 0.;
+        }
+        // sum of the faces, perimeter in 2d
+        (0..D)
+            .map(|i| {
+                (0..D)
+                    .filter(|j| *j != i)
+                    .map(|j| self.max[j] - self.min[j])
+                    .product::<f32>()
+                    * 2.
+            })
+            .sum()
+    }
 }
 
 #[cfg(test)]
@@ -343,6 +399,68 @@ mod aabb_test {
         assert!(a.intersect_aabb(&b, 1e-7));
         assert!(b.intersect_aabb(&c, 1e-7));
         assert!(!a.intersect_aabb(&c, 1e-7));
+        // boxes far apart, and boxes overlapping by less than err
+        let d = AABB {
+            min: [5.; 3],
+            max: [6.; 3],
+        };
+        assert!(!a.intersect_aabb(&d, 1e-7));
+        assert!(!d.intersect_aabb(&a, 1e-7));
+        assert!(!a.intersect_aabb(&c, 0.5));
+        assert!(a.intersect_aabb(&b, 0.5));
+    }
+
+    #[test]
+    fn test_merge_empty() {
+        use super::BoundingVolume;
+        let a = AABB {
+            min: [0.; 3],
+            max: [1.; 3],
+        };
+        let mut b = a;
+        b.merge(&AABB::new());
+        assert_eq!(a, b);
+        let mut c = AABB::new();
+        c.merge(&a);
+        assert_eq!(a, c);
+    }
+
+    #[test]
+    fn test_surface_area() {
+        use super::BoundingVolume;
+        let a = AABB {
+            min: [0.; 3],
+            max: [1., 2., 3.],
+        };
+        assert_eq!(a.surface_area(), 22.);
+        let b = AABB {
+            min: [0.; 2],
+            max: [1., 2.],
+        };
+        assert_eq!(b.surface_area(), 6.);
+        assert_eq!(AABB::<3>::new().surface_area(), 0.);
+    }
+
+    #[test]
+    fn test_transform() {
+        let a = AABB {
+            min: [-1.; 3],
+            max: [1.; 3],
+        };
+        let mat = glam::Mat4::from_scale_rotation_translation(
+            glam::Vec3::new(2., 1., 1.),
+            glam::Quat::from_rotation_z(std::f32::consts::FRAC_PI_4),
+            glam::Vec3::new(0., 0., 3.),
+        );
+        let b = a.transform(&mat);
+        let r = 1.5 * 2f32.sqrt();
+        for i in 0..2 {
+            assert!((b.min[i] + r).abs() < 1e-5);
+            assert!((b.max[i] - r).abs() < 1e-5);
+        }
+        assert_eq!((b.min[2], b.max[2]), (2., 4.));
+        let empty = AABB::new();
+        assert_eq!(empty.transform(&mat), empty);
     }
 
     #[test]
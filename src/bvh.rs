@@ -5,13 +5,15 @@ pub mod build_options;
 
 use std::collections::VecDeque;
 
+use glam::{Mat4, Vec3};
+
 use crate::{
     bv::{BoundingVolume, AABB},
     traits::{Bounded, Intersect},
     tree::{iter_types, ChildSide, Node, Tree, TreeIterator},
 };
 
-use self::build_options::{BuildBvhOption, DepthControl, SplitMethod};
+use self::build_options::{BuildBvhOption, DepthControl, SahOption, SplitMethod};
 
 #[derive(Debug)]
 pub struct BvhNodeData<const D: usize, BV, P>
@@ -46,20 +48,29 @@ impl<const D: usize, BV: BoundingVolume<D>, P> BvhNodeData<D, BV, P> {
     }
 }
 
+/// view of a node in a [`Bvh`]
 pub struct BvhNode<'a, const D: usize, BV: BoundingVolume<D>, P> {
+    /// index of the parent node
     pub parent: usize,
+    /// depth of the node, 0 for the root
     pub depth: usize,
+    /// index of the left child
     pub left: Option<usize>,
+    /// index of the right child
     pub right: Option<usize>,
+    /// bounding volume of everything below the node
     pub bv: BV,
+    /// primitives of a leaf, none for inner nodes
     pub primitives: Option<&'a [P]>,
 }
 
 impl<'a, const D: usize, BV: BoundingVolume<D>, P> BvhNode<'a, D, BV, P> {
+    /// whether the node holds primitives
     pub fn is_leaf(&self) -> bool {
         self.primitives.is_some()
     }
 
+    /// whether the node has children
     pub fn is_node(&self) -> bool {
         !self.is_leaf()
     }
@@ -76,6 +87,7 @@ impl<'a, const D: usize, BV: BoundingVolume<D>, P> BvhNode<'a, D, BV, P> {
     }
 }
 
+/// iterator over the nodes of a [`Bvh`] with their index
 pub struct BvhIter<'a, const D: usize, BV: BoundingVolume<D>, P, IT: iter_types::IterType> {
     tree_iter: TreeIterator<'a, BvhNodeData<D, BV, P>, IT>,
 }
@@ -113,37 +125,47 @@ impl<'a, const D: usize, BV: BoundingVolume<D>, P> Iterator
     }
 }
 
+/// bounding volume hierarchy over primitives of type `P`
 #[derive(Debug)]
 pub struct Bvh<const D: usize, BV: BoundingVolume<D>, P> {
     tree: Tree<BvhNodeData<D, BV, P>>,
+    option: BuildBvhOption,
+    /// sah cost right after building, refitting is compared against it
+    build_cost: f32,
 }
 
 impl<const D: usize, BV: BoundingVolume<D>, P> Bvh<D, BV, P> {
+    /// depth of the deepest node
     pub fn max_depth(&self) -> usize {
         self.tree.max_depth
     }
 
+    /// the root node, panics on an empty tree
     pub fn get_root<'a>(&'a self) -> BvhNode<'a, D, BV, P> {
         self.get_node(0).unwrap()
     }
 
+    /// node at `index`
     pub fn get_node<'a>(&'a self, index: usize) -> Option<BvhNode<'a, D, BV, P>> {
         let node = self.tree.get_node(index)?;
         Some(BvhNode::from_node(node))
     }
 
+    /// nodes below `from` in insertion order
     pub fn iter_rand<'a>(&'a self, from: usize) -> BvhIter<'a, D, BV, P, iter_types::PushOrder> {
         BvhIter {
             tree_iter: self.tree.iter::<iter_types::PushOrder>(from),
         }
     }
 
+    /// nodes below `from`, breadth first
     pub fn iter_bfs<'a>(&'a self, from: usize) -> BvhIter<'a, D, BV, P, iter_types::Bfs> {
         BvhIter {
             tree_iter: self.tree.iter::<iter_types::Bfs>(from),
         }
     }
 
+    /// nodes below `from`, depth first
     pub fn iter_dfs<'a>(&'a self, from: usize) -> BvhIter<'a, D, BV, P, iter_types::Dfs> {
         BvhIter {
             tree_iter: self.tree.iter::<iter_types::Dfs>(from),
@@ -157,6 +179,7 @@ impl<const D: usize, BV: BoundingVolume<D>, P> Bvh<D, BV, P> {
         F: Fn(P) -> T,
     {
         let max_depth = self.tree.max_depth;
+        let (option, build_cost) = (self.option, self.build_cost);
         let data = self
             .tree
             .data
@@ -185,6 +208,8 @@ impl<const D: usize, BV: BoundingVolume<D>, P> Bvh<D, BV, P> {
             .collect::<Vec<_>>();
         Bvh {
             tree: Tree { data, max_depth },
+            option,
+            build_cost,
         }
     }
 
@@ -230,11 +255,23 @@ impl<const D: usize, BV: BoundingVolume<D>, P> Bvh<D, BV, P> {
                 tree.add_child(parent_index, side, leaf_data).unwrap();
                 continue;
             }
-            let node_data = BvhNodeData::new_node_data(aabb);
-            let current_index = tree.add_child(parent_index, side, node_data).unwrap();
             let longest_axis = helper_aabb.longest_dimension();
             let (left, right) =
                 Self::split_triangles(helper_aabb, primitives, longest_axis, option.split_method);
+            // nothing to split, or splitting does not pay off
+            if left.is_empty() || right.is_empty() {
+                let mut primitives = left;
+                primitives.extend(right);
+                tree.add_child(
+                    parent_index,
+                    side,
+                    BvhNodeData::new_leaf_data(aabb, primitives),
+                )
+                .unwrap();
+                continue;
+            }
+            let node_data = BvhNodeData::new_node_data(aabb);
+            let current_index = tree.add_child(parent_index, side, node_data).unwrap();
             let helper_left_aabb = left.iter().fold(AABB::new(), |mut aabb, v| {
                 aabb.grow(&v.center());
                 aabb
@@ -268,7 +305,13 @@ impl<const D: usize, BV: BoundingVolume<D>, P> Bvh<D, BV, P> {
                 current_index,
             ));
         }
-        Bvh { tree }
+        let mut bvh = Bvh {
+            tree,
+            option,
+            build_cost: 0.,
+        };
+        bvh.build_cost = bvh.cost();
+        bvh
     }
 
     fn split_triangles(
@@ -284,7 +327,7 @@ impl<const D: usize, BV: BoundingVolume<D>, P> Bvh<D, BV, P> {
         match split_method {
             SplitMethod::Mid => Self::split_triangles_mid(aabb, primitives, split_axis),
             SplitMethod::Average => Self::split_triangles_average(primitives, split_axis),
-            // SplitMethod::SAH => split_triangles_sah(),
+            SplitMethod::Sah(option) => Self::split_triangles_sah(aabb, primitives, option),
         }
     }
 
@@ -314,14 +357,203 @@ impl<const D: usize, BV: BoundingVolume<D>, P> Bvh<D, BV, P> {
         (primitives, right)
     }
 
-    // TODO SAH split method support later
-    // fn split_triangles_sah<'a, G: 'a + Iterator<Item = &'a [f32; D]>>() -> (Vec<(P, G)>, Vec<(P, G)>)
-    // {
-    //     todo!()
-    // }
+    /// binned sah over every axis of `aabb`, the box of the primitive centers,
+    /// everything goes to the left if no split is cheaper than a leaf
+    fn split_triangles_sah(aabb: AABB<D>, primitives: Vec<P>, option: SahOption) -> (Vec<P>, Vec<P>)
+    where
+        P: Bounded<D, BV>,
+        BV: BoundingVolume<D>,
+    {
+        let bins = option.bins.max(2);
+        let bin_of = |p: &P, axis: usize| {
+            let extent = aabb.max[axis] - aabb.min[axis];
+            let t = (p.center_at_axis(axis) - aabb.min[axis]) / extent;
+            ((t * bins as f32) as usize).min(bins - 1)
+        };
+        let parent = primitives.iter().fold(BV::default(), |mut bv, p| {
+            bv.merge(&p.bv());
+            bv
+        });
+        if parent.surface_area() <= 0. {
+            let axis = aabb.longest_dimension();
+            return Self::split_triangles_mid(aabb, primitives, axis);
+        }
+
+        // (cost, axis, first bin of the right side)
+        let mut best: Option<(f32, usize, usize)> = None;
+        for axis in 0..D {
+            if aabb.max[axis] <= aabb.min[axis] {
+                continue;
+            }
+            let mut counts = vec![0usize; bins];
+            let mut bvs = vec![BV::default(); bins];
+            for p in primitives.iter() {
+                let bin = bin_of(p, axis);
+                counts[bin] += 1;
+                bvs[bin].merge(&p.bv());
+            }
+            // sweep from the right, then from the left
+            let mut right = vec![(0., 0); bins];
+            let (mut bv, mut count) = (BV::default(), 0);
+            for bin in (1..bins).rev() {
+                bv.merge(&bvs[bin]);
+                count += counts[bin];
+                right[bin] = (bv.surface_area(), count);
+            }
+            let (mut bv, mut count) = (BV::default(), 0);
+            for bin in 1..bins {
+                bv.merge(&bvs[bin - 1]);
+                count += counts[bin - 1];
+                let (right_area, right_count) = right[bin];
+                if count == 0 || right_count == 0 {
+                    continue;
+                }
+                let cost = bv.surface_area() * count as f32 + right_area * right_count as f32;
+                if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                    best = Some((cost, axis, bin));
+                }
+            }
+        }
+
+        let Some((cost, axis, bin)) = best else {
+            return (primitives, vec![]);
+        };
+        let split_cost =
+            option.traversal_cost + option.intersection_cost * cost / parent.surface_area();
+        let leaf_cost = option.intersection_cost * primitives.len() as f32;
+        if split_cost >= leaf_cost {
+            return (primitives, vec![]);
+        }
+        primitives
+            .into_iter()
+            .partition::<Vec<_>, _>(|p| bin_of(p, axis) < bin)
+    }
 }
 
 impl<const D: usize, BV: BoundingVolume<D>, P> Bvh<D, BV, P> {
+    /// sah cost of the tree, node areas are relative to the root so scaling does not change it.
+    /// uses the costs of the sah option the tree was built with, or the default ones
+    pub fn cost(&self) -> f32 {
+        let option = match self.option.split_method {
+            SplitMethod::Sah(option) => option,
+            _ => SahOption::default(),
+        };
+        let root = match self.tree.get_node(0) {
+            Some(root) => root.data.bv.surface_area(),
+            None => return 0.,
+        };
+        if root <= 0. {
+            return 0.;
+        }
+        self.tree
+            .data
+            .iter()
+            .map(|node| {
+                let area = node.data.bv.surface_area() / root;
+                match &node.data.primitives {
+                    Some(p) => area * option.intersection_cost * p.len() as f32,
+                    None => area * option.traversal_cost,
+                }
+            })
+            .sum()
+    }
+
+    /// how many times more expensive the tree got since it was built, 1 for a fresh tree
+    pub fn degradation(&self) -> f32 {
+        if self.build_cost <= 0. {
+            return 1.;
+        }
+        self.cost() / self.build_cost
+    }
+
+    /// recompute the bounding volumes bottom-up, `f` gives the bounding volume of a primitive.
+    /// the tree structure is kept, so it only fits geometry with unchanged topology
+    pub fn refit_by<F>(&mut self, f: F)
+    where
+        F: Fn(&P) -> BV,
+    {
+        // children are always stored after their parent
+        for index in (0..self.tree.len()).rev() {
+            let node = &self.tree.data[index];
+            let bv = match &node.data.primitives {
+                Some(primitives) => primitives.iter().fold(BV::default(), |mut bv, p| {
+                    bv.merge(&f(p));
+                    bv
+                }),
+                None => [node.left, node.right].into_iter().flatten().fold(
+                    BV::default(),
+                    |mut bv, child| {
+                        bv.merge(&self.tree.data[child].data.bv);
+                        bv
+                    },
+                ),
+            };
+            self.tree.data[index].data.bv = bv;
+        }
+    }
+
+    /// refit to the current bounding volumes of the primitives
+    pub fn refit(&mut self)
+    where
+        P: Bounded<D, BV>,
+    {
+        self.refit_by(|p| p.bv());
+    }
+
+    /// build the tree again from its primitives, with the options it was first built with
+    pub fn rebuild(&mut self)
+    where
+        P: Bounded<D, BV>,
+    {
+        let primitives = self.take_primitives();
+        *self = Self::build(self.option, primitives);
+    }
+
+    pub(crate) fn primitives_mut(&mut self) -> impl Iterator<Item = &mut P> {
+        self.tree
+            .data
+            .iter_mut()
+            .filter_map(|node| node.data.primitives.as_mut())
+            .flatten()
+    }
+
+    fn take_primitives(&mut self) -> Vec<P> {
+        self.tree
+            .data
+            .iter_mut()
+            .filter_map(|node| node.data.primitives.take())
+            .flatten()
+            .collect()
+    }
+}
+
+impl<BV: BoundingVolume<3>, P> Bvh<3, BV, P> {
+    /// refit to the triangles `f` gives for the primitives. when `max_degradation` is set and
+    /// the tree got more than that many times as expensive as after building, it is rebuilt.
+    /// return true if the tree was rebuilt
+    pub fn refit_triangles<F>(&mut self, f: F, max_degradation: Option<f32>) -> bool
+    where
+        F: Fn(&P) -> [Vec3; 3],
+    {
+        self.refit_by(|p| Bounded::<3, BV>::bv(&f(p)));
+        if max_degradation.is_none_or(|max| self.degradation() <= max) {
+            return false;
+        }
+        let primitives = self
+            .take_primitives()
+            .into_iter()
+            .map(|p| {
+                let triangle = f(&p);
+                (p, triangle)
+            })
+            .collect();
+        *self = Bvh::build(self.option, primitives).transfrom_by(|(p, _)| p);
+        true
+    }
+}
+
+impl<const D: usize, BV: BoundingVolume<D>, P> Bvh<D, BV, P> {
+    /// primitives accepted by `fi`, only nodes accepted by `fbv` are visited
     pub fn intersect_by<F1, F2, I>(&self, intersecter: I, fi: F1, fbv: F2) -> Vec<&P>
     where
         F1: Fn(&I, &P) -> bool,
@@ -340,16 +572,14 @@ impl<const D: usize, BV: BoundingVolume<D>, P> Bvh<D, BV, P> {
             }
             let node = queue.pop_front().unwrap();
             if node.data.is_leaf() {
-                if let Some(p) = node
-                    .data
-                    .primitives
-                    .as_ref()
-                    .unwrap()
-                    .iter()
-                    .find(|&p| fi(&intersecter, p))
-                {
-                    res.push(p);
-                }
+                res.extend(
+                    node.data
+                        .primitives
+                        .as_ref()
+                        .unwrap()
+                        .iter()
+                        .filter(|&p| fi(&intersecter, p)),
+                );
             }
             if let Some(left) = node.left {
                 let node = self.tree.get_node(left).unwrap();
@@ -368,7 +598,7 @@ impl<const D: usize, BV: BoundingVolume<D>, P> Bvh<D, BV, P> {
         res
     }
 
-    // return all intersected primitives
+    /// all primitives intersected by `intersecter`
     pub fn intersect<I: Intersect<BV> + Intersect<P>>(&self, intersecter: I, err: f32) -> Vec<&P> {
         self.intersect_by(
             intersecter,
@@ -377,3 +607,61 @@ impl<const D: usize, BV: BoundingVolume<D>, P> Bvh<D, BV, P> {
         )
     }
 }
+
+impl<P> Bvh<3, AABB<3>, P> {
+    /// pairs of primitives from both trees accepted by `fp`, each tree placed by its own transform
+    ///
+    /// both trees are traversed together, only node pairs with overlapping boxes are visited.
+    /// `fp` gets the primitives as they are stored, transforming them is up to the caller.
+    pub fn intersect_bvh_by<'a, 'b, Q, F>(
+        &'a self,
+        transform: Mat4,
+        other: &'b Bvh<3, AABB<3>, Q>,
+        other_transform: Mat4,
+        fp: F,
+    ) -> Vec<(&'a P, &'b Q)>
+    where
+        F: Fn(&P, &Q) -> bool,
+    {
+        // work in the space of self
+        let relative = transform.inverse() * other_transform;
+        let mut res = vec![];
+        let mut stack = vec![(0, 0)];
+        while let Some((a, b)) = stack.pop() {
+            let (Some(node_a), Some(node_b)) = (self.tree.get_node(a), other.tree.get_node(b))
+            else {
+                continue;
+            };
+            let bv_b = node_b.data.bv.transform(&relative);
+            if !node_a.data.bv.intersect_aabb(&bv_b, 0.) {
+                continue;
+            }
+            let split_a = match (&node_a.data.primitives, &node_b.data.primitives) {
+                (Some(pa), Some(pb)) => {
+                    for p in pa.iter() {
+                        res.extend(pb.iter().filter(|q| fp(p, q)).map(|q| (p, q)));
+                    }
+                    continue;
+                }
+                (None, Some(_)) => true,
+                (Some(_), None) => false,
+                // split the larger box first
+                (None, None) => volume(&node_a.data.bv) >= volume(&bv_b),
+            };
+            if split_a {
+                for child in [node_a.left, node_a.right].into_iter().flatten() {
+                    stack.push((child, b));
+                }
+            } else {
+                for child in [node_b.left, node_b.right].into_iter().flatten() {
+                    stack.push((a, child));
+                }
+            }
+        }
+        res
+    }
+}
+
+fn volume(aabb: &AABB<3>) -> f32 {
+    (0..3).map(|i| aabb.max[i] - aabb.min[i]).product()
+}
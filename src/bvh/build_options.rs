@@ -1,12 +1,18 @@
-#[derive(Default)]
+/// how a bvh is built
+#[derive(Clone, Copy, Debug, Default)]
 pub struct BuildBvhOption {
+    /// when a node stops being split
     pub depth_control: DepthControl,
+    /// where a node is split
     pub split_method: SplitMethod,
 }
 
-#[derive(Clone, Copy)]
+/// when a node becomes a leaf
+#[derive(Clone, Copy, Debug)]
 pub enum DepthControl {
+    /// nodes at this depth are leaves
     MaxDepth(usize),
+    /// nodes with at most this many primitives are leaves
     MinPrimitives(usize),
 }
 
@@ -16,10 +22,35 @@ impl Default for DepthControl {
     }
 }
 
-#[derive(Clone, Copy, Default)]
+/// where a node is split
+#[derive(Clone, Copy, Debug, Default)]
 pub enum SplitMethod {
+    /// middle of the primitive centers along their longest axis
     #[default]
     Mid,
+    /// median of the primitive centers along their longest axis
     Average,
-    // SAH,
+    /// binned surface area heuristic
+    Sah(SahOption),
+}
+
+/// options of the surface area heuristic, costs are relative, only their ratio matters
+#[derive(Clone, Copy, Debug)]
+pub struct SahOption {
+    /// number of bins along each axis
+    pub bins: usize,
+    /// cost of visiting a node
+    pub traversal_cost: f32,
+    /// cost of testing a primitive
+    pub intersection_cost: f32,
+}
+
+impl Default for SahOption {
+    fn default() -> Self {
+        SahOption {
+            bins: 16,
+            traversal_cost: 0.125,
+            intersection_cost: 1.,
+        }
+    }
 }
@@ -5,7 +5,7 @@ use crate::bv::Bsphere;
 use crate::IndexMesh;
 use glam::Vec2;
 use glam::Vec3;
-use rand::{distributions::Uniform, prelude::Distribution};
+use rand::{distributions::Uniform, prelude::Distribution, SeedableRng};
 
 #[test]
 fn test_from_node() {
@@ -309,6 +309,275 @@ fn test_intersect() {
     println!("{:?}", bvh);
 }
 
+#[test]
+fn test_intersect_by_leaf() {
+    // two overlapping triangles kept in a single leaf, both contain the query point
+    let triangles = vec![
+        (
+            1,
+            [
+                Vec2::new(0.0, 0.0),
+                Vec2::new(2.0, 0.0),
+                Vec2::new(0.0, 2.0),
+            ],
+        ),
+        (
+            2,
+            [
+                Vec2::new(1.0, 1.0),
+                Vec2::new(0.0, 0.0),
+                Vec2::new(1.0, 0.0),
+            ],
+        ),
+    ];
+    let option = BuildBvhOption {
+        depth_control: DepthControl::MinPrimitives(2),
+        ..Default::default()
+    };
+    let bvh = Bvh::<2, AABB<2>, _>::build(option, triangles);
+    let res = bvh.intersect(Vec2::new(0.6, 0.3), 1e-7);
+    let mut ids = res.iter().map(|t| t.0).collect::<Vec<_>>();
+    ids.sort();
+    assert_eq!(ids, vec![1, 2]);
+}
+
+#[test]
+fn test_intersect_bvh_by() {
+    // random triangles, compare the dual traversal with testing every pair of boxes
+    let a = random_triangles(300, 0.1);
+    let b = random_triangles(200, 0.1);
+    let bvh_a = Bvh::<3, AABB<3>, _>::build(BuildBvhOption::default(), a.clone());
+    let bvh_b = Bvh::<3, AABB<3>, _>::build(BuildBvhOption::default(), b.clone());
+    let transform_a =
+        Mat4::from_rotation_translation(glam::Quat::from_rotation_x(0.5), Vec3::new(0.1, 0.2, 0.));
+    let transform_b = Mat4::from_rotation_translation(
+        glam::Quat::from_rotation_z(1.2),
+        Vec3::new(0.3, -0.1, 0.2),
+    );
+    // triangles of b placed in the space of a
+    let relative = transform_a.inverse() * transform_b;
+    let overlap = |(_, p): &(usize, [Vec3; 3]), (_, q): &(usize, [Vec3; 3])| {
+        let p: AABB<3> = p.bv();
+        let q: AABB<3> = q.map(|v| relative.transform_point3(v)).bv();
+        p.intersect_aabb(&q, 0.)
+    };
+    let mut found = bvh_a
+        .intersect_bvh_by(transform_a, &bvh_b, transform_b, overlap)
+        .into_iter()
+        .map(|(p, q)| (p.0, q.0))
+        .collect::<Vec<_>>();
+    found.sort();
+    let mut expected = vec![];
+    for p in a.iter() {
+        for q in b.iter() {
+            if overlap(p, q) {
+                expected.push((p.0, q.0));
+            }
+        }
+    }
+    assert!(!expected.is_empty());
+    assert_eq!(found, expected);
+
+    // moved apart
+    let far = Mat4::from_translation(Vec3::new(10., 0., 0.));
+    assert!(bvh_a
+        .intersect_bvh_by(transform_a, &bvh_b, far, |_, _| true)
+        .is_empty());
+}
+
+fn random_triangles(count: usize, size: f32) -> Vec<(usize, [Vec3; 3])> {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(count as u64);
+    let dist = Uniform::new(-1., 1.);
+    let mut random_vec3 = || {
+        Vec3::new(
+            dist.sample(&mut rng),
+            dist.sample(&mut rng),
+            dist.sample(&mut rng),
+        )
+    };
+    (0..count)
+        .map(|i| {
+            // clustered, so a midpoint split does badly
+            let center = random_vec3() * random_vec3().abs().powf(3.);
+            (i, [(); 3].map(|_| center + random_vec3() * size))
+        })
+        .collect()
+}
+
+/// expected cost of a ray hitting the root box
+fn sah_cost(bvh: &Bvh<3, AABB<3>, (usize, [Vec3; 3])>, option: SahOption) -> f32 {
+    let root = bvh.get_root().bv.surface_area();
+    bvh.iter_bfs(0)
+        .map(|(node, _)| match node.primitives {
+            Some(p) => node.bv.surface_area() / root * option.intersection_cost * p.len() as f32,
+            None => node.bv.surface_area() / root * option.traversal_cost,
+        })
+        .sum()
+}
+
+#[test]
+fn test_build_sah() {
+    let triangles = random_triangles(2000, 0.02);
+    let option = SahOption::default();
+    let sah = Bvh::<3, AABB<3>, _>::build(
+        BuildBvhOption {
+            split_method: SplitMethod::Sah(option),
+            ..Default::default()
+        },
+        triangles.clone(),
+    );
+    // every primitive is in exactly one leaf, inside the box of the leaf
+    let mut found = vec![0; triangles.len()];
+    for (node, _) in sah.iter_bfs(0).filter(|(node, _)| node.is_leaf()) {
+        for (i, tri) in node.primitives.unwrap() {
+            found[*i] += 1;
+            let mut bv = node.bv;
+            bv.merge(&tri.bv());
+            assert_eq!(bv, node.bv);
+        }
+    }
+    assert!(found.iter().all(|count| *count == 1));
+
+    let mid = Bvh::<3, AABB<3>, _>::build(BuildBvhOption::default(), triangles);
+    assert!(sah_cost(&sah, option) < sah_cost(&mid, option));
+}
+
+#[test]
+fn test_cost_sah_option() {
+    // the cost follows the sah option the tree was built with
+    let option = SahOption {
+        traversal_cost: 0.5,
+        intersection_cost: 2.,
+        ..Default::default()
+    };
+    let bvh = Bvh::<3, AABB<3>, _>::build(
+        BuildBvhOption {
+            split_method: SplitMethod::Sah(option),
+            ..Default::default()
+        },
+        random_triangles(200, 0.02),
+    );
+    assert!((bvh.cost() - sah_cost(&bvh, option)).abs() < 1e-3);
+    assert!((bvh.degradation() - 1.).abs() < 1e-6);
+}
+
+/// bounding volume keeping the default surface area
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Unmeasured(AABB<3>);
+
+impl BoundingVolume<3> for Unmeasured {
+    fn merge(&mut self, other: &Self) {
+        self.0.merge(&other.0);
+    }
+
+    fn grow(&mut self, point: &[f32; 3]) {
+        self.0.grow(point);
+    }
+}
+
+#[test]
+fn test_build_sah_unmeasured() {
+    // without a surface area sah falls back to midpoint splits
+    let triangles = random_triangles(200, 0.02);
+    let option = BuildBvhOption {
+        split_method: SplitMethod::Sah(Default::default()),
+        ..Default::default()
+    };
+    let sah = Bvh::<3, Unmeasured, _>::build(option, triangles.clone());
+    let mid = Bvh::<3, Unmeasured, _>::build(BuildBvhOption::default(), triangles);
+    let boxes =
+        |bvh: &Bvh<3, Unmeasured, _>| bvh.iter_bfs(0).map(|(node, _)| node.bv).collect::<Vec<_>>();
+    assert_eq!(boxes(&sah), boxes(&mid));
+    assert_eq!(sah.cost(), 0.);
+    assert_eq!(sah.degradation(), 1.);
+}
+
+#[test]
+fn test_build_sah_leaf() {
+    // visiting a node costs more than testing every primitive
+    let option = SahOption {
+        traversal_cost: 1000.,
+        ..Default::default()
+    };
+    let bvh = Bvh::<3, AABB<3>, _>::build(
+        BuildBvhOption {
+            split_method: SplitMethod::Sah(option),
+            ..Default::default()
+        },
+        random_triangles(100, 0.02),
+    );
+    assert!(bvh.get_root().is_leaf());
+    assert_eq!(bvh.get_root().primitives.unwrap().len(), 100);
+
+    // primitives at the same place can not be split
+    let tri = [Vec3::ZERO, Vec3::X, Vec3::Y];
+    let bvh = Bvh::<3, AABB<3>, _>::build(
+        BuildBvhOption {
+            split_method: SplitMethod::Sah(Default::default()),
+            ..Default::default()
+        },
+        (0..10).map(|i| (i, tri)).collect(),
+    );
+    assert!(bvh.get_root().is_leaf());
+}
+
+/// every box is exactly the union of its primitives or children
+fn assert_fitted(bvh: &Bvh<3, AABB<3>, (usize, [Vec3; 3])>) {
+    for (node, _) in bvh.iter_bfs(0) {
+        let mut bv = AABB::new();
+        match node.primitives {
+            Some(p) => p
+                .iter()
+                .for_each(|p| bv.merge(&Bounded::<3, AABB<3>>::bv(p))),
+            None => [node.left, node.right]
+                .into_iter()
+                .flatten()
+                .for_each(|child| bv.merge(&bvh.get_node(child).unwrap().bv)),
+        }
+        assert_eq!(node.bv, bv);
+    }
+}
+
+#[test]
+fn test_refit() {
+    let triangles = random_triangles(500, 0.02);
+    let mut bvh = Bvh::<3, AABB<3>, _>::build(BuildBvhOption::default(), triangles.clone());
+    assert_eq!(bvh.degradation(), 1.);
+    for (_, triangle) in bvh.primitives_mut() {
+        *triangle = triangle.map(|v| v * 2. + Vec3::new(1., -3., 0.5));
+    }
+    bvh.refit();
+    assert_fitted(&bvh);
+    // an affine motion keeps the tree as good as it was
+    assert!((bvh.degradation() - 1.).abs() < 1e-3);
+}
+
+#[test]
+fn test_refit_rebuild() {
+    let triangles = random_triangles(500, 0.02);
+    let mut bvh = Bvh::<3, AABB<3>, _>::build(BuildBvhOption::default(), triangles.clone());
+    // scramble the geometry, the old tree no longer matches it
+    for (i, triangle) in bvh.primitives_mut() {
+        *triangle = triangles[triangles.len() - 1 - *i].1;
+    }
+    assert!(!bvh.refit_triangles(|(_, t)| *t, None));
+    assert_fitted(&bvh);
+    assert!(bvh.degradation() > 2.);
+
+    assert!(!bvh.refit_triangles(|(_, t)| *t, Some(f32::MAX)));
+    assert!(bvh.refit_triangles(|(_, t)| *t, Some(2.)));
+    assert_fitted(&bvh);
+    assert_eq!(bvh.degradation(), 1.);
+    let mut found = vec![false; triangles.len()];
+    for (node, _) in bvh.iter_bfs(0).filter(|(node, _)| node.is_leaf()) {
+        for (i, triangle) in node.primitives.unwrap() {
+            assert_eq!(*triangle, triangles[triangles.len() - 1 - *i].1);
+            found[*i] = true;
+        }
+    }
+    assert!(found.into_iter().all(|f| f));
+}
+
 #[cfg(test)]
 mod test_bvh {
     use crate::bv::Bsphere;
@@ -1,97 +1,74 @@
-use std::{
-    collections::HashMap,
-    ops::{BitAnd, BitOr, Sub},
+use std::ops::{BitAnd, BitOr, Sub};
+
+use anyhow::bail;
+
+use crate::{
+    half_edge::HalfEdgeMesh,
+    tree::{iter_types, Tree},
+    IndexMesh,
 };
-use uuid::Uuid;
 
-use crate::{half_edge::HalfEdgeMesh, tree::Tree};
+use self::split::{split, Location};
+
+mod split;
 
 #[cfg(test)]
 mod csg_tests;
 
-struct ClusterdHalfEdgeMesh {
-    origin_mesh: HalfEdgeMesh,
-    origin_cluster_uuid: Uuid,
-    /// cluster id -> vec of face id, rest belong to origin cluster
-    clusters: HashMap<Uuid, Vec<Uuid>>,
-    /// edge id -> ((cluster id, vec<half edge id>), (cluster id, vec<half edge id>)),
-    /// vec of half edge id should be closed
-    edges: HashMap<Uuid, ((Uuid, Vec<Uuid>), (Uuid, Vec<Uuid>))>,
-}
-
 // https://arxiv.org/pdf/2205.14151.pdf
 // https://arxiv.org/pdf/1308.4434.pdf
-impl ClusterdHalfEdgeMesh {
-    fn new(mesh: HalfEdgeMesh) -> Self {
-        ClusterdHalfEdgeMesh {
-            origin_mesh: mesh,
-            origin_cluster_uuid: Uuid::new_v4(),
-            clusters: HashMap::new(),
-            edges: HashMap::new(),
-        }
-    }
-
-    fn split(&mut self, other: &mut Self) {
-        // TODO
-        if !self.origin_mesh.is_mesh_self_intersect()
-            || self.origin_mesh.is_mesh_manifold()
-            || !other.origin_mesh.is_mesh_self_intersect()
-            || other.origin_mesh.is_mesh_manifold()
-        {
-            panic!("mesh need to be non self-intersect and manifold");
-        }
-        let bvh1 = self.origin_mesh.bvh.as_ref().unwrap();
-        let bvh2 = other.origin_mesh.bvh.as_ref().unwrap();
-        // find intersect face pairs
-        
-    }
-
-    fn union(&self, other: &Self) -> Self {
-        // TODO
-        // intersect test
-        // mesh need to be non self-intersect
-        // if
-
-        todo!()
-    }
-
-    fn intersection(&self, other: &Self) -> Self {
-        // TODO
-        todo!()
-    }
-
-    fn subtraction(&self, other: &Self) -> Self {
-        // TODO
-        todo!()
-    }
+fn boolean(a: &HalfEdgeMesh, b: &HalfEdgeMesh, operation: CsgOperation) -> HalfEdgeMesh {
+    use Location::{Inside, OnOpposite, OnSame, Outside};
+
+    let split = split(&IndexMesh::from(a), &IndexMesh::from(b));
+    let mesh = match operation {
+        CsgOperation::Union => split.select(&[Outside, OnSame], &[Outside], false),
+        CsgOperation::Intersection => split.select(&[Inside, OnSame], &[Inside], false),
+        CsgOperation::Difference => split.select(&[Outside, OnOpposite], &[Inside], true),
+    };
+    HalfEdgeMesh::from(&mesh)
 }
 
+/// boolean operation of a csg tree node
 #[derive(Clone, Copy, Debug)]
 pub enum CsgOperation {
+    /// a | b
     Union,
+    /// a & b
     Intersection,
+    /// a - b
     Difference,
 }
 
 struct CsgNode {
-    mesh: Option<ClusterdHalfEdgeMesh>,
+    mesh: Option<HalfEdgeMesh>,
     operation: Option<CsgOperation>,
 }
 
 impl CsgNode {
     fn new(origin_mesh: HalfEdgeMesh) -> Self {
         CsgNode {
-            mesh: Some(ClusterdHalfEdgeMesh::new(origin_mesh)),
+            mesh: Some(origin_mesh),
             operation: None,
         }
     }
 }
 
+/// tree of boolean operations on closed meshes
+///
+/// ## Example
+///
+/// ```ignore
+/// let a = CsgSolid::new(Box { size: 1. }.to_mesh().to_halfedge_mesh());
+/// let b = CsgSolid::new(Box { size: 1. }.to_mesh().to_halfedge_mesh());
+/// let mesh = (a - b).to_mesh()?;
+/// ```
 pub struct CsgSolid {
     tree: Tree<CsgNode>,
 }
 
 impl CsgSolid {
+    /// leaf solid, the mesh must be closed
     pub fn new(origin_mesh: HalfEdgeMesh) -> Self {
         CsgSolid {
             tree: Tree::new_root(CsgNode::new(origin_mesh)),
@@ -111,71 +88,72 @@ impl CsgSolid {
         Self { tree }
     }
 
+    /// union of two solids, same as `self | mesh`
     pub fn union(self, mesh: Self) -> Self {
         self.merge(mesh, CsgOperation::Union)
     }
 
+    /// intersection of two solids, same as `self & mesh`
     pub fn intersection(self, mesh: Self) -> Self {
         self.merge(mesh, CsgOperation::Intersection)
     }
 
+    /// difference of two solids, same as `self - mesh`
     pub fn difference(self, mesh: Self) -> Self {
         self.merge(mesh, CsgOperation::Difference)
     }
 
-    pub fn construct(&mut self) {
+    /// evaluate the tree from the leaves to the root,
+    /// every leaf mesh must be closed
+    pub fn construct(&mut self) -> anyhow::Result<()> {
         let root = self.tree.get_node(0).unwrap();
-        if let Some(_) = &root.data.mesh {
+        if root.data.mesh.is_some() {
             // no need to do any thing
-            return;
+            return Ok(());
         }
 
-        // init works
-        let mut vec = vec![0usize];
-        let mut current_pointer = 0;
-        while let Some(node_index) = vec.get(current_pointer) {
-            let node = self.tree.get_node(*node_index).unwrap();
-            if node.data.mesh.is_none() {
-                if node.left.is_none() {
-                    vec.push(node.left.unwrap());
-                }
-                if node.right.is_none() {
-                    vec.push(node.right.unwrap());
+        // operation nodes in bfs order, children always come after their parent
+        let works = self
+            .tree
+            .iter::<iter_types::Bfs>(0)
+            .filter(|(node, _)| node.data.mesh.is_none())
+            .map(|(_, index)| index)
+            .collect::<Vec<_>>();
+        for (node, index) in self.tree.iter::<iter_types::Bfs>(0) {
+            if let Some(mesh) = &node.data.mesh {
+                if !mesh.is_mesh_watertight() {
+                    bail!("csg node {index} is not a closed mesh");
                 }
             }
-            current_pointer += 1;
         }
 
         // do works in reverse order
-
-        for node_index in vec.iter().rev() {
-            let node = self.tree.get_node(*node_index).unwrap();
+        for node_index in works.into_iter().rev() {
+            let node = self.tree.get_node(node_index).unwrap();
             // must have left and right
-            let left_mesh = self
-                .tree
-                .get_node(node.left.unwrap())
-                .unwrap()
-                .data
-                .mesh
-                .as_ref()
-                .unwrap();
-            let right_mesh = self
-                .tree
-                .get_node(node.right.unwrap())
-                .unwrap()
-                .data
-                .mesh
-                .as_ref()
-                .unwrap();
-
-            let operation = node.data.operation.unwrap();
-            let new_mesh = match operation {
-                CsgOperation::Union => left_mesh.union(right_mesh),
-                CsgOperation::Intersection => left_mesh.intersection(right_mesh),
-                CsgOperation::Difference => left_mesh.subtraction(right_mesh),
+            let (Some(left), Some(right)) = (node.left, node.right) else {
+                bail!("csg node {node_index} need two operands");
             };
-            self.tree.get_node_mut(*node_index).unwrap().data.mesh = Some(new_mesh);
+            let mesh = |i: usize| self.tree.get_node(i).unwrap().data.mesh.as_ref().unwrap();
+            let operation = node.data.operation.unwrap();
+            let new_mesh = boolean(mesh(left), mesh(right), operation);
+            self.tree.get_node_mut(node_index).unwrap().data.mesh = Some(new_mesh);
         }
+        Ok(())
+    }
+
+    /// evaluate the tree and return the result as an index mesh
+    pub fn to_mesh(&mut self) -> anyhow::Result<IndexMesh> {
+        self.construct()?;
+        let root = self.tree.get_node(0).unwrap();
+        Ok(IndexMesh::from(root.data.mesh.as_ref().unwrap()))
+    }
+
+    /// evaluate the tree and return the result as a half-edge mesh
+    pub fn to_halfedge_mesh(mut self) -> anyhow::Result<HalfEdgeMesh> {
+        self.construct()?;
+        let root = self.tree.get_node_mut(0).unwrap();
+        Ok(root.data.mesh.take().unwrap())
     }
 }
 
@@ -1,21 +1,164 @@
-use std::ops::Add;
-
 use glam::Vec3;
+use rand::{distributions::Uniform, prelude::Distribution, SeedableRng};
 
-use crate::half_edge::HalfEdgeMesh;
+use crate::{
+    test_utils::{assert_closed, volume},
+    IndexMesh, AABB,
+};
 
 use super::CsgSolid;
 
+fn solid(translation: Vec3) -> CsgSolid {
+    let mut mesh = crate::Box { size: 1.0 }.to_mesh();
+    mesh.translate(translation);
+    CsgSolid::new(mesh.to_halfedge_mesh())
+}
+
+fn bounding_box(mesh: &IndexMesh) -> AABB<3> {
+    mesh.vertices().fold(AABB::new(), |mut aabb, v| {
+        aabb.grow(&v.to_array());
+        aabb
+    })
+}
+
 #[test]
 fn test_union() {
-    // TODO should crate a primtive lib for generial perpose
-    let box1 = crate::Box { size: 1.0 }.to_mesh();
-    let mut box2 = crate::Box { size: 1.0 }.to_mesh();
-    box2.translate(Vec3::new(0.5, 0.5, 0.5));
-
-    let csg_box1 = CsgSolid::new(box1.to_halfedge_mesh());
-    let csg_box2 = CsgSolid::new(box2.to_halfedge_mesh());
-    let new = csg_box1 & csg_box2;
-    // let mut result = csg_box1.union(csg_box2);
-    // result.construct();
+    let mut result = solid(Vec3::ZERO) | solid(Vec3::new(0.5, 0.5, 0.5));
+    let mesh = result.to_mesh().unwrap();
+    assert_closed(&mesh);
+    assert!((volume(&mesh) - 1.875).abs() < 1e-5);
+    let aabb = bounding_box(&mesh);
+    assert_eq!(aabb.min, [-0.5; 3]);
+    assert_eq!(aabb.max, [1.; 3]);
+}
+
+#[test]
+fn test_intersection() {
+    let mut result = solid(Vec3::ZERO) & solid(Vec3::new(0.5, 0.5, 0.5));
+    let mesh = result.to_mesh().unwrap();
+    assert_closed(&mesh);
+    assert!((volume(&mesh) - 0.125).abs() < 1e-5);
+    let aabb = bounding_box(&mesh);
+    assert_eq!(aabb.min, [0.; 3]);
+    assert_eq!(aabb.max, [0.5; 3]);
+}
+
+#[test]
+fn test_difference() {
+    let mut result = solid(Vec3::ZERO) - solid(Vec3::new(0.5, 0.5, 0.5));
+    let mesh = result.to_mesh().unwrap();
+    assert_closed(&mesh);
+    assert!((volume(&mesh) - 0.875).abs() < 1e-5);
+}
+
+#[test]
+fn test_general_position() {
+    let b = Vec3::new(0.3, 0.2, 0.1);
+    let mut union = solid(Vec3::ZERO) | solid(b);
+    let mut intersection = solid(Vec3::ZERO) & solid(b);
+    let mut difference = solid(Vec3::ZERO) - solid(b);
+    let overlap = 0.7 * 0.8 * 0.9;
+    let union = union.to_mesh().unwrap();
+    let intersection = intersection.to_mesh().unwrap();
+    let difference = difference.to_mesh().unwrap();
+    assert_closed(&union);
+    assert_closed(&intersection);
+    assert_closed(&difference);
+    assert!((volume(&union) - (2. - overlap)).abs() < 1e-5);
+    assert!((volume(&intersection) - overlap).abs() < 1e-5);
+    assert!((volume(&difference) - (1. - overlap)).abs() < 1e-5);
+}
+
+#[test]
+fn test_random_offsets() {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+    let dist = Uniform::new(-0.5, 0.5);
+    let sphere = crate::Sphere {
+        radius: 1.,
+        segments: 24,
+        rings: 12,
+    }
+    .to_mesh()
+    .unwrap();
+    let icosphere = crate::Icosphere::default().to_mesh().unwrap();
+    let (volume_a, volume_b) = (volume(&sphere), volume(&icosphere));
+    // the first offset used to leave split points 6e-6 apart unmerged
+    let random = (0..8).map(|_| {
+        Vec3::new(
+            dist.sample(&mut rng),
+            dist.sample(&mut rng),
+            dist.sample(&mut rng),
+        )
+    });
+    for offset in std::iter::once(Vec3::new(0.3, 0.2, 0.1)).chain(random) {
+        let mut a = sphere.clone();
+        a.translate(offset);
+        let operand = |mesh: &IndexMesh| CsgSolid::new(mesh.to_halfedge_mesh());
+        let union = (operand(&a) | operand(&icosphere)).to_mesh().unwrap();
+        let intersection = (operand(&a) & operand(&icosphere)).to_mesh().unwrap();
+        let difference = (operand(&a) - operand(&icosphere)).to_mesh().unwrap();
+        assert_closed(&union);
+        assert_closed(&intersection);
+        assert_closed(&difference);
+        let (union, intersection) = (volume(&union), volume(&intersection));
+        assert!((union + intersection - volume_a - volume_b).abs() < 1e-4);
+        assert!((volume(&difference) - (volume_a - intersection)).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn test_coplanar_faces() {
+    // faces at y = ±0.5 and z = ±0.5 are shared by both boxes
+    let b = Vec3::new(0.5, 0., 0.);
+    let mut union = solid(Vec3::ZERO) | solid(b);
+    let mut intersection = solid(Vec3::ZERO) & solid(b);
+    let mut difference = solid(Vec3::ZERO) - solid(b);
+    let union = union.to_mesh().unwrap();
+    let intersection = intersection.to_mesh().unwrap();
+    let difference = difference.to_mesh().unwrap();
+    assert_closed(&union);
+    assert_closed(&intersection);
+    assert_closed(&difference);
+    assert!((volume(&union) - 1.5).abs() < 1e-5);
+    assert!((volume(&intersection) - 0.5).abs() < 1e-5);
+    assert!((volume(&difference) - 0.5).abs() < 1e-5);
+}
+
+#[test]
+fn test_disjoint_and_nested() {
+    let mut union = solid(Vec3::ZERO) | solid(Vec3::new(2., 0., 0.));
+    let union = union.to_mesh().unwrap();
+    assert_eq!(union.triangles.len(), 24);
+    assert!((volume(&union) - 2.).abs() < 1e-5);
+
+    let mut inner = crate::Box { size: 0.5 }.to_mesh();
+    inner.translate(Vec3::new(0.1, 0., 0.));
+    let mut difference = solid(Vec3::ZERO) - CsgSolid::new(inner.to_halfedge_mesh());
+    let difference = difference.to_mesh().unwrap();
+    assert_eq!(difference.triangles.len(), 24);
+    assert!((volume(&difference) - 0.875).abs() < 1e-5);
+}
+
+#[test]
+fn test_tree() {
+    // (a | b) - c, c cuts a hole through both
+    let a = solid(Vec3::ZERO);
+    let b = solid(Vec3::new(0.75, 0., 0.));
+    let mut c = crate::Box { size: 0.5 }.to_mesh();
+    c.scale(Vec3::new(1., 1., 4.));
+    c.translate(Vec3::new(0.375, 0.05, 0.));
+    let c = CsgSolid::new(c.to_halfedge_mesh());
+    let result = (a | b) - c;
+    let mesh = result.to_halfedge_mesh().unwrap();
+    assert!(mesh.is_mesh_watertight());
+    let mesh = IndexMesh::from(&mesh);
+    assert!((volume(&mesh) - (1.75 - 0.25)).abs() < 1e-5);
+}
+
+#[test]
+fn test_open_mesh() {
+    let mut mesh = crate::Box { size: 1.0 }.to_mesh();
+    mesh.triangles.pop();
+    let mut result = CsgSolid::new(mesh.to_halfedge_mesh()) | solid(Vec3::ZERO);
+    assert!(result.to_mesh().is_err());
 }
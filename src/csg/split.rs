@@ -0,0 +1,496 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use glam::{DVec2, DVec3, Vec3};
+
+use crate::{
+    algorithms::{
+        cdt::Cdt, locate_on_triangle, tri_tri_intersect, winding_number, IntersectTopo,
+        TriTriIntersectStatus,
+    },
+    bvh::{build_options::BuildBvhOption, Bvh},
+    index_mesh::IndexTriangle,
+    traits::Bounded,
+    IndexMesh, AABB,
+};
+
+/// where a cluster of split triangles sits relative to the other operand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Location {
+    Inside,
+    Outside,
+    /// on the surface of the other operand, facing the same direction
+    OnSame,
+    /// on the surface of the other operand, facing the opposite direction
+    OnOpposite,
+}
+
+/// vertex, edge or face of an operand, edges are stored as (small, big)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Feature {
+    Vertex(usize),
+    Edge(usize, usize),
+    Face(usize),
+}
+
+struct Operand {
+    vertices: Vec<DVec3>,
+    triangles: Vec<[usize; 3]>,
+    normals: Vec<DVec3>,
+    /// index of the first vertex in the shared point list
+    offset: usize,
+}
+
+impl Operand {
+    fn new(mesh: &IndexMesh, offset: usize) -> Self {
+        let vertices = mesh.vertices().map(|v| v.as_dvec3()).collect::<Vec<_>>();
+        let triangles = mesh
+            .triangles()
+            .map(|t| [t.0, t.1, t.2])
+            .collect::<Vec<_>>();
+        let normals = triangles
+            .iter()
+            .map(|t| {
+                (vertices[t[1]] - vertices[t[0]])
+                    .cross(vertices[t[2]] - vertices[t[0]])
+                    .normalize_or_zero()
+            })
+            .collect();
+        Operand {
+            vertices,
+            triangles,
+            normals,
+            offset,
+        }
+    }
+
+    fn triangle(&self, t: usize) -> [DVec3; 3] {
+        self.triangles[t].map(|v| self.vertices[v])
+    }
+
+    fn feature(&self, t: usize, topo: IntersectTopo) -> Feature {
+        let tri = self.triangles[t];
+        match topo {
+            IntersectTopo::Vertex(i) => Feature::Vertex(tri[i as usize]),
+            IntersectTopo::Edge(i, j) => {
+                let (a, b) = (tri[i as usize], tri[j as usize]);
+                Feature::Edge(a.min(b), a.max(b))
+            }
+            IntersectTopo::Face => Feature::Face(t),
+        }
+    }
+}
+
+/// intersection data of one triangle
+#[derive(Default)]
+struct Cut {
+    /// (point id, position of the point on the triangle)
+    points: Vec<(usize, IntersectTopo)>,
+    segments: Vec<(usize, usize)>,
+    /// triangles of the other operand lying on the same plane and overlapping this one
+    coplanar: Vec<usize>,
+}
+
+/// one operand after being cut along the intersection curve with the other operand
+pub(super) struct SplitMesh {
+    /// triangles indexing into the shared point list
+    pub(super) triangles: Vec<[usize; 3]>,
+    /// cluster of each triangle, clusters are separated by the intersection curve
+    pub(super) clusters: Vec<usize>,
+    /// location of each cluster
+    pub(super) locations: Vec<Location>,
+}
+
+pub(super) struct Split {
+    points: Vec<DVec3>,
+    pub(super) a: SplitMesh,
+    pub(super) b: SplitMesh,
+}
+
+impl Split {
+    /// collect the clusters of a and b at the given locations into one mesh,
+    /// the triangles taken from b can be flipped
+    pub(super) fn select(&self, a: &[Location], b: &[Location], flip_b: bool) -> IndexMesh {
+        let mut mesh = IndexMesh::new();
+        let mut map = HashMap::new();
+        let mut push = |mesh: &mut IndexMesh, tri: [usize; 3]| {
+            let tri = tri.map(|p| {
+                *map.entry(p).or_insert_with(|| {
+                    mesh.vertices.push(self.points[p].as_vec3());
+                    mesh.vertices.len() - 1
+                })
+            });
+            mesh.triangles.push(IndexTriangle(tri[0], tri[1], tri[2]));
+        };
+        for (tri, cluster) in self.a.triangles.iter().zip(self.a.clusters.iter()) {
+            if a.contains(&self.a.locations[*cluster]) {
+                push(&mut mesh, *tri);
+            }
+        }
+        for (tri, cluster) in self.b.triangles.iter().zip(self.b.clusters.iter()) {
+            if b.contains(&self.b.locations[*cluster]) {
+                push(
+                    &mut mesh,
+                    if flip_b {
+                        [tri[0], tri[2], tri[1]]
+                    } else {
+                        *tri
+                    },
+                );
+            }
+        }
+        mesh
+    }
+}
+
+/// cut both meshes along their intersection curve and classify the pieces
+///
+/// every intersection point is identified by the pair of features (vertex, edge or face)
+/// it lies on, so triangles sharing an edge always share the points on that edge and the
+/// retriangulated meshes stay closed.
+pub(super) fn split(a: &IndexMesh, b: &IndexMesh) -> Split {
+    let a = Operand::new(a, 0);
+    let b = Operand::new(b, a.vertices.len());
+    let err = tolerance(&a, &b);
+
+    let mut points = a.vertices.clone();
+    points.extend(b.vertices.iter());
+    let mut alias = (0..points.len()).collect::<Vec<_>>();
+    let mut ids = HashMap::<(Feature, Feature), usize>::new();
+    let mut cuts_a = HashMap::<usize, Cut>::new();
+    let mut cuts_b = HashMap::<usize, Cut>::new();
+
+    for (ta, tb) in candidate_pairs(&a, &b, err) {
+        let (ipoints, coplanar) = match tri_tri_intersect(a.triangle(ta), b.triangle(tb), err) {
+            TriTriIntersectStatus::NotIntersect => continue,
+            TriTriIntersectStatus::Intersect(p, q) => {
+                (std::iter::once(p).chain(q).collect(), false)
+            }
+            TriTriIntersectStatus::Coplanar(points) => (points, true),
+        };
+        let mut point_ids = vec![];
+        for ip in ipoints.iter() {
+            let id = match (a.feature(ta, ip.t1), b.feature(tb, ip.t2)) {
+                (Feature::Vertex(v), Feature::Vertex(w)) => {
+                    union(&mut alias, v, b.offset + w);
+                    v
+                }
+                (Feature::Vertex(v), _) => v,
+                (_, Feature::Vertex(w)) => b.offset + w,
+                key => *ids.entry(key).or_insert_with(|| {
+                    points.push(ip.p);
+                    alias.push(alias.len());
+                    points.len() - 1
+                }),
+            };
+            point_ids.push(id);
+        }
+
+        let cut_a = cuts_a.entry(ta).or_default();
+        let cut_b = cuts_b.entry(tb).or_default();
+        for (ip, id) in ipoints.iter().zip(point_ids.iter()) {
+            cut_a.points.push((*id, ip.t1));
+            cut_b.points.push((*id, ip.t2));
+        }
+        let segments = match point_ids.len() {
+            0 | 1 => vec![],
+            2 => vec![(point_ids[0], point_ids[1])],
+            // boundary of the overlapping polygon
+            n => (0..n)
+                .map(|i| (point_ids[i], point_ids[(i + 1) % n]))
+                .collect(),
+        };
+        cut_a.segments.extend(segments.iter());
+        cut_b.segments.extend(segments.iter());
+        if coplanar {
+            cut_a.coplanar.push(tb);
+            cut_b.coplanar.push(ta);
+        }
+    }
+
+    // vertices of a and b at the same place are merged, intersection points found on different
+    // features but only a few tolerances apart are snapped together
+    snap(&points, &mut alias, b.offset + b.vertices.len(), SNAP * err);
+    let roots = (0..alias.len())
+        .map(|i| find(&mut alias, i))
+        .collect::<Vec<_>>();
+    let points = roots.iter().map(|root| points[*root]).collect::<Vec<_>>();
+    let mut cut_edges = HashSet::new();
+    for cut in cuts_a.values_mut().chain(cuts_b.values_mut()) {
+        cut.points.iter_mut().for_each(|(id, _)| *id = roots[*id]);
+        cut.segments = cut
+            .segments
+            .iter()
+            .map(|(p, q)| (roots[*p], roots[*q]))
+            .filter(|(p, q)| p != q)
+            .collect();
+        cut_edges.extend(cut.segments.iter().map(|(p, q)| (*p.min(q), *p.max(q))));
+    }
+
+    let split_a = split_operand(&a, &b, &cuts_a, &points, &roots, &cut_edges, err);
+    let split_b = split_operand(&b, &a, &cuts_b, &points, &roots, &cut_edges, err);
+    Split {
+        points,
+        a: split_a,
+        b: split_b,
+    }
+}
+
+fn tolerance(a: &Operand, b: &Operand) -> f64 {
+    let (min, max) = a.vertices.iter().chain(b.vertices.iter()).fold(
+        (DVec3::splat(f64::MAX), DVec3::splat(f64::MIN)),
+        |(min, max), v| (min.min(*v), max.max(*v)),
+    );
+    (max - min).length().max(1.) * 1e-6
+}
+
+/// triangle pairs with overlapping bounding boxes
+fn candidate_pairs(a: &Operand, b: &Operand, err: f64) -> Vec<(usize, usize)> {
+    let to_vec3 = |tri: [DVec3; 3]| tri.map(|v| v.as_vec3());
+    let primitives = (0..b.triangles.len())
+        .map(|t| (t, to_vec3(b.triangle(t))))
+        .collect::<Vec<_>>();
+    if primitives.is_empty() {
+        return vec![];
+    }
+    let bvh = Bvh::<3, AABB<3>, _>::build(BuildBvhOption::default(), primitives);
+    let mut pairs = vec![];
+    for ta in 0..a.triangles.len() {
+        let mut aabb: AABB<3> = to_vec3(a.triangle(ta)).bv();
+        aabb.grow(&(Vec3::from(aabb.min) - err as f32).to_array());
+        aabb.grow(&(Vec3::from(aabb.max) + err as f32).to_array());
+        let found = bvh.intersect_by(
+            aabb,
+            |aabb, (_, tri)| aabb.intersect_aabb(&tri.bv(), 0.),
+            |aabb, bv| aabb.intersect_aabb(bv, 0.),
+        );
+        pairs.extend(found.into_iter().map(|(tb, _)| (ta, *tb)));
+    }
+    pairs
+}
+
+/// intersection points closer than this many tolerances are merged
+const SNAP: f64 = 10.;
+
+/// merge every point from `first` on with the points closer than `radius`
+fn snap(points: &[DVec3], alias: &mut [usize], first: usize, radius: f64) {
+    let cell = |p: DVec3| (p / radius).floor().to_array().map(|x| x as i64);
+    let mut grid = HashMap::<_, Vec<usize>>::new();
+    for (i, p) in points.iter().enumerate() {
+        grid.entry(cell(*p)).or_default().push(i);
+    }
+    for (i, p) in points.iter().enumerate().skip(first) {
+        let [x, y, z] = cell(*p);
+        for k in 0..27 {
+            let near = [x + k % 3 - 1, y + k / 3 % 3 - 1, z + k / 9 - 1];
+            let Some(near) = grid.get(&near) else {
+                continue;
+            };
+            for j in near {
+                if *j != i && points[*j].distance(*p) < radius {
+                    union(alias, *j, i);
+                }
+            }
+        }
+    }
+}
+
+fn find(alias: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while alias[root] != root {
+        root = alias[root];
+    }
+    let mut i = i;
+    while alias[i] != root {
+        let next = alias[i];
+        alias[i] = root;
+        i = next;
+    }
+    root
+}
+
+/// the smaller index stays the root, so vertices of the operands are never moved
+fn union(alias: &mut [usize], i: usize, j: usize) {
+    let (i, j) = (find(alias, i), find(alias, j));
+    alias[i.max(j)] = i.min(j);
+}
+
+fn split_operand(
+    op: &Operand,
+    other: &Operand,
+    cuts: &HashMap<usize, Cut>,
+    points: &[DVec3],
+    roots: &[usize],
+    cut_edges: &HashSet<(usize, usize)>,
+    err: f64,
+) -> SplitMesh {
+    let mut triangles = vec![];
+    let mut origins = vec![];
+    for t in 0..op.triangles.len() {
+        let corners = op.triangles[t].map(|v| roots[op.offset + v]);
+        match cuts.get(&t) {
+            Some(cut) => {
+                for tri in retriangulate(op, t, corners, cut, points, err) {
+                    triangles.push(tri);
+                    origins.push(t);
+                }
+            }
+            None => {
+                triangles.push(corners);
+                origins.push(t);
+            }
+        }
+    }
+
+    let (clusters, count) = cluster(&triangles, cut_edges);
+
+    // classify every cluster by its biggest triangle
+    let mut representative = vec![None; count];
+    for (t, tri) in triangles.iter().enumerate() {
+        let area = (points[tri[1]] - points[tri[0]])
+            .cross(points[tri[2]] - points[tri[0]])
+            .length();
+        match representative[clusters[t]] {
+            Some((_, max)) if max >= area => {}
+            _ => representative[clusters[t]] = Some((t, area)),
+        }
+    }
+    let locations = representative
+        .into_iter()
+        .map(|r| {
+            let (t, _) = r.unwrap();
+            let tri = triangles[t];
+            let center = (points[tri[0]] + points[tri[1]] + points[tri[2]]) / 3.;
+            let origin = origins[t];
+            let on_surface = cuts.get(&origin).and_then(|cut| {
+                cut.coplanar.iter().find(|o| {
+                    locate_on_triangle(&other.triangle(**o), other.normals[**o], center, err)
+                        .is_some()
+                })
+            });
+            match on_surface {
+                Some(o) if op.normals[origin].dot(other.normals[*o]) > 0. => Location::OnSame,
+                Some(_) => Location::OnOpposite,
+                None => {
+                    let triangles = (0..other.triangles.len()).map(|t| other.triangle(t));
+                    if winding_number(center, triangles) > 0.5 {
+                        Location::Inside
+                    } else {
+                        Location::Outside
+                    }
+                }
+            }
+        })
+        .collect();
+
+    SplitMesh {
+        triangles,
+        clusters,
+        locations,
+    }
+}
+
+/// triangulate a triangle together with all intersection points and segments on it
+fn retriangulate(
+    op: &Operand,
+    t: usize,
+    corners: [usize; 3],
+    cut: &Cut,
+    points: &[DVec3],
+    err: f64,
+) -> Vec<[usize; 3]> {
+    // local point list: the corners first, then the intersection points
+    let mut ids = corners.to_vec();
+    let mut topos = vec![None; 3];
+    for (id, topo) in cut.points.iter() {
+        match ids.iter().position(|i| i == id) {
+            // a snapped point may be reported on the face and on an edge, the edge wins
+            Some(k) if topos[k] == Some(IntersectTopo::Face) => topos[k] = Some(*topo),
+            Some(_) => {}
+            None => {
+                ids.push(*id);
+                topos.push(Some(*topo));
+            }
+        }
+    }
+
+    let origin = points[corners[0]];
+    let u = (points[corners[1]] - origin).normalize();
+    let v = op.normals[t].cross(u);
+    let local = ids
+        .iter()
+        .map(|id| {
+            let p = points[*id] - origin;
+            DVec2::new(p.dot(u), p.dot(v))
+        })
+        .collect::<Vec<_>>();
+    let mut cdt = Cdt::new(&local, err);
+
+    // boundary of the triangle, with the points on each edge sorted along the edge
+    for (i, j) in [(0, 1), (1, 2), (2, 0)] {
+        let edge = IntersectTopo::Edge(i.min(j), i.max(j));
+        let dir = local[j as usize] - local[i as usize];
+        let mut on_edge = (3..ids.len())
+            .filter(|k| topos[*k] == Some(edge))
+            .collect::<Vec<_>>();
+        on_edge.sort_by(|k1, k2| {
+            let t1 = (local[*k1] - local[i as usize]).dot(dir);
+            let t2 = (local[*k2] - local[i as usize]).dot(dir);
+            t1.partial_cmp(&t2).unwrap()
+        });
+        let mut chain = vec![i as usize];
+        chain.extend(on_edge);
+        chain.push(j as usize);
+        for w in chain.windows(2) {
+            cdt.insert_constraint(w[0], w[1]);
+        }
+    }
+    let index = |id: &usize| ids.iter().position(|i| i == id).unwrap();
+    for (p, q) in cut.segments.iter() {
+        cdt.insert_constraint(index(p), index(q));
+    }
+    cdt.restore_delaunay();
+
+    cdt.triangles_with_depth()
+        .into_iter()
+        .filter(|(_, depth)| *depth > 0)
+        .map(|(tri, _)| tri.map(|k| ids[k]))
+        .collect()
+}
+
+/// group triangles connected without crossing a cut edge
+fn cluster(triangles: &[[usize; 3]], cut_edges: &HashSet<(usize, usize)>) -> (Vec<usize>, usize) {
+    let mut edge_map = HashMap::<(usize, usize), Vec<usize>>::new();
+    for (t, tri) in triangles.iter().enumerate() {
+        for k in 0..3 {
+            let (p, q) = (tri[k], tri[(k + 1) % 3]);
+            edge_map.entry((p.min(q), p.max(q))).or_default().push(t);
+        }
+    }
+    let mut clusters = vec![usize::MAX; triangles.len()];
+    let mut count = 0;
+    for start in 0..triangles.len() {
+        if clusters[start] != usize::MAX {
+            continue;
+        }
+        clusters[start] = count;
+        let mut queue = VecDeque::from([start]);
+        while let Some(t) = queue.pop_front() {
+            let tri = triangles[t];
+            for k in 0..3 {
+                let (p, q) = (tri[k], tri[(k + 1) % 3]);
+                let edge = (p.min(q), p.max(q));
+                if cut_edges.contains(&edge) {
+                    continue;
+                }
+                for next in edge_map[&edge].iter() {
+                    if clusters[*next] == usize::MAX {
+                        clusters[*next] = count;
+                        queue.push_back(*next);
+                    }
+                }
+            }
+        }
+        count += 1;
+    }
+    (clusters, count)
+}
@@ -72,6 +72,16 @@ where
     }
 }
 
+/// two faces of a mesh crossing each other
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelfIntersection {
+    /// uuid of the crossing faces
+    pub faces: (Uuid, Uuid),
+    /// where the faces cross, both ends are the same point if they only touch,
+    /// for overlapping coplanar faces it spans the overlapping area
+    pub segment: [Vec3; 2],
+}
+
 #[derive(Debug, Default)]
 pub struct HalfEdgeMesh<BV = AABB<3>>
 where
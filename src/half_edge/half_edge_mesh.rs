@@ -4,12 +4,15 @@ use glam::{Mat4, Quat, Vec3};
 use uuid::Uuid;
 
 use crate::{
+    algorithms::{tri_tri_intersect, IntersectTopo, TriTriIntersectStatus},
     bv::BoundingVolume,
     bvh::{build_options::BuildBvhOption, Bvh},
-    IndexMesh,
+    query::{tolerance, triangles_intersect},
+    traits::{Bounded, Intersect},
+    IndexMesh, AABB,
 };
 
-use super::{Face, HalfEdge, HalfEdgeMesh, Vertex};
+use super::{Face, HalfEdge, HalfEdgeMesh, SelfIntersection, Vertex};
 
 impl<BV> From<&IndexMesh> for HalfEdgeMesh<BV>
 where
@@ -40,6 +43,134 @@ impl HalfEdgeMesh {
             bvh: None,
         }
     }
+
+    /// pairs of crossing or touching faces, the first uuid is from this mesh
+    ///
+    /// each mesh is placed by its own transform
+    pub fn find_intersect(
+        &mut self,
+        transform: Mat4,
+        other: &mut Self,
+        other_transform: Mat4,
+    ) -> Vec<(Uuid, Uuid)> {
+        if self.faces.is_empty() || other.faces.is_empty() {
+            return vec![];
+        }
+        if self.bvh.is_none() {
+            self.build_bvh();
+        }
+        if other.bvh.is_none() {
+            other.build_bvh();
+        }
+        let (bvh, other_bvh) = (self.bvh.as_ref().unwrap(), other.bvh.as_ref().unwrap());
+        let relative = transform.inverse() * other_transform;
+        let err = tolerance(&bvh.get_root().bv);
+        let pairs = bvh.intersect_bvh_by(transform, other_bvh, other_transform, |a, b| {
+            triangles_intersect(a.1, b.1.map(|v| relative.transform_point3(v)), err)
+        });
+        let mut res = pairs
+            .into_iter()
+            .map(|((a, _), (b, _))| (*a, *b))
+            .collect::<Vec<_>>();
+        res.sort();
+        res
+    }
+}
+
+impl<BV> HalfEdgeMesh<BV>
+where
+    BV: BoundingVolume<3> + Intersect<BV>,
+{
+    /// whether any two faces of the mesh cross each other,
+    /// see [`HalfEdgeMesh::self_intersections`]
+    pub fn is_mesh_self_intersect(&mut self) -> bool {
+        !self.self_intersections().is_empty()
+    }
+
+    /// every pair of faces crossing each other, sorted by face uuid
+    ///
+    /// faces sharing an edge or a vertex only count when they cross somewhere else
+    pub fn self_intersections(&mut self) -> Vec<SelfIntersection> {
+        if self.faces.is_empty() {
+            return vec![];
+        }
+        if self.bvh.is_none() {
+            self.build_bvh();
+        }
+        let bvh = self.bvh.as_ref().unwrap();
+        let bounds = self.vertices.values().fold(AABB::new(), |mut aabb, v| {
+            aabb.grow(&v.geometry.to_array());
+            aabb
+        });
+        let err = tolerance(&bounds);
+        let mut res = vec![];
+        for (uuid, face) in self.faces.iter() {
+            let (v1, v2, v3) = self.find_vertex_in_face(face);
+            let tri = [v1.geometry, v2.geometry, v3.geometry];
+            let ids = [v1.uuid, v2.uuid, v3.uuid];
+            let bv: BV = tri.bv();
+            // every pair is tested once, from the face with the smaller uuid
+            let candidates = bvh.intersect_by(
+                bv,
+                |bv, (other, other_tri)| uuid < other && bv.intersect(&other_tri.bv(), 0.),
+                |bv, node| bv.intersect(node, 0.),
+            );
+            for (other, other_tri) in candidates {
+                let (w1, w2, w3) = self.find_vertex_in_face(&self.faces[other]);
+                let shared = ids.map(|id| [w1.uuid, w2.uuid, w3.uuid].contains(&id));
+                if let Some(segment) = face_intersection(tri, *other_tri, shared, err) {
+                    res.push(SelfIntersection {
+                        faces: (*uuid, *other),
+                        segment,
+                    });
+                }
+            }
+        }
+        res.sort_by_key(|i| i.faces);
+        res
+    }
+}
+
+fn find_fan(fans: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while fans[root] != root {
+        root = fans[root];
+    }
+    fans[i] = root;
+    root
+}
+
+/// intersection of two faces, `shared` marks the vertices of `tri1` also used by `tri2`
+fn face_intersection(
+    tri1: [Vec3; 3],
+    tri2: [Vec3; 3],
+    shared: [bool; 3],
+    err: f64,
+) -> Option<[Vec3; 2]> {
+    let (tri1, tri2) = (tri1.map(|v| v.as_dvec3()), tri2.map(|v| v.as_dvec3()));
+    let points = match tri_tri_intersect(tri1, tri2, err) {
+        TriTriIntersectStatus::NotIntersect => return None,
+        TriTriIntersectStatus::Intersect(p, q) => std::iter::once(p).chain(q).collect(),
+        TriTriIntersectStatus::Coplanar(points) => points,
+    };
+    let on_shared = |topo: IntersectTopo| match topo {
+        IntersectTopo::Vertex(i) => shared[i as usize],
+        IntersectTopo::Edge(i, j) => shared[i as usize] && shared[j as usize],
+        IntersectTopo::Face => false,
+    };
+    // the same face twice always counts
+    if !shared.iter().all(|s| *s) && points.iter().all(|p| on_shared(p.t1)) {
+        return None;
+    }
+    let mut segment = [points[0].p, points[0].p];
+    for (i, a) in points.iter().enumerate() {
+        for b in points[i + 1..].iter() {
+            if a.p.distance_squared(b.p) > segment[0].distance_squared(segment[1]) {
+                segment = [a.p, b.p];
+            }
+        }
+    }
+    Some(segment.map(|p| p.as_vec3()))
 }
 
 impl<BV> HalfEdgeMesh<BV>
@@ -50,15 +181,75 @@ where
         self.is_mesh_watertight() && self.is_vertex_manifold() && self.is_edge_manifold()
     }
 
-    // TODO 
     /// https://cs184.eecs.berkeley.edu/uploads/lectures/10_mesh-rep/images/slide_018.jpg
     pub fn is_vertex_manifold(&self) -> bool {
-        todo!()
+        self.non_manifold_vertices().is_empty()
     }
 
-    // TODO 
+    /// every edge is shared by at most two faces
     pub fn is_edge_manifold(&self) -> bool {
-        todo!()
+        self.non_manifold_edges().is_empty()
+    }
+
+    /// vertices whose faces do not form a single fan, like the center of a bowtie
+    pub fn non_manifold_vertices(&self) -> Vec<Uuid> {
+        // faces around every vertex, with the other two vertices of the face
+        let mut corners = HashMap::<Uuid, Vec<[Uuid; 2]>>::new();
+        for e in self.half_edges.values() {
+            let from = self.start_vertex(e);
+            let next = self.half_edges.get(&e.next_half_edge);
+            let (Some(from), Some(next)) = (from, next) else {
+                continue;
+            };
+            corners
+                .entry(from)
+                .or_default()
+                .push([e.next_vertex, next.next_vertex]);
+        }
+        let mut res = vec![];
+        for (vertex, faces) in corners {
+            // faces sharing an edge with the vertex belong to the same fan
+            let mut fans = (0..faces.len()).collect::<Vec<_>>();
+            let mut first = HashMap::<Uuid, usize>::new();
+            for (i, neighbors) in faces.iter().enumerate() {
+                for w in neighbors {
+                    let j = *first.entry(*w).or_insert(i);
+                    let (a, b) = (find_fan(&mut fans, i), find_fan(&mut fans, j));
+                    fans[a] = b;
+                }
+            }
+            let root = find_fan(&mut fans, 0);
+            if (0..faces.len()).any(|i| find_fan(&mut fans, i) != root) {
+                res.push(vertex);
+            }
+        }
+        res.sort();
+        res
+    }
+
+    /// half edges on edges shared by more than two faces
+    pub fn non_manifold_edges(&self) -> Vec<Uuid> {
+        let mut edges = HashMap::<(Uuid, Uuid), Vec<Uuid>>::new();
+        for e in self.half_edges.values() {
+            let Some(from) = self.start_vertex(e) else {
+                continue;
+            };
+            let key = (from.min(e.next_vertex), from.max(e.next_vertex));
+            edges.entry(key).or_default().push(e.uuid);
+        }
+        let mut res = edges
+            .into_values()
+            .filter(|half_edges| half_edges.len() > 2)
+            .flatten()
+            .collect::<Vec<_>>();
+        res.sort();
+        res
+    }
+
+    fn start_vertex(&self, e: &HalfEdge) -> Option<Uuid> {
+        self.half_edges
+            .get(&e.pre_half_edge)
+            .map(|pre| pre.next_vertex)
     }
 
     pub fn is_mesh_watertight(&self) -> bool {
@@ -70,30 +261,6 @@ where
         true
     }
 
-    // TODO: implement
-    pub fn is_mesh_self_intersect(&mut self) -> bool {
-        if self.bvh.is_none() {
-            self.build_bvh();
-        }
-        let bvh = self.bvh.as_ref().unwrap();
-        for (_, face) in self.faces.iter() {
-            let intersects = bvh.intersect_by(
-                face,
-                |f, (face_id, b)| {
-                    if face_id == &f.uuid {
-                        return false;
-                    }
-                    let (v1 , v2, v3) = self.find_vertex_in_face(f);
-                    // v1.geometry
-
-                    todo!()
-                },
-                |a, b| false,
-            );
-        }
-        todo!()
-    }
-
     pub(super) fn insert_vertex(&mut self, v: Vertex) {
         self.vertices.insert(v.uuid, v);
     }
@@ -288,6 +455,7 @@ where
         self.vertices.iter_mut().for_each(|(_, v)| {
             v.geometry = mat4.transform_point3(v.geometry.clone());
         });
+        self.refit_bvh(None);
     }
 
     pub fn translate(&mut self, translation: Vec3) {
@@ -314,11 +482,23 @@ where
                 (face.uuid, [v1.geometry, v2.geometry, v3.geometry])
             })
             .collect::<Vec<_>>();
-        Bvh::<3, BV, _>::build(BuildBvhOption::default(), p);
+        self.bvh = Some(Bvh::<3, BV, _>::build(BuildBvhOption::default(), p));
     }
 
-    // TODO find intersect triangles
-    fn find_intersect(&self, other: &Self) -> Vec<(Uuid, Uuid)> {
-        todo!()
+    /// move the triangles in the bvh to the current vertex positions and refit it,
+    /// call it after editing vertex geometry. faces must not be added or removed since the bvh was built.
+    /// with `max_degradation` set, the bvh is rebuilt once refitting makes it that many times as expensive
+    pub fn refit_bvh(&mut self, max_degradation: Option<f32>) {
+        let Some(mut bvh) = self.bvh.take() else {
+            return;
+        };
+        for (uuid, triangle) in bvh.primitives_mut() {
+            if let Some(face) = self.faces.get(uuid) {
+                let (v1, v2, v3) = self.find_vertex_in_face(face);
+                *triangle = [v1.geometry, v2.geometry, v3.geometry];
+            }
+        }
+        bvh.refit_triangles(|(_, triangle)| *triangle, max_degradation);
+        self.bvh = Some(bvh);
     }
 }
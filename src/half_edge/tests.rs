@@ -130,6 +130,82 @@ mod half_edge_mesh_test {
         assert_eq!(mesh.faces.len(), 12);
     }
 
+    fn index_mesh(vertices: &[[f32; 3]], triangles: &[[usize; 3]]) -> IndexMesh {
+        let mut mesh = IndexMesh::new();
+        mesh.vertices = vertices.iter().map(|v| Vec3::from(*v)).collect();
+        mesh.triangles = triangles
+            .iter()
+            .map(|t| crate::index_mesh::IndexTriangle(t[0], t[1], t[2]))
+            .collect();
+        mesh
+    }
+
+    #[test]
+    fn test_non_manifold_vertices() {
+        // open fan around the center is fine
+        let vertices = [
+            [0., 0., 0.],
+            [1., 0., 0.],
+            [0., 1., 0.],
+            [-1., 0., 0.],
+            [0., -1., 0.],
+            [1., -1., 0.],
+        ];
+        let mesh = HalfEdgeMesh::<AABB<3>>::from(&index_mesh(&vertices, &[[0, 1, 2], [0, 2, 3]]));
+        assert!(mesh.is_vertex_manifold());
+        assert!(mesh.non_manifold_vertices().is_empty());
+
+        // bowtie, two fans meet at the center
+        let mesh = HalfEdgeMesh::<AABB<3>>::from(&index_mesh(&vertices, &[[0, 1, 2], [0, 3, 4]]));
+        assert!(!mesh.is_vertex_manifold());
+        assert!(mesh.is_edge_manifold());
+        let center = mesh
+            .vertices
+            .values()
+            .find(|v| v.geometry == Vec3::ZERO)
+            .unwrap();
+        assert_eq!(mesh.non_manifold_vertices(), vec![center.uuid]);
+
+        // fans joined again by a third face
+        let mesh = HalfEdgeMesh::<AABB<3>>::from(&index_mesh(
+            &vertices,
+            &[[0, 1, 2], [0, 3, 4], [0, 4, 5]],
+        ));
+        assert_eq!(mesh.non_manifold_vertices().len(), 1);
+        let mesh = HalfEdgeMesh::<AABB<3>>::from(&index_mesh(
+            &vertices,
+            &[[0, 1, 2], [0, 3, 4], [0, 4, 1]],
+        ));
+        assert!(mesh.is_vertex_manifold());
+    }
+
+    #[test]
+    fn test_non_manifold_edges() {
+        // three faces share the edge (0, 1)
+        let vertices = [
+            [0., 0., 0.],
+            [1., 0., 0.],
+            [0., 1., 0.],
+            [0., -1., 0.],
+            [0., 0., 1.],
+        ];
+        let mesh = HalfEdgeMesh::<AABB<3>>::from(&index_mesh(&vertices, &[[0, 1, 2], [1, 0, 3]]));
+        assert!(mesh.is_edge_manifold());
+        let mesh = HalfEdgeMesh::<AABB<3>>::from(&index_mesh(
+            &vertices,
+            &[[0, 1, 2], [1, 0, 3], [1, 0, 4]],
+        ));
+        assert!(!mesh.is_edge_manifold());
+        assert!(!mesh.is_mesh_manifold());
+        let edges = mesh.non_manifold_edges();
+        assert_eq!(edges.len(), 3);
+        for e in edges {
+            let e = mesh.half_edges.get(&e).unwrap();
+            let end = mesh.vertices.get(&e.next_vertex).unwrap();
+            assert!(end.geometry == Vec3::ZERO || end.geometry == Vec3::X);
+        }
+    }
+
     fn construct_half_edge_mesh() -> HalfEdgeMesh {
         let mut f = std::fs::File::open("assets/bunny.stl").unwrap();
         let mesh = IndexMesh::from_stl(&mut f).unwrap();
@@ -285,4 +361,142 @@ mod half_edge_mesh_test {
 
         println!("{:?}", index_mesh);
     }
+
+    fn two_boxes(offset: Vec3) -> HalfEdgeMesh {
+        let mut a = Box { size: 1. }.to_mesh();
+        let mut b = Box { size: 1. }.to_mesh();
+        b.translate(offset);
+        let n = a.vertices.len();
+        a.vertices.append(&mut b.vertices);
+        a.triangles.extend(
+            b.triangles
+                .iter()
+                .map(|t| crate::index_mesh::IndexTriangle(t.0 + n, t.1 + n, t.2 + n)),
+        );
+        HalfEdgeMesh::from(&a)
+    }
+
+    #[test]
+    fn test_self_intersect_closed_mesh() {
+        let mut mesh: HalfEdgeMesh = HalfEdgeMesh::from(&Box { size: 1. }.to_mesh());
+        assert!(!mesh.is_mesh_self_intersect());
+        assert!(mesh.bvh.is_some());
+
+        // apart from each other
+        let mut mesh = two_boxes(Vec3::new(2., 0., 0.));
+        assert!(mesh.self_intersections().is_empty());
+    }
+
+    #[test]
+    fn test_self_intersect_overlapping_boxes() {
+        let mut mesh = two_boxes(Vec3::new(0.3, 0.2, 0.1));
+        assert!(mesh.is_mesh_self_intersect());
+        let report = mesh.self_intersections();
+        assert!(!report.is_empty());
+        for i in report.iter() {
+            assert!(i.faces.0 < i.faces.1);
+            // segments lie inside both boxes
+            for p in i.segment {
+                assert!(p.cmpge(Vec3::new(-0.2, -0.3, -0.4) - 1e-5).all());
+                assert!(p.cmple(Vec3::splat(0.5) + 1e-5).all());
+            }
+        }
+        // the faces x = 0.5 of a and x = -0.2 of b cross the faces of the other box
+        assert!(report
+            .iter()
+            .any(|i| (i.segment[0].x - 0.5).abs() < 1e-6 && (i.segment[1].x - 0.5).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_self_intersect_bsphere() {
+        let mesh = IndexMesh::from(&two_boxes(Vec3::new(0.3, 0.2, 0.1)));
+        let mut mesh = HalfEdgeMesh::<crate::bv::Bsphere<3>>::from(&mesh);
+        assert!(mesh.is_mesh_self_intersect());
+        let mesh = IndexMesh::from(&two_boxes(Vec3::new(2., 0., 0.)));
+        let mut mesh = HalfEdgeMesh::<crate::bv::Bsphere<3>>::from(&mesh);
+        assert!(!mesh.is_mesh_self_intersect());
+    }
+
+    #[test]
+    fn test_self_intersect_shared_topology() {
+        // neighbour faces sharing an edge or a vertex are fine
+        let mut mesh = IndexMesh::new();
+        mesh.vertices = vec![
+            Vec3::new(0., 0., 0.),
+            Vec3::new(1., 0., 0.),
+            Vec3::new(0., 1., 0.),
+            Vec3::new(1., 1., 1.),
+            Vec3::new(-1., -1., 0.5),
+            Vec3::new(-1., 0., 0.5),
+        ];
+        mesh.triangles = vec![
+            crate::index_mesh::IndexTriangle(0, 1, 2),
+            crate::index_mesh::IndexTriangle(2, 1, 3),
+            crate::index_mesh::IndexTriangle(0, 5, 4),
+        ];
+        let mut half_edge_mesh: HalfEdgeMesh = HalfEdgeMesh::from(&mesh);
+        assert!(!half_edge_mesh.is_mesh_self_intersect());
+
+        // a face folded back onto its neighbour overlaps it
+        mesh.vertices[3] = Vec3::new(0.2, 0.2, 0.);
+        let mut half_edge_mesh: HalfEdgeMesh = HalfEdgeMesh::from(&mesh);
+        let report = half_edge_mesh.self_intersections();
+        assert_eq!(report.len(), 1);
+
+        // a face sharing a vertex but passing through the other face
+        mesh.vertices[3] = Vec3::new(1., 1., 1.);
+        mesh.vertices[4] = Vec3::new(0.5, 0.2, -1.);
+        mesh.vertices[5] = Vec3::new(0.2, 0.5, 1.);
+        let mut half_edge_mesh: HalfEdgeMesh = HalfEdgeMesh::from(&mesh);
+        let report = half_edge_mesh.self_intersections();
+        assert_eq!(report.len(), 1);
+        let [p, q] = report[0].segment;
+        assert!(p.distance(Vec3::ZERO) < 1e-6 || q.distance(Vec3::ZERO) < 1e-6);
+        assert!(p.distance(q) > 0.1);
+    }
+
+    #[test]
+    fn test_self_intersect_after_transform() {
+        let mut mesh = two_boxes(Vec3::new(2., 0., 0.));
+        assert!(!mesh.is_mesh_self_intersect());
+        assert!(mesh.bvh.is_some());
+        mesh.scale(Vec3::new(3., 1., 1.));
+        assert!(mesh.bvh.is_some());
+        assert!(!mesh.is_mesh_self_intersect());
+
+        // pull the second box into the first one, the refitted bvh finds the overlap
+        for v in mesh.vertices.values_mut().filter(|v| v.geometry.x > 3.) {
+            v.geometry.x -= 5.;
+        }
+        mesh.refit_bvh(Some(2.));
+        assert!(mesh.is_mesh_self_intersect());
+    }
+
+    #[test]
+    fn test_find_intersect() {
+        let mut a = HalfEdgeMesh::from(&Box { size: 1. }.to_mesh());
+        let mut b = HalfEdgeMesh::from(&Box { size: 1. }.to_mesh());
+        let identity = glam::Mat4::IDENTITY;
+        let apart = glam::Mat4::from_translation(Vec3::new(1.5, 0., 0.));
+        assert!(a.find_intersect(identity, &mut b, apart).is_empty());
+
+        // the corner of b pokes into the face x = 0.5 of a
+        let rotation = glam::Quat::from_rotation_arc(Vec3::splat(1.).normalize(), Vec3::NEG_X);
+        let poke = glam::Mat4::from_rotation_translation(
+            rotation,
+            Vec3::new(0.5 + 3f32.sqrt() / 2. - 0.1, 0., 0.),
+        );
+        let pairs = a.find_intersect(identity, &mut b, poke);
+        assert!(!pairs.is_empty());
+        for (fa, fb) in pairs.iter() {
+            assert!(a.faces.contains_key(fa));
+            assert!(b.faces.contains_key(fb));
+            // only faces of a at x = 0.5
+            let (v1, v2, v3) = a.find_vertex_in_face(&a.faces[fa]);
+            assert!([v1, v2, v3].iter().all(|v| v.geometry.x == 0.5));
+        }
+        // same result with both meshes moved together
+        let moved = glam::Mat4::from_rotation_y(0.7);
+        assert_eq!(a.find_intersect(moved, &mut b, moved * poke), pairs);
+    }
 }
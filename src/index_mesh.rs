@@ -1,9 +1,10 @@
-use std::{collections::HashMap, fmt::Debug, slice::Iter};
+use std::{collections::HashMap, fmt::Debug, ops::Range, slice::Iter};
 
 use anyhow::Ok;
-use glam::{Mat4, Quat, Vec3};
+use glam::{Mat3, Mat4, Quat, Vec2, Vec3, Vec4};
 
 use crate::{
+    algorithms::triangulate_polygon,
     bv::BoundingVolume,
     bvh::{build_options::BuildBvhOption, Bvh},
     half_edge::HalfEdgeMesh,
@@ -14,6 +15,27 @@ use crate::{
 #[cfg(test)]
 mod index_mesh_tests;
 
+mod format;
+mod gltf;
+mod obj;
+mod off;
+mod ply;
+mod stl;
+mod surface;
+mod three_mf;
+mod weld;
+
+pub use format::{FormatRegistry, MeshFormat, ReadSeek, WriteSeek};
+pub use gltf::GltfNode;
+pub use ply::PlyFormat;
+pub use stl::{StlFormat, StlOption};
+pub use surface::SurfaceOption;
+pub use three_mf::{ThreeMfItem, ThreeMfModel, ThreeMfObject, ThreeMfUnit};
+pub use weld::WeldReport;
+
+/// bvh over the triangle indices of a mesh, built by [`IndexMesh::build_aabb_bvh_indexed`]
+pub type MeshBvh = Bvh<3, AABB<3>, usize>;
+
 #[derive(Clone, Copy)]
 pub struct IndexTriangle(pub usize, pub usize, pub usize);
 
@@ -24,10 +46,53 @@ impl Debug for IndexTriangle {
     }
 }
 
-#[derive(Debug)]
+/// consecutive triangles sharing a name and a material, like an obj group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriangleGroup {
+    /// name of the object the group belongs to
+    pub object: String,
+    /// name of the group
+    pub name: String,
+    /// name of the material
+    pub material: Option<String>,
+    /// indices of the triangles in the group
+    pub triangles: Range<usize>,
+}
+
+/// a named material with its mtl statements, like `Kd 1 0 0` or `map_Kd wood.png`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Material {
+    /// name used by `usemtl`
+    pub name: String,
+    /// statements as (keyword, rest of the line)
+    pub properties: Vec<(String, String)>,
+}
+
+impl Material {
+    /// value of the first statement with `keyword`
+    pub fn property(&self, keyword: &str) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|(k, _)| k == keyword)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct IndexMesh {
     pub(crate) vertices: Vec<Vec3>,
     pub(crate) triangles: Vec<IndexTriangle>,
+    /// per-vertex normals
+    pub(crate) normals: Option<Vec<Vec3>>,
+    /// per-vertex rgba colors in [0, 1]
+    pub(crate) colors: Option<Vec<Vec4>>,
+    /// extra per-vertex scalars by name, in the order they were added
+    pub(crate) attributes: Vec<(String, Vec<f64>)>,
+    /// per-vertex texture coordinates
+    pub(crate) uvs: Option<Vec<Vec2>>,
+    /// named triangle ranges, they do not overlap
+    pub(crate) groups: Vec<TriangleGroup>,
+    pub(crate) materials: Vec<Material>,
 }
 
 impl<BV> From<&HalfEdgeMesh<BV>> for IndexMesh
@@ -58,6 +123,12 @@ impl IndexMesh {
         IndexMesh {
             vertices: vec![],
             triangles: vec![],
+            normals: None,
+            colors: None,
+            attributes: vec![],
+            uvs: None,
+            groups: vec![],
+            materials: vec![],
         }
     }
 
@@ -69,75 +140,169 @@ impl IndexMesh {
         self.triangles.iter()
     }
 
-    pub fn from_stl<F: std::io::Read + std::io::Seek>(f: &mut F) -> anyhow::Result<Self> {
-        let mut res = IndexMesh::new();
-        let mesh = stl_io::read_stl(f)?;
-        mesh.vertices.into_iter().for_each(|vertex| {
-            res.vertices
-                .push(Vec3::new(vertex[0], vertex[1], vertex[2]));
-        });
-        mesh.faces.into_iter().for_each(|face| {
-            res.triangles.push(IndexTriangle(
-                face.vertices[0],
-                face.vertices[1],
-                face.vertices[2],
-            ));
-        });
-        Ok(res)
-    }
-
-    pub fn to_stl<F: std::io::Write>(&self, f: &mut F) -> anyhow::Result<()> {
-        let mut triangles = Vec::new();
-        for triangle in self.triangles.iter() {
-            let normal = (self.vertices[triangle.1] - self.vertices[triangle.0])
-                .cross(self.vertices[triangle.2] - self.vertices[triangle.0])
-                .normalize();
-            triangles.push(stl_io::Triangle {
-                normal: stl_io::Vertex::new(normal.into()),
-                vertices: [
-                    stl_io::Vertex::new(self.vertices[triangle.0].into()),
-                    stl_io::Vertex::new(self.vertices[triangle.1].into()),
-                    stl_io::Vertex::new(self.vertices[triangle.2].into()),
-                ],
-            });
-        }
-        stl_io::write_stl(f, triangles.iter())?;
+    /// per-vertex normals, if the mesh has them
+    pub fn normals(&self) -> Option<&[Vec3]> {
+        self.normals.as_deref()
+    }
+
+    /// per-vertex rgba colors in [0, 1], if the mesh has them
+    pub fn colors(&self) -> Option<&[Vec4]> {
+        self.colors.as_deref()
+    }
+
+    /// values of the per-vertex attribute `name`
+    pub fn attribute(&self, name: &str) -> Option<&[f64]> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, values)| values.as_slice())
+    }
+
+    /// all per-vertex attributes as (name, values)
+    pub fn attributes(&self) -> impl Iterator<Item = (&str, &[f64])> {
+        self.attributes
+            .iter()
+            .map(|(name, values)| (name.as_str(), values.as_slice()))
+    }
+
+    /// per-vertex texture coordinates, if the mesh has them
+    pub fn uvs(&self) -> Option<&[Vec2]> {
+        self.uvs.as_deref()
+    }
+
+    /// named triangle ranges with their materials
+    pub fn groups(&self) -> &[TriangleGroup] {
+        &self.groups
+    }
+
+    /// materials the groups refer to by name
+    pub fn materials(&self) -> &[Material] {
+        &self.materials
+    }
+
+    /// one texture coordinate per vertex
+    pub fn set_uvs(&mut self, uvs: Vec<Vec2>) -> anyhow::Result<()> {
+        self.check_vertex_count(uvs.len())?;
+        self.uvs = Some(uvs);
         Ok(())
     }
 
-    pub fn from_obj<F: std::io::Read>(f: &mut F) -> anyhow::Result<Self> {
-        let data = obj::ObjData::load_buf(f)?;
-        let mut triangles = vec![];
-        for obj in data.objects {
-            for g in obj.groups {
-                for p in g.polys {
-                    let mut iter = p.0.iter();
-                    triangles.push(IndexTriangle(
-                        iter.next().unwrap().0,
-                        iter.next().unwrap().0,
-                        iter.next().unwrap().0,
-                    ));
-                }
+    /// groups must not overlap and stay inside the triangles
+    pub fn set_groups(&mut self, mut groups: Vec<TriangleGroup>) -> anyhow::Result<()> {
+        groups.sort_by_key(|g| g.triangles.start);
+        let mut end = 0;
+        for g in groups.iter() {
+            if g.triangles.start < end || g.triangles.end > self.triangles.len() {
+                anyhow::bail!("group {} overlaps or is out of the triangles", g.name);
             }
+            end = g.triangles.end;
         }
+        self.groups = groups;
+        Ok(())
+    }
 
-        Ok(IndexMesh {
-            vertices: data
-                .position
-                .into_iter()
-                .map(|p| Vec3::from_array(p))
-                .collect::<Vec<_>>(),
-            triangles,
-        })
+    /// materials the groups refer to by name
+    pub fn set_materials(&mut self, materials: Vec<Material>) {
+        self.materials = materials;
+    }
+
+    /// one normal per vertex
+    pub fn set_normals(&mut self, normals: Vec<Vec3>) -> anyhow::Result<()> {
+        self.check_vertex_count(normals.len())?;
+        self.normals = Some(normals);
+        Ok(())
     }
 
-    pub fn to_obj<F: std::io::Write>(&self, f: &mut F) -> anyhow::Result<()> {
-        for v in self.vertices.iter() {
-            writeln!(f, "v {} {} {}", v.x, v.y, v.z)?;
+    /// one rgba color in [0, 1] per vertex
+    pub fn set_colors(&mut self, colors: Vec<Vec4>) -> anyhow::Result<()> {
+        self.check_vertex_count(colors.len())?;
+        self.colors = Some(colors);
+        Ok(())
+    }
+
+    /// add or replace the per-vertex attribute `name`
+    pub fn set_attribute(&mut self, name: &str, values: Vec<f64>) -> anyhow::Result<()> {
+        self.check_vertex_count(values.len())?;
+        match self.attributes.iter_mut().find(|(n, _)| n == name) {
+            Some((_, old)) => *old = values,
+            None => self.attributes.push((name.to_string(), values)),
         }
+        Ok(())
+    }
 
-        for t in self.triangles.iter() {
-            writeln!(f, "f {} {} {}", t.0 + 1, t.1 + 1, t.2 + 1)?;
+    /// append the vertices and triangles of `other`.
+    /// normals, colors and attributes are kept only when both meshes have them
+    pub(crate) fn merge(&mut self, other: IndexMesh) {
+        if self.vertices.is_empty() && self.triangles.is_empty() {
+            *self = other;
+            return;
+        }
+        let IndexMesh {
+            vertices,
+            triangles,
+            normals,
+            colors,
+            attributes,
+            uvs,
+            groups,
+            materials,
+        } = other;
+        let offset = self.vertices.len();
+        let triangle_offset = self.triangles.len();
+        self.vertices.extend(vertices);
+        self.triangles.extend(
+            triangles
+                .into_iter()
+                .map(|t| IndexTriangle(t.0 + offset, t.1 + offset, t.2 + offset)),
+        );
+        self.normals = match (self.normals.take(), normals) {
+            (Some(mut a), Some(b)) => {
+                a.extend(b);
+                Some(a)
+            }
+            _ => None,
+        };
+        self.colors = match (self.colors.take(), colors) {
+            (Some(mut a), Some(b)) => {
+                a.extend(b);
+                Some(a)
+            }
+            _ => None,
+        };
+        self.uvs = match (self.uvs.take(), uvs) {
+            (Some(mut a), Some(b)) => {
+                a.extend(b);
+                Some(a)
+            }
+            _ => None,
+        };
+        self.groups
+            .extend(groups.into_iter().map(|g| TriangleGroup {
+                triangles: g.triangles.start + triangle_offset..g.triangles.end + triangle_offset,
+                ..g
+            }));
+        for material in materials {
+            if !self.materials.iter().any(|m| m.name == material.name) {
+                self.materials.push(material);
+            }
+        }
+        self.attributes = std::mem::take(&mut self.attributes)
+            .into_iter()
+            .filter_map(|(name, mut values)| {
+                let (_, other) = attributes.iter().find(|(n, _)| *n == name)?;
+                values.extend(other);
+                Some((name, values))
+            })
+            .collect();
+    }
+
+    fn check_vertex_count(&self, count: usize) -> anyhow::Result<()> {
+        if count != self.vertices.len() {
+            anyhow::bail!(
+                "expect {} values, one per vertex, got {}",
+                self.vertices.len(),
+                count
+            );
         }
         Ok(())
     }
@@ -159,6 +324,28 @@ impl IndexMesh {
         a.transfrom::<&IndexTriangle>()
     }
 
+    /// bvh over triangle indices, it does not borrow the mesh so it can be kept while the vertices
+    /// move and refitted by [`IndexMesh::refit_aabb_bvh`]
+    pub fn build_aabb_bvh_indexed(&self, option: BuildBvhOption) -> MeshBvh {
+        let a = (0..self.triangles.len())
+            .map(|i| (i, self.triangle_vertices(i)))
+            .collect::<Vec<_>>();
+        Bvh::<3, AABB<3>, _>::build(option, a).transfrom_by(|(i, _)| i)
+    }
+
+    /// refit `bvh` built by [`IndexMesh::build_aabb_bvh_indexed`] to the current vertices,
+    /// the triangles must be the same as when it was built.
+    /// with `max_degradation` set, the bvh is rebuilt once refitting makes it that many times as expensive,
+    /// return true if it was rebuilt
+    pub fn refit_aabb_bvh(&self, bvh: &mut MeshBvh, max_degradation: Option<f32>) -> bool {
+        bvh.refit_triangles(|&i| self.triangle_vertices(i), max_degradation)
+    }
+
+    pub(crate) fn triangle_vertices(&self, index: usize) -> [Vec3; 3] {
+        let t = &self.triangles[index];
+        [self.vertices[t.0], self.vertices[t.1], self.vertices[t.2]]
+    }
+
     /// ## Example
     ///
     ///
@@ -182,6 +369,7 @@ impl IndexMesh {
         let mut indices = vec![];
         let mut positions = vec![];
         let mut normals = vec![];
+        let mut colors = self.colors.as_ref().map(|_| vec![]);
         for tri in self.triangles() {
             let v0 = vertices[tri.0];
             let v1 = vertices[tri.1];
@@ -193,6 +381,11 @@ impl IndexMesh {
             normals.push(n.to_array());
             normals.push(n.to_array());
             normals.push(n.to_array());
+            if let (Some(colors), Some(vertex_colors)) = (colors.as_mut(), self.colors.as_ref()) {
+                for i in [tri.0, tri.1, tri.2] {
+                    colors.push(vertex_colors[i].truncate().to_array());
+                }
+            }
             indices.push(indices_count);
             indices_count += 1;
             indices.push(indices_count);
@@ -204,7 +397,7 @@ impl IndexMesh {
             positions,
             normals,
             indices,
-            colors: None,
+            colors,
         }
     }
 
@@ -214,8 +407,14 @@ impl IndexMesh {
 
     pub fn transfrom(&mut self, mat4: Mat4) {
         self.vertices.iter_mut().for_each(|v| {
-            *v = mat4.transform_vector3(v.clone());
+            *v = mat4.transform_point3(*v);
         });
+        if let Some(normals) = self.normals.as_mut() {
+            let mat3 = Mat3::from_mat4(mat4).inverse().transpose();
+            normals.iter_mut().for_each(|n| {
+                *n = (mat3 * *n).normalize_or_zero();
+            });
+        }
     }
 
     pub fn translate(&mut self, translation: Vec3) {
@@ -234,6 +433,59 @@ impl IndexMesh {
     }
 }
 
+/// triangles of a polygon face given by vertex indices, polygons with more than 3 vertices are
+/// ear clipped on their plane. repeated corners are skipped, faces without area are an error
+pub(crate) fn triangulate_face(
+    vertices: &[Vec3],
+    face: &[usize],
+) -> anyhow::Result<Vec<IndexTriangle>> {
+    Ok(triangulate_corners(vertices, face)?
+        .into_iter()
+        .map(|[a, b, c]| IndexTriangle(face[a], face[b], face[c]))
+        .collect())
+}
+
+/// like [`triangulate_face`], but the triangles index the corners of the face
+pub(crate) fn triangulate_corners(
+    vertices: &[Vec3],
+    face: &[usize],
+) -> anyhow::Result<Vec<[usize; 3]>> {
+    if let Some(i) = face.iter().find(|&&i| i >= vertices.len()) {
+        anyhow::bail!("vertex index {} is out of {} vertices", i, vertices.len());
+    }
+    let mut distinct = face.to_vec();
+    distinct.sort_unstable();
+    distinct.dedup();
+    if distinct.len() < 3 {
+        anyhow::bail!("face {:?} has less than 3 distinct vertices", face);
+    }
+    if face.len() == 3 {
+        return Ok(vec![[0, 1, 2]]);
+    }
+    // corners that repeat the vertex before them add nothing to the polygon
+    let corners = (0..face.len())
+        .filter(|&k| face[k] != face[(k + face.len() - 1) % face.len()])
+        .collect::<Vec<_>>();
+    if corners.len() == 3 {
+        return Ok(vec![[corners[0], corners[1], corners[2]]]);
+    }
+    let points = corners
+        .iter()
+        .map(|&k| vertices[face[k]].as_dvec3())
+        .collect::<Vec<_>>();
+    let triangles = triangulate_polygon(&points)
+        .ok_or_else(|| anyhow::anyhow!("face {:?} has no area", face))?
+        .into_iter()
+        .map(|t| t.map(|k| corners[k]))
+        // a vertex the polygon passes twice gives triangles without area
+        .filter(|[a, b, c]| face[*a] != face[*b] && face[*b] != face[*c] && face[*c] != face[*a])
+        .collect::<Vec<_>>();
+    if triangles.is_empty() {
+        anyhow::bail!("face {:?} has no area", face);
+    }
+    Ok(triangles)
+}
+
 pub struct RenderableMesh {
     pub positions: Vec<[f32; 3]>,
     pub normals: Vec<[f32; 3]>,
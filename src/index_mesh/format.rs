@@ -0,0 +1,241 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, Context};
+
+use super::{off::sniff_off, stl::is_binary_stl, IndexMesh, PlyFormat};
+
+/// number of bytes at the start of a file given to [`MeshFormat::sniff`]
+const SNIFF_LEN: usize = 512;
+
+/// a readable and seekable stream
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+/// a writable and seekable stream
+pub trait WriteSeek: Write + Seek {}
+
+impl<T: Write + Seek> WriteSeek for T {}
+
+/// a mesh file format that [`FormatRegistry`] can dispatch to
+pub trait MeshFormat: Send + Sync {
+    /// short name used in error messages
+    fn name(&self) -> &str;
+
+    /// lowercase file extensions without the dot
+    fn extensions(&self) -> &[&str];
+
+    /// whether a file with this start and length looks like this format
+    fn sniff(&self, head: &[u8], len: u64) -> bool;
+
+    /// read a mesh from the start of `f`
+    fn read(&self, f: &mut dyn ReadSeek) -> anyhow::Result<IndexMesh>;
+
+    /// write `mesh` to `f`
+    fn write(&self, mesh: &IndexMesh, f: &mut dyn WriteSeek) -> anyhow::Result<()>;
+}
+
+/// formats the crate reads and writes
+#[derive(Clone, Copy)]
+enum Builtin {
+    Obj,
+    Stl,
+    Ply,
+    Off,
+    Gltf,
+    Glb,
+    ThreeMf,
+}
+
+impl MeshFormat for Builtin {
+    fn name(&self) -> &str {
+        match self {
+            Builtin::Obj => "obj",
+            Builtin::Stl => "stl",
+            Builtin::Ply => "ply",
+            Builtin::Off => "off",
+            Builtin::Gltf => "gltf",
+            Builtin::Glb => "glb",
+            Builtin::ThreeMf => "3mf",
+        }
+    }
+
+    fn extensions(&self) -> &[&str] {
+        match self {
+            Builtin::Obj => &["obj"],
+            Builtin::Stl => &["stl"],
+            Builtin::Ply => &["ply"],
+            Builtin::Off => &["off"],
+            Builtin::Gltf => &["gltf"],
+            Builtin::Glb => &["glb"],
+            Builtin::ThreeMf => &["3mf"],
+        }
+    }
+
+    fn sniff(&self, head: &[u8], len: u64) -> bool {
+        let text = || String::from_utf8_lossy(head);
+        match self {
+            // obj has no signature, only its keywords
+            Builtin::Obj => text()
+                .lines()
+                .map(str::trim)
+                .find(|l| !l.is_empty() && !l.starts_with('#'))
+                .and_then(|l| l.split_whitespace().next())
+                .is_some_and(|keyword| {
+                    ["v", "vt", "vn", "f", "o", "g", "mtllib", "usemtl"].contains(&keyword)
+                }),
+            Builtin::Stl => {
+                is_binary_stl(head, len)
+                    || text().trim_start().starts_with("solid")
+                        && (text().contains("facet") || text().contains("endsolid"))
+            }
+            Builtin::Ply => head.starts_with(b"ply\n") || head.starts_with(b"ply\r\n"),
+            Builtin::Off => sniff_off(head),
+            Builtin::Gltf => text().trim_start().starts_with('{') && text().contains("\"asset\""),
+            Builtin::Glb => head.starts_with(b"glTF"),
+            Builtin::ThreeMf => head.starts_with(b"PK\x03\x04"),
+        }
+    }
+
+    fn read(&self, mut f: &mut dyn ReadSeek) -> anyhow::Result<IndexMesh> {
+        match self {
+            Builtin::Obj => IndexMesh::from_obj(&mut f),
+            Builtin::Stl => IndexMesh::from_stl(&mut f),
+            Builtin::Ply => IndexMesh::from_ply(&mut f),
+            Builtin::Off => IndexMesh::from_off(&mut f),
+            Builtin::Gltf | Builtin::Glb => IndexMesh::from_gltf(&mut f),
+            Builtin::ThreeMf => IndexMesh::from_3mf(&mut f),
+        }
+    }
+
+    fn write(&self, mesh: &IndexMesh, mut f: &mut dyn WriteSeek) -> anyhow::Result<()> {
+        match self {
+            Builtin::Obj => mesh.to_obj(&mut f),
+            Builtin::Stl => mesh.to_stl(&mut f),
+            Builtin::Ply => mesh.to_ply(&mut f, PlyFormat::BinaryLittleEndian),
+            Builtin::Off => mesh.to_off(&mut f),
+            Builtin::Gltf => mesh.to_gltf(&mut f),
+            Builtin::Glb => mesh.to_glb(&mut f),
+            Builtin::ThreeMf => mesh.to_3mf(&mut f),
+        }
+    }
+}
+
+/// mesh formats looked up by file extension and by content
+pub struct FormatRegistry {
+    formats: Vec<Box<dyn MeshFormat>>,
+}
+
+impl Default for FormatRegistry {
+    /// every format of the crate. ply is saved as binary, stl as binary and obj without mtl
+    fn default() -> Self {
+        let mut registry = FormatRegistry::new();
+        for format in [
+            Builtin::Obj,
+            Builtin::Stl,
+            Builtin::Ply,
+            Builtin::Off,
+            Builtin::Gltf,
+            Builtin::Glb,
+            Builtin::ThreeMf,
+        ] {
+            registry.register(Box::new(format));
+        }
+        registry
+    }
+}
+
+impl FormatRegistry {
+    /// a registry without formats
+    pub fn new() -> Self {
+        FormatRegistry { formats: vec![] }
+    }
+
+    /// add a format, it takes precedence over the formats registered before
+    pub fn register(&mut self, format: Box<dyn MeshFormat>) {
+        self.formats.push(format);
+    }
+
+    /// the format for a file extension, case insensitive
+    pub fn by_extension(&self, extension: &str) -> Option<&dyn MeshFormat> {
+        let extension = extension.to_ascii_lowercase();
+        self.formats
+            .iter()
+            .rev()
+            .find(|f| f.extensions().contains(&extension.as_str()))
+            .map(|f| f.as_ref())
+    }
+
+    /// the format a file with this start and length looks like
+    pub fn sniff(&self, head: &[u8], len: u64) -> Option<&dyn MeshFormat> {
+        self.formats
+            .iter()
+            .rev()
+            .find(|f| f.sniff(head, len))
+            .map(|f| f.as_ref())
+    }
+
+    /// read a mesh from `f`. the content decides the format, the extension is used when the content
+    /// fits it or no format recognizes the content
+    pub fn read(&self, f: &mut dyn ReadSeek, extension: Option<&str>) -> anyhow::Result<IndexMesh> {
+        let len = f.seek(SeekFrom::End(0))?;
+        f.seek(SeekFrom::Start(0))?;
+        let mut head = vec![];
+        (&mut *f).take(SNIFF_LEN as u64).read_to_end(&mut head)?;
+        f.seek(SeekFrom::Start(0))?;
+
+        let by_extension = extension.and_then(|e| self.by_extension(e));
+        let format = by_extension
+            .filter(|format| format.sniff(&head, len))
+            .or_else(|| self.sniff(&head, len))
+            .or(by_extension)
+            .ok_or_else(|| anyhow!("unknown mesh format"))?;
+        format
+            .read(f)
+            .with_context(|| format!("failed to read {}", format.name()))
+    }
+
+    /// read the mesh file at `path`
+    pub fn load<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<IndexMesh> {
+        let path = path.as_ref();
+        let mut f =
+            BufReader::new(File::open(path).with_context(|| format!("failed to open {:?}", path))?);
+        let extension = path.extension().and_then(|e| e.to_str());
+        self.read(&mut f, extension)
+            .with_context(|| format!("failed to load {:?}", path))
+    }
+
+    /// write `mesh` to `path` in the format of its extension
+    pub fn save<P: AsRef<Path>>(&self, mesh: &IndexMesh, path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let format = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(|e| self.by_extension(e))
+            .ok_or_else(|| anyhow!("unknown mesh format for {:?}", path))?;
+        let mut f = BufWriter::new(
+            File::create(path).with_context(|| format!("failed to create {:?}", path))?,
+        );
+        format
+            .write(mesh, &mut f)
+            .with_context(|| format!("failed to save {:?} as {}", path, format.name()))?;
+        f.flush()?;
+        Ok(())
+    }
+}
+
+impl IndexMesh {
+    /// read a mesh file in any format of [`FormatRegistry::default`]
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        FormatRegistry::default().load(path)
+    }
+
+    /// write a mesh file in the format of the extension of `path`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        FormatRegistry::default().save(self, path)
+    }
+}
@@ -0,0 +1,594 @@
+use std::io::{Read, Write};
+
+use anyhow::{anyhow, bail};
+use base64::Engine;
+use glam::{Mat4, Quat, Vec3, Vec4};
+use serde_json::{json, Value};
+
+use super::{IndexMesh, IndexTriangle, RenderableMesh};
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const TRIANGLES: u64 = 4;
+const TRIANGLE_STRIP: u64 = 5;
+const TRIANGLE_FAN: u64 = 6;
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_JSON: u32 = 0x4E4F_534A;
+const GLB_BIN: u32 = 0x004E_4942;
+
+/// buffer views and accessors of a single buffer
+#[derive(Default)]
+struct Buffers {
+    data: Vec<u8>,
+    views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl Buffers {
+    fn view(&mut self, bytes: Vec<u8>, target: u32) -> usize {
+        self.views.push(json!({
+            "buffer": 0,
+            "byteOffset": self.data.len(),
+            "byteLength": bytes.len(),
+            "target": target,
+        }));
+        self.data.extend(bytes);
+        self.views.len() - 1
+    }
+
+    fn vec3(&mut self, values: &[[f32; 3]], bounds: bool) -> usize {
+        let bytes = values
+            .iter()
+            .flatten()
+            .flat_map(|x| x.to_le_bytes())
+            .collect();
+        let view = self.view(bytes, ARRAY_BUFFER);
+        let mut accessor = json!({
+            "bufferView": view,
+            "componentType": FLOAT,
+            "count": values.len(),
+            "type": "VEC3",
+        });
+        // POSITION must have bounds
+        if bounds {
+            let (min, max) =
+                values
+                    .iter()
+                    .fold(([f32::MAX; 3], [f32::MIN; 3]), |(mut min, mut max), v| {
+                        for i in 0..3 {
+                            min[i] = min[i].min(v[i]);
+                            max[i] = max[i].max(v[i]);
+                        }
+                        (min, max)
+                    });
+            accessor["min"] = json!(min);
+            accessor["max"] = json!(max);
+        }
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn indices(&mut self, indices: &[u32]) -> usize {
+        let bytes = indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+        let view = self.view(bytes, ELEMENT_ARRAY_BUFFER);
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": UNSIGNED_INT,
+            "count": indices.len(),
+            "type": "SCALAR",
+        }));
+        self.accessors.len() - 1
+    }
+}
+
+impl RenderableMesh {
+    /// the gltf json and the buffer it refers to, the buffer has no uri yet
+    fn to_gltf_parts(&self) -> anyhow::Result<(Value, Vec<u8>)> {
+        let count = self.positions.len();
+        if count == 0 {
+            bail!("empty mesh can not be written to gltf");
+        }
+        if self.normals.len() != count {
+            bail!("expect {} normals, got {}", count, self.normals.len());
+        }
+        if let Some(colors) = self.colors.as_ref() {
+            if colors.len() != count {
+                bail!("expect {} colors, got {}", count, colors.len());
+            }
+        }
+        if !self.indices.len().is_multiple_of(3) {
+            bail!("index count {} is not a multiple of 3", self.indices.len());
+        }
+        if let Some(i) = self.indices.iter().find(|&&i| i as usize >= count) {
+            bail!("index {} is out of {} vertices", i, count);
+        }
+
+        let mut buffers = Buffers::default();
+        let mut attributes = json!({
+            "POSITION": buffers.vec3(&self.positions, true),
+            "NORMAL": buffers.vec3(&self.normals, false),
+        });
+        if let Some(colors) = self.colors.as_ref() {
+            attributes["COLOR_0"] = json!(buffers.vec3(colors, false));
+        }
+        let mut primitive = json!({
+            "attributes": attributes,
+            "mode": TRIANGLES,
+        });
+        if !self.indices.is_empty() {
+            primitive["indices"] = json!(buffers.indices(&self.indices));
+        }
+        let document = json!({
+            "asset": { "version": "2.0", "generator": "mesh" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [{ "mesh": 0 }],
+            "meshes": [{ "primitives": [primitive] }],
+            "buffers": [{ "byteLength": buffers.data.len() }],
+            "bufferViews": buffers.views,
+            "accessors": buffers.accessors,
+        });
+        Ok((document, buffers.data))
+    }
+
+    /// write a .gltf file, the buffer is embedded as a base64 data uri
+    pub fn to_gltf<F: Write>(&self, f: &mut F) -> anyhow::Result<()> {
+        let (mut document, data) = self.to_gltf_parts()?;
+        let encoded = base64::engine::general_purpose::STANDARD.encode(data);
+        document["buffers"][0]["uri"] =
+            json!(format!("data:application/octet-stream;base64,{}", encoded));
+        serde_json::to_writer(f, &document)?;
+        Ok(())
+    }
+
+    /// write a binary .glb file
+    pub fn to_glb<F: Write>(&self, f: &mut F) -> anyhow::Result<()> {
+        let (document, mut data) = self.to_gltf_parts()?;
+        let mut json = serde_json::to_vec(&document)?;
+        // chunks are 4-byte aligned, json is padded with spaces and the buffer with zeros
+        json.resize(json.len().next_multiple_of(4), b' ');
+        data.resize(data.len().next_multiple_of(4), 0);
+        let length = 12 + 8 + json.len() + 8 + data.len();
+        if length > u32::MAX as usize {
+            bail!("glb of {} bytes is too large", length);
+        }
+        f.write_all(&GLB_MAGIC.to_le_bytes())?;
+        f.write_all(&2u32.to_le_bytes())?;
+        f.write_all(&(length as u32).to_le_bytes())?;
+        for (kind, chunk) in [(GLB_JSON, json), (GLB_BIN, data)] {
+            f.write_all(&(chunk.len() as u32).to_le_bytes())?;
+            f.write_all(&kind.to_le_bytes())?;
+            f.write_all(&chunk)?;
+        }
+        Ok(())
+    }
+}
+
+impl IndexMesh {
+    /// shared vertices when the mesh has normals, otherwise flat shaded like [`IndexMesh::to_renderable_mesh`]
+    fn to_gltf_mesh(&self) -> anyhow::Result<RenderableMesh> {
+        let Some(normals) = self.normals.as_ref() else {
+            return Ok(self.to_renderable_mesh());
+        };
+        // gltf indices are u32
+        if self.vertices.len() > u32::MAX as usize + 1 {
+            bail!(
+                "{} vertices do not fit in gltf indices",
+                self.vertices.len()
+            );
+        }
+        Ok(RenderableMesh {
+            positions: self.vertices.iter().map(|v| v.to_array()).collect(),
+            normals: normals.iter().map(|n| n.to_array()).collect(),
+            colors: self
+                .colors
+                .as_ref()
+                .map(|colors| colors.iter().map(|c| c.truncate().to_array()).collect()),
+            indices: self
+                .triangles
+                .iter()
+                .flat_map(|t| [t.0 as u32, t.1 as u32, t.2 as u32])
+                .collect(),
+        })
+    }
+
+    /// write a .gltf file with positions, normals, colors and indices
+    pub fn to_gltf<F: Write>(&self, f: &mut F) -> anyhow::Result<()> {
+        self.to_gltf_mesh()?.to_gltf(f)
+    }
+
+    /// write a binary .glb file with positions, normals, colors and indices
+    pub fn to_glb<F: Write>(&self, f: &mut F) -> anyhow::Result<()> {
+        self.to_gltf_mesh()?.to_glb(f)
+    }
+}
+
+/// a node of a gltf scene that has a mesh
+#[derive(Debug)]
+pub struct GltfNode {
+    /// name of the node
+    pub name: Option<String>,
+    /// node to world transform, already applied to `mesh`
+    pub transform: Mat4,
+    /// the triangle primitives of the node mesh merged, in world space
+    pub mesh: IndexMesh,
+}
+
+/// gltf json with its buffers loaded
+struct Document {
+    json: Value,
+    buffers: Vec<Vec<u8>>,
+}
+
+fn index(value: &Value, what: &str) -> anyhow::Result<usize> {
+    value
+        .as_u64()
+        .map(|i| i as usize)
+        .ok_or_else(|| anyhow!("gltf {} is not an index", what))
+}
+
+/// json and bin chunk of a glb file
+fn parse_glb(data: &[u8]) -> anyhow::Result<(Value, Option<Vec<u8>>)> {
+    let u32_at = |i: usize| -> anyhow::Result<u32> {
+        let bytes = data
+            .get(i..i + 4)
+            .ok_or_else(|| anyhow!("unexpected end of glb"))?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    };
+    if u32_at(0)? != GLB_MAGIC {
+        bail!("not a glb file");
+    }
+    if u32_at(4)? != 2 {
+        bail!("unsupported glb version {}", u32_at(4)?);
+    }
+    let length = (u32_at(8)? as usize).min(data.len());
+    let (mut json, mut bin) = (None, None);
+    let mut offset = 12;
+    while offset + 8 <= length {
+        let chunk_length = u32_at(offset)? as usize;
+        let kind = u32_at(offset + 4)?;
+        let chunk = data
+            .get(offset + 8..offset + 8 + chunk_length)
+            .ok_or_else(|| anyhow!("unexpected end of glb"))?;
+        match kind {
+            GLB_JSON => json = Some(serde_json::from_slice(chunk)?),
+            GLB_BIN if bin.is_none() => bin = Some(chunk.to_vec()),
+            // unknown chunks must be ignored
+            _ => {}
+        }
+        offset += 8 + chunk_length;
+    }
+    let json = json.ok_or_else(|| anyhow!("glb without json chunk"))?;
+    Ok((json, bin))
+}
+
+impl Document {
+    fn read<F: Read>(f: &mut F) -> anyhow::Result<Self> {
+        let mut data = vec![];
+        f.read_to_end(&mut data)?;
+        let (json, mut bin) = if data.starts_with(&GLB_MAGIC.to_le_bytes()) {
+            parse_glb(&data)?
+        } else {
+            (serde_json::from_slice(&data)?, None)
+        };
+        let mut buffers = vec![];
+        for buffer in json["buffers"].as_array().into_iter().flatten() {
+            let data = match buffer["uri"].as_str() {
+                Some(uri) if uri.starts_with("data:") => {
+                    let (_, encoded) = uri
+                        .split_once(";base64,")
+                        .ok_or_else(|| anyhow!("gltf data uri is not base64"))?;
+                    base64::engine::general_purpose::STANDARD.decode(encoded)?
+                }
+                Some(uri) => bail!("external gltf buffer {} is not supported", uri),
+                None => bin
+                    .take()
+                    .ok_or_else(|| anyhow!("gltf buffer without uri outside of a glb"))?,
+            };
+            let length = index(&buffer["byteLength"], "buffer byteLength")?;
+            if data.len() < length {
+                bail!("gltf buffer has {} bytes, expect {}", data.len(), length);
+            }
+            buffers.push(data);
+        }
+        Ok(Document { json, buffers })
+    }
+
+    /// values of an accessor as f64 and the number of components per element
+    fn accessor(&self, accessor: &Value) -> anyhow::Result<(Vec<f64>, usize)> {
+        let accessor = &self.json["accessors"][index(accessor, "accessor")?];
+        if accessor.is_null() {
+            bail!("missing gltf accessor");
+        }
+        if !accessor["sparse"].is_null() {
+            bail!("sparse gltf accessors are not supported");
+        }
+        let count = index(&accessor["count"], "accessor count")?;
+        let components = match accessor["type"].as_str() {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") | Some("MAT2") => 4,
+            Some("MAT3") => 9,
+            Some("MAT4") => 16,
+            _ => bail!("unknown gltf accessor type {}", accessor["type"]),
+        };
+        let component_type = accessor["componentType"].as_u64().unwrap_or_default();
+        let size = match component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            _ => bail!("unknown gltf component type {}", component_type),
+        };
+        let normalized = accessor["normalized"].as_bool().unwrap_or(false);
+        // without a buffer view the values only come from sparse storage
+        if accessor["bufferView"].is_null() {
+            bail!("gltf accessors without bufferView are not supported");
+        }
+        let view = &self.json["bufferViews"][index(&accessor["bufferView"], "bufferView")?];
+        let buffer = self
+            .buffers
+            .get(index(&view["buffer"], "buffer")?)
+            .ok_or_else(|| anyhow!("missing gltf buffer"))?;
+        let view_offset = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let view_length = index(&view["byteLength"], "bufferView byteLength")?;
+        if view_offset
+            .checked_add(view_length)
+            .is_none_or(|end| end > buffer.len())
+        {
+            bail!("gltf buffer view is out of its buffer");
+        }
+        let element = size * components;
+        let stride = view["byteStride"].as_u64().map_or(element, |s| s as usize);
+        if stride < element {
+            bail!(
+                "gltf byteStride {} is less than element size {}",
+                stride,
+                element
+            );
+        }
+        // the count is untrusted, check it against the view before allocating
+        let accessor_offset = accessor["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let length = match count.checked_sub(1) {
+            Some(last) => last
+                .checked_mul(stride)
+                .and_then(|l| l.checked_add(element)),
+            None => Some(0),
+        };
+        if length
+            .and_then(|l| l.checked_add(accessor_offset))
+            .is_none_or(|end| end > view_length)
+        {
+            bail!("gltf accessor is out of its buffer view");
+        }
+        let offset = view_offset + accessor_offset;
+        let mut values = Vec::with_capacity(count * components);
+        for i in 0..count {
+            for c in 0..components {
+                let start = offset + i * stride + c * size;
+                let bytes = buffer
+                    .get(start..start + size)
+                    .ok_or_else(|| anyhow!("gltf accessor is out of its buffer"))?;
+                let value = match component_type {
+                    5120 => bytes[0] as i8 as f64,
+                    5121 => bytes[0] as f64,
+                    5122 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    5123 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    5125 => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                    _ => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                };
+                values.push(match (normalized, component_type) {
+                    (true, 5120) => (value / i8::MAX as f64).max(-1.),
+                    (true, 5121) => value / u8::MAX as f64,
+                    (true, 5122) => (value / i16::MAX as f64).max(-1.),
+                    (true, 5123) => value / u16::MAX as f64,
+                    _ => value,
+                });
+            }
+        }
+        Ok((values, components))
+    }
+
+    fn vec3s(&self, accessor: &Value, what: &str) -> anyhow::Result<Vec<Vec3>> {
+        let (values, components) = self.accessor(accessor)?;
+        if components != 3 {
+            bail!("gltf {} is not a vec3", what);
+        }
+        Ok(values
+            .chunks(3)
+            .map(|v| Vec3::new(v[0] as f32, v[1] as f32, v[2] as f32))
+            .collect())
+    }
+
+    /// a triangle primitive as mesh, none for points and lines
+    fn primitive(&self, primitive: &Value) -> anyhow::Result<Option<IndexMesh>> {
+        let mode = primitive["mode"].as_u64().unwrap_or(TRIANGLES);
+        if !(TRIANGLES..=TRIANGLE_FAN).contains(&mode) {
+            return Ok(None);
+        }
+        let attributes = &primitive["attributes"];
+        if attributes["POSITION"].is_null() {
+            bail!("gltf primitive without POSITION");
+        }
+        let mut mesh = IndexMesh::new();
+        mesh.vertices = self.vec3s(&attributes["POSITION"], "POSITION")?;
+        let count = mesh.vertices.len();
+        if !attributes["NORMAL"].is_null() {
+            mesh.set_normals(self.vec3s(&attributes["NORMAL"], "NORMAL")?)?;
+        }
+        if !attributes["COLOR_0"].is_null() {
+            let colors = match self.accessor(&attributes["COLOR_0"])? {
+                (values, 3) => values
+                    .chunks(3)
+                    .map(|c| Vec4::new(c[0] as f32, c[1] as f32, c[2] as f32, 1.))
+                    .collect(),
+                (values, 4) => values
+                    .chunks(4)
+                    .map(|c| Vec4::new(c[0] as f32, c[1] as f32, c[2] as f32, c[3] as f32))
+                    .collect(),
+                _ => bail!("gltf COLOR_0 is not a vec3 or vec4"),
+            };
+            mesh.set_colors(colors)?;
+        }
+        let indices = if primitive["indices"].is_null() {
+            (0..count).collect()
+        } else {
+            let (values, components) = self.accessor(&primitive["indices"])?;
+            if components != 1 {
+                bail!("gltf indices are not scalars");
+            }
+            values
+                .into_iter()
+                .map(|i| match i >= 0. && i < count as f64 && i.fract() == 0. {
+                    true => Ok(i as usize),
+                    false => Err(anyhow!("gltf index {} is out of {} vertices", i, count)),
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?
+        };
+        let triangles = indices.len().saturating_sub(2);
+        mesh.triangles = match mode {
+            TRIANGLE_STRIP => (0..triangles)
+                .map(|i| match i % 2 {
+                    0 => IndexTriangle(indices[i], indices[i + 1], indices[i + 2]),
+                    _ => IndexTriangle(indices[i + 1], indices[i], indices[i + 2]),
+                })
+                .collect(),
+            TRIANGLE_FAN => (0..triangles)
+                .map(|i| IndexTriangle(indices[0], indices[i + 1], indices[i + 2]))
+                .collect(),
+            _ => indices
+                .chunks_exact(3)
+                .map(|t| IndexTriangle(t[0], t[1], t[2]))
+                .collect(),
+        };
+        Ok(Some(mesh))
+    }
+
+    fn node_transform(node: &Value) -> anyhow::Result<Mat4> {
+        let floats = |value: &Value, len: usize, what: &str| -> anyhow::Result<Vec<f32>> {
+            let values = value
+                .as_array()
+                .map(|a| a.iter().filter_map(|x| x.as_f64()).map(|x| x as f32))
+                .ok_or_else(|| anyhow!("gltf node {} is not an array", what))?
+                .collect::<Vec<_>>();
+            if values.len() != len {
+                bail!("gltf node {} expects {} numbers", what, len);
+            }
+            Ok(values)
+        };
+        if !node["matrix"].is_null() {
+            return Ok(Mat4::from_cols_slice(&floats(
+                &node["matrix"],
+                16,
+                "matrix",
+            )?));
+        }
+        let mut transform = (Vec3::ONE, Quat::IDENTITY, Vec3::ZERO);
+        if !node["scale"].is_null() {
+            transform.0 = Vec3::from_slice(&floats(&node["scale"], 3, "scale")?);
+        }
+        if !node["rotation"].is_null() {
+            transform.1 = Quat::from_slice(&floats(&node["rotation"], 4, "rotation")?);
+        }
+        if !node["translation"].is_null() {
+            transform.2 = Vec3::from_slice(&floats(&node["translation"], 3, "translation")?);
+        }
+        Ok(Mat4::from_scale_rotation_translation(
+            transform.0,
+            transform.1,
+            transform.2,
+        ))
+    }
+
+    /// the nodes with meshes in the default scene, depth first
+    fn nodes(&self) -> anyhow::Result<Vec<GltfNode>> {
+        let nodes = self.json["nodes"].as_array().map_or(&[][..], |n| n);
+        let scene = match self.json["scene"].as_u64() {
+            Some(scene) => &self.json["scenes"][scene as usize],
+            None => &self.json["scenes"][0],
+        };
+        let roots = if scene.is_null() {
+            // no scene, every node that is not a child is a root
+            let mut is_child = vec![false; nodes.len()];
+            for node in nodes {
+                for child in node["children"].as_array().into_iter().flatten() {
+                    if let Some(flag) = is_child.get_mut(index(child, "node")?) {
+                        *flag = true;
+                    }
+                }
+            }
+            (0..nodes.len()).filter(|&i| !is_child[i]).collect()
+        } else {
+            scene["nodes"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|n| index(n, "node"))
+                .collect::<anyhow::Result<Vec<_>>>()?
+        };
+
+        let mut visited = vec![false; nodes.len()];
+        let mut stack = roots
+            .into_iter()
+            .rev()
+            .map(|n| (n, Mat4::IDENTITY))
+            .collect::<Vec<_>>();
+        let mut result = vec![];
+        while let Some((n, parent)) = stack.pop() {
+            let node = nodes
+                .get(n)
+                .ok_or_else(|| anyhow!("missing gltf node {}", n))?;
+            if std::mem::replace(&mut visited[n], true) {
+                bail!("gltf node {} is reached twice", n);
+            }
+            let transform = parent * Self::node_transform(node)?;
+            if !node["mesh"].is_null() {
+                let gltf_mesh = &self.json["meshes"][index(&node["mesh"], "mesh")?];
+                let mut mesh = IndexMesh::new();
+                for primitive in gltf_mesh["primitives"].as_array().into_iter().flatten() {
+                    if let Some(primitive) = self.primitive(primitive)? {
+                        mesh.merge(primitive);
+                    }
+                }
+                if transform != Mat4::IDENTITY {
+                    mesh.transfrom(transform);
+                }
+                // a mirroring transform turns the triangles inside out
+                if transform.determinant() < 0. {
+                    mesh.triangles
+                        .iter_mut()
+                        .for_each(|t| std::mem::swap(&mut t.1, &mut t.2));
+                }
+                result.push(GltfNode {
+                    name: node["name"].as_str().map(String::from),
+                    transform,
+                    mesh,
+                });
+            }
+            for child in node["children"].as_array().into_iter().flatten().rev() {
+                stack.push((index(child, "node")?, transform));
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl IndexMesh {
+    /// read a .gltf or .glb file, the triangle primitives of the default scene are moved by
+    /// their node transforms and merged. buffers must be embedded as data uris or in the glb
+    pub fn from_gltf<F: Read>(f: &mut F) -> anyhow::Result<Self> {
+        let mut mesh = IndexMesh::new();
+        for node in IndexMesh::from_gltf_nodes(f)? {
+            mesh.merge(node.mesh);
+        }
+        Ok(mesh)
+    }
+
+    /// like [`IndexMesh::from_gltf`] but one mesh per node of the default scene
+    pub fn from_gltf_nodes<F: Read>(f: &mut F) -> anyhow::Result<Vec<GltfNode>> {
+        Document::read(f)?.nodes()
+    }
+}
@@ -1,8 +1,15 @@
 use std::{fs::File, io::Cursor};
 
-use glam::Vec3;
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use rand::Rng;
 
-use super::IndexMesh;
+use crate::test_utils::{assert_closed, volume};
+
+use super::{
+    FormatRegistry, IndexMesh, IndexTriangle, Material, MeshFormat, PlyFormat, ReadSeek, StlFormat,
+    StlOption, SurfaceOption, ThreeMfItem, ThreeMfModel, ThreeMfObject, ThreeMfUnit, TriangleGroup,
+    WeldReport, WriteSeek,
+};
 
 #[test]
 fn test_from_stl() {
@@ -34,6 +41,17 @@ fn test_from_stl() {
     );
 }
 
+#[test]
+fn test_translate() {
+    // vertices are points, a translation has to move them like it moves a half-edge mesh
+    let mut mesh = IndexMesh::new();
+    mesh.vertices.push(Vec3::new(1.0, 2.0, 3.0));
+    mesh.translate(Vec3::new(1.0, 0.0, -1.0));
+    assert_eq!(mesh.vertices[0], Vec3::new(2.0, 2.0, 2.0));
+    mesh.scale(Vec3::splat(2.0));
+    assert_eq!(mesh.vertices[0], Vec3::new(4.0, 4.0, 4.0));
+}
+
 #[test]
 fn test_to_stl() {
     let mut writer = ::std::io::Cursor::new(Vec::new());
@@ -57,6 +75,92 @@ fn test_build_bvh() {
     // println!("{:?}", bvh);
 }
 
+#[test]
+fn test_refit_bvh() {
+    let mut mesh = crate::Box { size: 1. }.to_mesh();
+    let mut bvh = mesh.build_aabb_bvh_indexed(Default::default());
+    mesh.rotate(glam::Quat::from_rotation_z(0.3));
+    mesh.translate(Vec3::new(1., 2., 3.));
+    assert!(!mesh.refit_aabb_bvh(&mut bvh, Some(f32::MAX)));
+    let fresh = mesh.build_aabb_bvh(Default::default());
+    assert_eq!(bvh.get_root().bv, fresh.get_root().bv);
+    let leaves = bvh
+        .iter_bfs(0)
+        .filter_map(|(node, _)| node.primitives)
+        .flatten()
+        .count();
+    assert_eq!(leaves, mesh.triangles.len());
+}
+
+#[test]
+fn test_to_stl_ascii() {
+    let mesh = tetrahedron();
+    let option = StlOption {
+        format: StlFormat::Ascii,
+        name: "part 1".to_string(),
+        ..StlOption::default()
+    };
+    let mut buf = vec![];
+    mesh.to_stl_with(&mut buf, &option).unwrap();
+    let text = String::from_utf8(buf.clone()).unwrap();
+    assert!(
+        text.starts_with("solid part 1\n  facet normal 0.000000e+00 0.000000e+00 -1.000000e+00\n")
+    );
+    assert!(text.contains("      vertex 1.000000e+00 0.000000e+00 0.000000e+00\n"));
+    assert!(text.ends_with("endsolid part 1\n"));
+    assert_eq!(text.matches("endfacet").count(), 4);
+    let read = IndexMesh::from_stl(&mut Cursor::new(buf)).unwrap();
+    assert_eq!(read.vertices.len(), 4);
+    assert_eq!(read.triangles.len(), 4);
+
+    let option = StlOption {
+        name: "two\nlines".to_string(),
+        ..option
+    };
+    assert!(mesh.to_stl_with(&mut vec![], &option).is_err());
+}
+
+#[test]
+fn test_to_stl_binary() {
+    let mut mesh = tetrahedron();
+    mesh.set_colors(vec![
+        Vec4::new(1., 0., 0., 1.),
+        Vec4::new(1., 0., 0., 1.),
+        Vec4::new(1., 0., 0., 1.),
+        Vec4::new(0., 0., 1., 1.),
+    ])
+    .unwrap();
+    let attributes = mesh.stl_color_attributes().unwrap();
+    assert_eq!(attributes[0], 0x8000 | 31 << 10);
+    assert_eq!(attributes[3], 0x8000 | 21 << 10 | 10);
+    let option = StlOption {
+        header: b"exported mesh".to_vec(),
+        attributes: Some(attributes.clone()),
+        ..StlOption::default()
+    };
+    let mut buf = vec![];
+    mesh.to_stl_with(&mut buf, &option).unwrap();
+    assert_eq!(buf.len(), 84 + 50 * 4);
+    assert!(buf.starts_with(b"exported mesh\0"));
+    for (i, attribute) in attributes.iter().enumerate() {
+        let end = 84 + 50 * (i + 1);
+        assert_eq!(buf[end - 2..end], attribute.to_le_bytes());
+    }
+    let read = IndexMesh::from_stl(&mut Cursor::new(buf)).unwrap();
+    assert_eq!(read.triangles.len(), 4);
+
+    let long = StlOption {
+        header: vec![b'x'; 81],
+        ..StlOption::default()
+    };
+    assert!(mesh.to_stl_with(&mut vec![], &long).is_err());
+    let short = StlOption {
+        attributes: Some(vec![0; 3]),
+        ..StlOption::default()
+    };
+    assert!(mesh.to_stl_with(&mut vec![], &short).is_err());
+}
+
 #[test]
 fn test_from_obj() {
     let mut f = File::open("assets/bunny.obj").unwrap();
@@ -65,6 +169,55 @@ fn test_from_obj() {
     println!("{:?}", res);
 }
 
+#[test]
+fn test_from_obj_polygons() {
+    let data = b"v 0 0 0
+v 2 0 0
+v 2 2 0
+v 1 0.5 0
+v 0 2 0
+v 0 0 1
+v 2 0 1
+v 2 2 1
+v 0 2 1
+f 1 2 3 4 5
+f 6 7 8 9
+f 1 2 7
+";
+    let mesh = IndexMesh::from_obj(&mut Cursor::new(data.to_vec())).unwrap();
+    assert_eq!(mesh.triangles.len(), 3 + 2 + 1);
+    let area = |t: &super::IndexTriangle| {
+        let (a, b, c) = (mesh.vertices[t.0], mesh.vertices[t.1], mesh.vertices[t.2]);
+        (b - a).cross(c - a)
+    };
+    // every triangle keeps the winding of its face
+    let pentagon = mesh.triangles[..3].iter().map(area).collect::<Vec<_>>();
+    assert!(pentagon.iter().all(|n| n.z > 0.));
+    assert!((pentagon.iter().map(|n| n.z).sum::<f32>() / 2. - 2.5).abs() < 1e-6);
+    let quad = mesh.triangles[3..5].iter().map(area).collect::<Vec<_>>();
+    assert!(quad.iter().all(|n| n.z > 0.));
+    assert!((quad.iter().map(|n| n.z).sum::<f32>() / 2. - 4.).abs() < 1e-6);
+}
+
+#[test]
+fn test_from_obj_invalid() {
+    let read = |data: &[u8]| IndexMesh::from_obj(&mut Cursor::new(data.to_vec()));
+    let vertices = "v 0 0 0\nv 1 0 0\nv 0 1 0\n";
+    let two = read(format!("{}f 1 2\n", vertices).as_bytes()).unwrap_err();
+    assert!(two.to_string().contains("face 1"));
+    assert!(read(format!("{}f 1 2 3\nf 1 2 2\n", vertices).as_bytes()).is_err());
+    assert!(read(format!("{}f 1 2 4\n", vertices).as_bytes()).is_err());
+    assert!(read(format!("{}f 1 2 3\n", vertices).as_bytes()).is_ok());
+
+    // a repeated corner is skipped instead of giving a triangle without area
+    let mesh = read(format!("{}f 1 2 2 3\n", vertices).as_bytes()).unwrap();
+    let t = &mesh.triangles[..];
+    assert!(t.len() == 1 && (t[0].0, t[0].1, t[0].2) == (0, 1, 2));
+    // a quad on a line has no area to triangulate
+    let line = "v 0 0 0\nv 1 0 0\nv 2 0 0\nv 3 0 0\nf 1 2 3 4\n";
+    assert!(read(line.as_bytes()).is_err());
+}
+
 #[test]
 fn test_to_obj() {
     let mut f = File::open("assets/bunny.obj").unwrap();
@@ -81,3 +234,1101 @@ fn test_to_obj() {
     assert_eq!(tri_len, mesh.triangles.len());
     assert_eq!(ver_len, mesh.vertices.len());
 }
+
+#[test]
+fn test_from_obj_attributes() {
+    let data = b"mtllib cube.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vt 0.5 0.5
+vn 0 0 1
+o quad
+g front
+usemtl red
+f 1/1/1 2/2/1 3/3/1 4/4/1
+usemtl blue
+f 1/5/1 3/3/1 4/4/1
+";
+    let mtl = b"# two materials
+newmtl red
+Kd 1 0 0
+map_Kd red wood.png
+
+newmtl blue
+Kd 0 0 1
+";
+    let mesh = IndexMesh::from_obj_with_mtl(
+        &mut Cursor::new(data.to_vec()),
+        &mut Cursor::new(mtl.to_vec()),
+    )
+    .unwrap();
+    // the first position is used with two texture coordinates
+    assert_eq!(mesh.vertices.len(), 5);
+    assert_eq!(mesh.triangles.len(), 3);
+    let uvs = mesh.uvs().unwrap();
+    assert_eq!(uvs[0], Vec2::new(0., 0.));
+    assert_eq!(uvs[4], Vec2::new(0.5, 0.5));
+    assert_eq!(mesh.vertices[4], mesh.vertices[0]);
+    assert!(mesh.normals().unwrap().iter().all(|n| *n == Vec3::Z));
+
+    let groups = mesh.groups();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0].object, "quad");
+    assert_eq!(groups[0].name, "front");
+    assert_eq!(groups[0].material.as_deref(), Some("red"));
+    assert_eq!(groups[0].triangles, 0..2);
+    assert_eq!(groups[1].material.as_deref(), Some("blue"));
+    assert_eq!(groups[1].triangles, 2..3);
+
+    let materials = mesh.materials();
+    assert_eq!(materials.len(), 2);
+    assert_eq!(materials[0].name, "red");
+    assert_eq!(materials[0].property("map_Kd"), Some("red wood.png"));
+    assert_eq!(materials[1].property("Kd"), Some("0 0 1"));
+
+    // texture coordinates are dropped when a corner has none
+    let partial = b"v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nf 1/1 2 3\n";
+    let mesh = IndexMesh::from_obj(&mut Cursor::new(partial.to_vec())).unwrap();
+    assert!(mesh.uvs().is_none());
+    assert!(mesh.normals().is_none());
+    assert_eq!(mesh.groups().len(), 1);
+    assert_eq!(mesh.groups()[0].name, "default");
+
+    let read = |data: &[u8]| IndexMesh::from_obj(&mut Cursor::new(data.to_vec()));
+    assert!(read(b"v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nf 1/2 2/1 3/1\n").is_err());
+    assert!(read(b"v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1//1 2//1 3//1\n").is_err());
+    let mtl = |data: &[u8]| {
+        IndexMesh::from_obj_with_mtl(
+            &mut Cursor::new(b"v 0 0 0\n".to_vec()),
+            &mut Cursor::new(data.to_vec()),
+        )
+    };
+    assert!(mtl(b"Kd 1 0 0\n").is_err());
+    assert!(mtl(b"newmtl\n").is_err());
+}
+
+#[test]
+fn test_obj_round_trip() {
+    let mut mesh = IndexMesh {
+        vertices: vec![
+            Vec3::new(0., 0., 0.),
+            Vec3::new(1., 0., 0.),
+            Vec3::new(1., 1., 0.),
+            Vec3::new(0., 1., 0.),
+        ],
+        triangles: vec![
+            IndexTriangle(0, 1, 2),
+            IndexTriangle(0, 2, 3),
+            IndexTriangle(0, 3, 1),
+            IndexTriangle(1, 3, 2),
+        ],
+        ..IndexMesh::new()
+    };
+    mesh.set_uvs(vec![
+        Vec2::new(0., 0.),
+        Vec2::new(1., 0.),
+        Vec2::new(1., 1.),
+        Vec2::new(0., 1.),
+    ])
+    .unwrap();
+    mesh.set_normals(vec![Vec3::Z; 4]).unwrap();
+    let group = |object: &str, name: &str, material: Option<&str>, triangles| TriangleGroup {
+        object: object.to_string(),
+        name: name.to_string(),
+        material: material.map(str::to_string),
+        triangles,
+    };
+    assert!(mesh
+        .set_groups(vec![
+            group("a", "x", None, 0..2),
+            group("a", "y", None, 1..3)
+        ])
+        .is_err());
+    assert!(mesh.set_groups(vec![group("a", "x", None, 3..5)]).is_err());
+    mesh.set_groups(vec![
+        group("b", "bottom", Some("steel"), 2..3),
+        group("a", "top", Some("wood"), 0..2),
+    ])
+    .unwrap();
+    mesh.set_materials(vec![Material {
+        name: "wood".to_string(),
+        properties: vec![
+            ("Kd".to_string(), "0.5 0.3 0.1".to_string()),
+            ("map_Kd".to_string(), "wood.png".to_string()),
+        ],
+    }]);
+
+    let (mut obj, mut mtl) = (vec![], vec![]);
+    mesh.to_obj_with_mtl(&mut obj, "mesh.mtl", &mut mtl)
+        .unwrap();
+    let text = String::from_utf8(obj.clone()).unwrap();
+    assert!(text.starts_with("mtllib mesh.mtl\n"));
+    assert!(text.contains("f 1/1/1 2/2/2 3/3/3"));
+    let read = IndexMesh::from_obj_with_mtl(&mut Cursor::new(obj), &mut Cursor::new(mtl)).unwrap();
+    assert_eq!(read.vertices, mesh.vertices);
+    assert_eq!(read.uvs(), mesh.uvs());
+    assert_eq!(read.normals(), mesh.normals());
+    // the last triangle is outside any group and reads back as the default group
+    assert_eq!(&read.groups()[..2], mesh.groups());
+    assert_eq!(read.groups()[2], group("b", "default", None, 3..4));
+    assert_eq!(read.materials()[0], mesh.materials()[0]);
+    assert_eq!(read.materials()[1].name, "steel");
+    assert!(read.materials()[1].properties.is_empty());
+
+    // merged meshes keep groups and materials of both
+    let mut merged = IndexMesh::from_obj(&mut Cursor::new(b"v 0 0 0\n".to_vec())).unwrap();
+    let offset = merged.triangles.len();
+    merged.merge(read);
+    assert_eq!(merged.groups()[0].triangles, offset..offset + 2);
+    assert_eq!(merged.materials().len(), 2);
+}
+
+#[test]
+fn test_obj_round_trip_empty_group() {
+    let mut mesh = IndexMesh {
+        vertices: vec![
+            Vec3::new(0., 0., 0.),
+            Vec3::new(1., 0., 0.),
+            Vec3::new(1., 1., 0.),
+            Vec3::new(0., 1., 0.),
+        ],
+        triangles: vec![
+            IndexTriangle(0, 1, 2),
+            IndexTriangle(0, 2, 3),
+            IndexTriangle(0, 3, 1),
+        ],
+        ..IndexMesh::new()
+    };
+    let group = |name: &str, triangles| TriangleGroup {
+        object: "a".to_string(),
+        name: name.to_string(),
+        material: None,
+        triangles,
+    };
+    mesh.set_groups(vec![
+        group("x", 0..1),
+        group("empty", 1..1),
+        group("z", 1..3),
+    ])
+    .unwrap();
+    let mut obj = vec![];
+    mesh.to_obj(&mut obj).unwrap();
+    let read = IndexMesh::from_obj(&mut Cursor::new(obj)).unwrap();
+    // the empty group has no face to write, the ones after it are kept
+    assert_eq!(read.groups(), [group("x", 0..1), group("z", 1..3)]);
+}
+
+#[test]
+fn test_from_off() {
+    let data = b"COFF
+# a colored quad and a triangle
+5 2 0
+0 0 0 255 0 0
+1 0 0 0 255 0 255
+1 1 0 0 0 255
+0 1 0 255 255 255 0
+0 0 1 0 0 0
+4 0 1 2 3
+3 0 1 4 255 0 0
+";
+    let mesh = IndexMesh::from_off(&mut Cursor::new(data.to_vec())).unwrap();
+    assert_eq!(mesh.vertices.len(), 5);
+    assert_eq!(mesh.triangles.len(), 3);
+    let colors = mesh.colors().unwrap();
+    assert_eq!(colors[0], Vec4::new(1., 0., 0., 1.));
+    assert_eq!(colors[3], Vec4::new(1., 1., 1., 0.));
+    assert!(mesh.normals().is_none());
+
+    // black and white in integers are still in [0, 255], decimals are in [0, 1]
+    let data = b"COFF\n3 1 0\n0 0 0 0 0 0\n1 0 0 1 1 1\n0 1 0 1.0 0.5 1e0 0.\n3 0 1 2\n";
+    let colors = IndexMesh::from_off(&mut Cursor::new(data.to_vec()))
+        .unwrap()
+        .colors()
+        .unwrap()
+        .to_vec();
+    assert_eq!(colors[0], Vec4::new(0., 0., 0., 1.));
+    assert_eq!(colors[1], Vec4::new(1. / 255., 1. / 255., 1. / 255., 1.));
+    assert_eq!(colors[2], Vec4::new(1., 0.5, 1., 0.));
+
+    // counts on the keyword line
+    let data = b"OFF 3 1 3\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n";
+    let mesh = IndexMesh::from_off(&mut Cursor::new(data.to_vec())).unwrap();
+    assert_eq!(mesh.triangles.len(), 1);
+    assert!(mesh.colors().is_none());
+
+    // ModelNet glues the vertex count to the keyword
+    let data = b"OFF3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n";
+    let mesh = IndexMesh::from_off(&mut Cursor::new(data.to_vec())).unwrap();
+    assert_eq!(mesh.vertices.len(), 3);
+    assert_eq!(mesh.triangles.len(), 1);
+
+    let read = |data: &[u8]| IndexMesh::from_off(&mut Cursor::new(data.to_vec()));
+    assert!(read(b"PLY\n3 1 0\n").is_err());
+    assert!(read(b"OFFX 1 0\n").is_err());
+    assert!(read(b"4OFF\n3 1 0\n").is_err());
+    assert!(read(b"OFF BINARY\n").is_err());
+    assert!(read(b"OFF\n3 1 0\n0 0 0\n1 0 0\n").is_err());
+    assert!(read(b"OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 3\n").is_err());
+    assert!(read(b"OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1\n").is_err());
+    assert!(read(b"NOFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n").is_err());
+}
+
+#[test]
+fn test_off_round_trip() {
+    let mut mesh = IndexMesh {
+        vertices: vec![
+            Vec3::new(0., 0., 0.),
+            Vec3::new(1., 0., 0.),
+            Vec3::new(0., 1., 0.5),
+        ],
+        triangles: vec![IndexTriangle(0, 1, 2)],
+        ..IndexMesh::new()
+    };
+    mesh.set_normals(vec![Vec3::Z, Vec3::X, Vec3::Y]).unwrap();
+    mesh.set_colors(vec![
+        Vec4::new(1., 0., 0., 1.),
+        Vec4::new(0., 0.5, 0., 1.),
+        Vec4::new(0., 0., 1., 0.25),
+    ])
+    .unwrap();
+    mesh.set_uvs(vec![Vec2::ZERO, Vec2::X, Vec2::Y]).unwrap();
+    let mut buf = vec![];
+    mesh.to_off(&mut buf).unwrap();
+    assert!(buf.starts_with(b"STCNOFF\n"));
+    let read = IndexMesh::from_off(&mut Cursor::new(buf)).unwrap();
+    assert_eq!(read.vertices, mesh.vertices);
+    assert_eq!(read.triangles.len(), 1);
+    assert_eq!(read.normals(), mesh.normals());
+    assert_eq!(read.colors(), mesh.colors());
+    assert_eq!(read.uvs(), mesh.uvs());
+}
+
+#[test]
+fn test_3mf_round_trip() {
+    let triangle = IndexMesh {
+        vertices: vec![
+            Vec3::new(0., 0., 0.),
+            Vec3::new(1., 0., 0.),
+            Vec3::new(0., 1., 0.),
+        ],
+        triangles: vec![IndexTriangle(0, 1, 2)],
+        ..IndexMesh::new()
+    };
+    let shift = Mat4::from_translation(Vec3::new(10., 0., 0.));
+    let mirror = Mat4::from_scale(Vec3::new(1., 1., -1.)) * Mat4::from_rotation_z(0.5);
+    let model = ThreeMfModel {
+        unit: ThreeMfUnit::Inch,
+        metadata: vec![("Title".to_string(), "a & b <c>".to_string())],
+        objects: vec![
+            ThreeMfObject {
+                id: 3,
+                name: Some("part".to_string()),
+                mesh: triangle.clone(),
+                components: vec![],
+            },
+            ThreeMfObject {
+                id: 7,
+                name: None,
+                mesh: IndexMesh::new(),
+                components: vec![ThreeMfItem {
+                    object: 3,
+                    transform: shift,
+                }],
+            },
+        ],
+        items: vec![
+            ThreeMfItem {
+                object: 3,
+                transform: Mat4::IDENTITY,
+            },
+            ThreeMfItem {
+                object: 7,
+                transform: mirror,
+            },
+        ],
+    };
+    let mut buf = Cursor::new(vec![]);
+    model.write(&mut buf).unwrap();
+    buf.set_position(0);
+    let read = ThreeMfModel::read(&mut buf).unwrap();
+    assert_eq!(read.unit, ThreeMfUnit::Inch);
+    assert_eq!(read.metadata, model.metadata);
+    assert_eq!(read.objects.len(), 2);
+    assert_eq!(read.objects[0].name.as_deref(), Some("part"));
+    assert_eq!(read.objects[0].mesh.vertices, triangle.vertices);
+    assert_eq!(read.objects[1].components[0].object, 3);
+    assert!(read.objects[1].mesh.triangles.is_empty());
+    assert_eq!(read.items[0], model.items[0]);
+    assert!(read.items[1].transform.abs_diff_eq(mirror, 1e-6));
+
+    // items are placed in the build and merged
+    buf.set_position(0);
+    let mesh = IndexMesh::from_3mf(&mut buf).unwrap();
+    assert_eq!(mesh.vertices.len(), 6);
+    assert_eq!(mesh.triangles.len(), 2);
+    let placed = mirror.transform_point3(Vec3::new(11., 0., 0.));
+    assert!(mesh.vertices[4].abs_diff_eq(placed, 1e-5));
+    // the mirrored triangle keeps facing outwards
+    let normal = |t: &IndexTriangle| {
+        (mesh.vertices[t.1] - mesh.vertices[t.0]).cross(mesh.vertices[t.2] - mesh.vertices[t.0])
+    };
+    assert!(normal(&mesh.triangles[0]).z > 0.);
+    assert!(normal(&mesh.triangles[1]).z < 0.);
+
+    let mut buf = Cursor::new(vec![]);
+    triangle.to_3mf(&mut buf).unwrap();
+    buf.set_position(0);
+    let mesh = IndexMesh::from_3mf(&mut buf).unwrap();
+    assert_eq!(mesh.vertices, triangle.vertices);
+    assert_eq!(mesh.triangles.len(), 1);
+}
+
+#[test]
+fn test_from_3mf_invalid() {
+    assert!(IndexMesh::from_3mf(&mut Cursor::new(b"solid".to_vec())).is_err());
+    let model = |objects: Vec<ThreeMfObject>, object| {
+        let mut buf = Cursor::new(vec![]);
+        let model = ThreeMfModel {
+            objects,
+            items: vec![ThreeMfItem {
+                object,
+                transform: Mat4::IDENTITY,
+            }],
+            ..ThreeMfModel::default()
+        };
+        model.write(&mut buf).map(|_| buf)
+    };
+    let object = |id, components: Vec<usize>| ThreeMfObject {
+        id,
+        name: None,
+        mesh: IndexMesh::new(),
+        components: components
+            .into_iter()
+            .map(|object| ThreeMfItem {
+                object,
+                transform: Mat4::IDENTITY,
+            })
+            .collect(),
+    };
+    assert!(model(vec![object(1, vec![])], 2).is_err());
+    assert!(model(vec![object(1, vec![]), object(1, vec![])], 1).is_err());
+    let mut cycle = model(vec![object(1, vec![2]), object(2, vec![1])], 1).unwrap();
+    cycle.set_position(0);
+    assert!(IndexMesh::from_3mf(&mut cycle).is_err());
+}
+
+fn tetrahedron() -> IndexMesh {
+    IndexMesh {
+        vertices: vec![
+            Vec3::new(0., 0., 0.),
+            Vec3::new(1., 0., 0.),
+            Vec3::new(0., 1., 0.),
+            Vec3::new(0., 0., 1.),
+        ],
+        triangles: vec![
+            IndexTriangle(0, 2, 1),
+            IndexTriangle(0, 1, 3),
+            IndexTriangle(0, 3, 2),
+            IndexTriangle(1, 2, 3),
+        ],
+        ..IndexMesh::new()
+    }
+}
+
+#[test]
+fn test_load_save() {
+    let dir = std::env::temp_dir().join(format!("mesh-load-save-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mesh = tetrahedron();
+    for extension in ["stl", "obj", "ply", "off", "gltf", "glb", "3mf", "STL"] {
+        let path = dir.join(format!("tetrahedron.{}", extension));
+        mesh.save(&path).unwrap();
+        let read = IndexMesh::load(&path).unwrap();
+        assert_eq!(read.triangles.len(), 4, "{}", extension);
+        let area = |m: &IndexMesh| {
+            m.triangles
+                .iter()
+                .map(|t| {
+                    let [a, b, c] = [t.0, t.1, t.2].map(|i| m.vertices[i]);
+                    (b - a).cross(c - a).length()
+                })
+                .sum::<f32>()
+        };
+        assert!((area(&read) - area(&mesh)).abs() < 1e-5, "{}", extension);
+    }
+
+    // the content wins over a wrong extension
+    let path = dir.join("tetrahedron.stl");
+    std::fs::rename(dir.join("tetrahedron.ply"), &path).unwrap();
+    assert_eq!(IndexMesh::load(&path).unwrap().vertices.len(), 4);
+    // and the extension is used when nothing recognizes the content
+    let path = dir.join("tetrahedron.obj");
+    std::fs::write(&path, "\nf 1 2 3\nv 0 0 0\nv 1 0 0\nv 0 1 0\n").unwrap();
+    assert_eq!(IndexMesh::load(&path).unwrap().triangles.len(), 1);
+    assert!(IndexMesh::load(dir.join("missing.stl")).is_err());
+    assert!(mesh.save(dir.join("tetrahedron.xyz")).is_err());
+    std::fs::write(dir.join("unknown.xyz"), "1 2 3").unwrap();
+    assert!(IndexMesh::load(dir.join("unknown.xyz")).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_format_sniff() {
+    let registry = FormatRegistry::default();
+    let name = |data: &[u8]| registry.sniff(data, data.len() as u64).map(|f| f.name());
+
+    // a binary stl whose header starts with solid
+    let mut stl = vec![];
+    tetrahedron().to_stl(&mut stl).unwrap();
+    stl[..12].copy_from_slice(b"solid binary");
+    assert_eq!(name(&stl), Some("stl"));
+    let read = registry.read(&mut Cursor::new(stl), None).unwrap();
+    assert_eq!(read.triangles.len(), 4);
+    let ascii = b"solid a\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\nendloop\nendfacet\nendsolid a\n";
+    assert_eq!(name(ascii), Some("stl"));
+    assert_eq!(
+        registry
+            .read(&mut Cursor::new(ascii.to_vec()), None)
+            .unwrap()
+            .triangles
+            .len(),
+        1
+    );
+
+    assert_eq!(name(b"ply\nformat ascii 1.0\n"), Some("ply"));
+    assert_eq!(name(b"# comment\nCOFF\n3 1 0\n"), Some("off"));
+    assert_eq!(name(b"OFF490 518 0\n"), Some("off"));
+    assert_eq!(name(b"OFFICE\n"), None);
+    assert_eq!(name(b"# comment\nv 0 0 0\n"), Some("obj"));
+    assert_eq!(name(b"glTF\x02\0\0\0"), Some("glb"));
+    assert_eq!(name(b" {\"asset\": {\"version\": \"2.0\"}}"), Some("gltf"));
+    assert_eq!(name(b"PK\x03\x04"), Some("3mf"));
+    assert_eq!(name(b"1 2 3"), None);
+}
+
+/// points written one per line as `x y z`, three points make a triangle
+struct Xyz;
+
+impl MeshFormat for Xyz {
+    fn name(&self) -> &str {
+        "xyz"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["xyz", "stl"]
+    }
+
+    fn sniff(&self, head: &[u8], _len: u64) -> bool {
+        head.starts_with(b"xyz")
+    }
+
+    fn read(&self, f: &mut dyn ReadSeek) -> anyhow::Result<IndexMesh> {
+        let mut text = String::new();
+        f.read_to_string(&mut text)?;
+        let mut mesh = IndexMesh::new();
+        for line in text.lines().skip(1) {
+            let v = line
+                .split_whitespace()
+                .map(|v| v.parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()?;
+            mesh.vertices.push(Vec3::from_slice(&v));
+        }
+        for i in 0..mesh.vertices.len() / 3 {
+            mesh.triangles
+                .push(IndexTriangle(i * 3, i * 3 + 1, i * 3 + 2));
+        }
+        Ok(mesh)
+    }
+
+    fn write(&self, mesh: &IndexMesh, f: &mut dyn WriteSeek) -> anyhow::Result<()> {
+        writeln!(f, "xyz")?;
+        for t in mesh.triangles.iter() {
+            for i in [t.0, t.1, t.2] {
+                let v = mesh.vertices[i];
+                writeln!(f, "{} {} {}", v.x, v.y, v.z)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_format_registry() {
+    let mut registry = FormatRegistry::new();
+    assert!(registry.by_extension("stl").is_none());
+    registry = FormatRegistry::default();
+    registry.register(Box::new(Xyz));
+    // later formats take precedence for their extensions
+    assert_eq!(registry.by_extension("STL").unwrap().name(), "xyz");
+    assert_eq!(registry.by_extension("obj").unwrap().name(), "obj");
+
+    let dir = std::env::temp_dir().join(format!("mesh-registry-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("tetrahedron.xyz");
+    registry.save(&tetrahedron(), &path).unwrap();
+    let read = registry.load(&path).unwrap();
+    assert_eq!(read.vertices.len(), 12);
+    assert_eq!(read.triangles.len(), 4);
+    // a binary stl is still recognized by its content
+    let path = dir.join("tetrahedron.stl");
+    tetrahedron().save(&path).unwrap();
+    assert_eq!(registry.load(&path).unwrap().vertices.len(), 4);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// the triangles of `mesh` with their own vertices, moved by up to `noise`
+fn triangle_soup(mesh: &IndexMesh, noise: f32) -> IndexMesh {
+    let mut rng = rand::thread_rng();
+    let mut soup = IndexMesh::new();
+    for t in mesh.triangles.iter() {
+        for i in [t.0, t.1, t.2] {
+            let offset = Vec3::new(rng.gen(), rng.gen(), rng.gen()) * 2. - Vec3::ONE;
+            soup.vertices.push(mesh.vertices[i] + offset * noise);
+        }
+        let n = soup.vertices.len();
+        soup.triangles.push(IndexTriangle(n - 3, n - 2, n - 1));
+    }
+    soup
+}
+
+#[test]
+fn test_weld_vertices() {
+    let mut soup = triangle_soup(&tetrahedron(), 1e-5);
+    assert!(!soup.to_halfedge_mesh().is_mesh_watertight());
+    let report = soup.weld_vertices(1e-3);
+    assert_eq!(
+        report,
+        WeldReport {
+            merged_vertices: 8,
+            removed_triangles: 0
+        }
+    );
+    assert_eq!(soup.vertices.len(), 4);
+    assert!(soup.to_halfedge_mesh().is_mesh_watertight());
+    assert_eq!(soup.weld_vertices(1e-3), WeldReport::default());
+
+    // a zero tolerance only merges equal positions
+    let mut soup = triangle_soup(&tetrahedron(), 0.);
+    soup.vertices[0] = Vec3::new(-0., 0., 0.);
+    assert_eq!(soup.weld_vertices(0.).merged_vertices, 8);
+
+    // a tolerance far below the coordinates saturates the grid cells
+    let mut soup = triangle_soup(&tetrahedron(), 0.);
+    assert_eq!(soup.weld_vertices(1e-20).merged_vertices, 8);
+
+    // collapsed triangles are removed, with their vertex data and groups
+    let mut mesh = tetrahedron();
+    mesh.vertices.push(Vec3::new(0., 0., 0.01));
+    mesh.triangles.push(IndexTriangle(0, 4, 1));
+    mesh.set_colors((0..5).map(|i| Vec4::splat(i as f32)).collect())
+        .unwrap();
+    mesh.set_groups(vec![TriangleGroup {
+        object: "default".to_string(),
+        name: "extra".to_string(),
+        material: None,
+        triangles: 4..5,
+    }])
+    .unwrap();
+    let report = mesh.weld_vertices(0.05);
+    assert_eq!(report.merged_vertices, 1);
+    assert_eq!(report.removed_triangles, 1);
+    assert_eq!(mesh.triangles.len(), 4);
+    assert_eq!(mesh.colors().unwrap().len(), 4);
+    assert_eq!(mesh.colors().unwrap()[3], Vec4::splat(3.));
+    assert!(mesh.groups().is_empty());
+
+    let mut stl = vec![];
+    triangle_soup(&tetrahedron(), 0.).to_stl(&mut stl).unwrap();
+    let (mesh, report) = IndexMesh::from_stl_welded(&mut Cursor::new(stl), 1e-4).unwrap();
+    assert_eq!(mesh.vertices.len(), 4);
+    assert_eq!(report.merged_vertices, 0);
+}
+
+#[test]
+fn test_from_ply_ascii() {
+    let data = b"ply
+format ascii 1.0
+comment a quad and a triangle
+element vertex 5
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+property float quality
+element face 2
+property list uchar int vertex_indices
+element edge 1
+property int vertex1
+property int vertex2
+end_header
+0 0 0 255 0 0 0.5
+1 0 0 0 255 0 1
+1 1 0 0 0 255 1.5
+0 1 0 255 255 255 2
+0 0 1 0 0 0 2.5
+4 0 1 2 3
+3 0 1 4
+0 1
+";
+    let mesh = IndexMesh::from_ply(&mut Cursor::new(data.to_vec())).unwrap();
+    assert_eq!(mesh.vertices.len(), 5);
+    assert_eq!(mesh.vertices[2], Vec3::new(1., 1., 0.));
+    let triangles = mesh
+        .triangles()
+        .map(|t| [t.0, t.1, t.2])
+        .collect::<Vec<_>>();
+    assert_eq!(triangles, vec![[3, 0, 1], [1, 2, 3], [0, 1, 4]]);
+    assert!(mesh.normals().is_none());
+    let colors = mesh.colors().unwrap();
+    assert_eq!(colors[0], Vec4::new(1., 0., 0., 1.));
+    assert_eq!(colors[3], Vec4::ONE);
+    assert_eq!(mesh.attribute("quality").unwrap(), &[0.5, 1., 1.5, 2., 2.5]);
+    assert_eq!(mesh.attributes().count(), 1);
+
+    // a concave quad is ear clipped, a fan from the first corner would leave the face
+    let data = b"ply
+format ascii 1.0
+element vertex 4
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+2 1 0
+4 0 0
+2 3 0
+4 0 1 2 3
+";
+    let mesh = IndexMesh::from_ply(&mut Cursor::new(data.to_vec())).unwrap();
+    let normals = mesh
+        .triangles()
+        .map(|t| {
+            (mesh.vertices[t.1] - mesh.vertices[t.0]).cross(mesh.vertices[t.2] - mesh.vertices[t.0])
+        })
+        .collect::<Vec<_>>();
+    assert!(normals.iter().all(|n| n.z > 0.));
+    assert!((normals.iter().map(|n| n.z).sum::<f32>() / 2. - 4.).abs() < 1e-6);
+}
+
+#[test]
+fn test_ply_round_trip() {
+    let mut mesh = crate::Box { size: 1. }.to_mesh();
+    let count = mesh.vertices.len();
+    let normals = mesh.vertices().map(|v| v.normalize()).collect::<Vec<_>>();
+    let colors = (0..count)
+        .map(|i| Vec4::new(i as f32 / count as f32, 0.2, 1., 0.4))
+        .collect::<Vec<_>>();
+    mesh.set_normals(normals.clone()).unwrap();
+    mesh.set_colors(colors.clone()).unwrap();
+    mesh.set_attribute("confidence", (0..count).map(|i| i as f64 * 0.1).collect())
+        .unwrap();
+    mesh.set_attribute("id", (0..count).map(|i| i as f64).collect())
+        .unwrap();
+    assert!(mesh.set_attribute("short", vec![1.]).is_err());
+
+    for format in [
+        PlyFormat::Ascii,
+        PlyFormat::BinaryLittleEndian,
+        PlyFormat::BinaryBigEndian,
+    ] {
+        let mut writer = Cursor::new(Vec::new());
+        mesh.to_ply(&mut writer, format).unwrap();
+        let read = IndexMesh::from_ply(&mut Cursor::new(writer.into_inner())).unwrap();
+        assert_eq!(read.vertices, mesh.vertices);
+        let triangles = |m: &IndexMesh| m.triangles().map(|t| [t.0, t.1, t.2]).collect::<Vec<_>>();
+        assert_eq!(triangles(&read), triangles(&mesh));
+        assert_eq!(read.normals().unwrap(), &normals[..]);
+        for (a, b) in read.colors().unwrap().iter().zip(colors.iter()) {
+            assert!((*a - *b).abs().max_element() <= 1. / 255.);
+        }
+        let names = read.attributes().map(|(name, _)| name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["confidence", "id"]);
+        assert_eq!(read.attribute("confidence"), mesh.attribute("confidence"));
+        assert_eq!(read.attribute("id"), mesh.attribute("id"));
+    }
+}
+
+#[test]
+fn test_from_ply_invalid() {
+    let read = |data: &[u8]| IndexMesh::from_ply(&mut Cursor::new(data.to_vec()));
+    assert!(read(b"solid foobar\nendsolid foobar\n").is_err());
+    let header = "ply
+format binary_little_endian 1.0
+element vertex 3
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+";
+    let mut data = header.as_bytes().to_vec();
+    for v in [[0f32, 0., 0.], [1., 0., 0.], [0., 1., 0.]] {
+        v.iter().for_each(|x| data.extend(x.to_le_bytes()));
+    }
+    data.push(3);
+    let mut valid = data.clone();
+    [0i32, 1, 2]
+        .iter()
+        .for_each(|i| valid.extend(i.to_le_bytes()));
+    assert_eq!(read(&valid).unwrap().triangles.len(), 1);
+    // truncated
+    assert!(read(&valid[..valid.len() - 1]).is_err());
+    // a face without area
+    let mut degenerate = data.clone();
+    [0i32, 1, 1]
+        .iter()
+        .for_each(|i| degenerate.extend(i.to_le_bytes()));
+    assert!(read(&degenerate).is_err());
+    // missing vertex
+    [0i32, 1, 3]
+        .iter()
+        .for_each(|i| data.extend(i.to_le_bytes()));
+    assert!(read(&data).is_err());
+}
+
+/// json and buffer of a glb file
+fn read_glb(data: &[u8]) -> (serde_json::Value, Vec<u8>) {
+    let u32_at = |i: usize| u32::from_le_bytes(data[i..i + 4].try_into().unwrap());
+    assert_eq!(&data[..4], b"glTF");
+    assert_eq!(u32_at(4), 2);
+    assert_eq!(u32_at(8) as usize, data.len());
+    let json_len = u32_at(12) as usize;
+    assert_eq!(&data[16..20], b"JSON");
+    let json = serde_json::from_slice(&data[20..20 + json_len]).unwrap();
+    let bin = 20 + json_len;
+    assert_eq!(&data[bin + 4..bin + 8], b"BIN\0");
+    let bin_len = u32_at(bin) as usize;
+    assert_eq!(bin_len % 4, 0);
+    (json, data[bin + 8..bin + 8 + bin_len].to_vec())
+}
+
+/// floats of the accessor at `index`
+fn read_accessor(json: &serde_json::Value, buffer: &[u8], index: &serde_json::Value) -> Vec<f32> {
+    let accessor = &json["accessors"][index.as_u64().unwrap() as usize];
+    let view = &json["bufferViews"][accessor["bufferView"].as_u64().unwrap() as usize];
+    let offset = view["byteOffset"].as_u64().unwrap() as usize;
+    let length = view["byteLength"].as_u64().unwrap() as usize;
+    buffer[offset..offset + length]
+        .chunks(4)
+        .map(|b| match accessor["componentType"].as_u64().unwrap() {
+            5126 => f32::from_le_bytes(b.try_into().unwrap()),
+            _ => u32::from_le_bytes(b.try_into().unwrap()) as f32,
+        })
+        .collect()
+}
+
+#[test]
+fn test_to_glb() {
+    let mut mesh = crate::Box { size: 1. }.to_mesh();
+    let mut writer = Cursor::new(Vec::new());
+    mesh.to_glb(&mut writer).unwrap();
+    let data = writer.into_inner();
+    assert_eq!(data.len() % 4, 0);
+    let (json, buffer) = read_glb(&data);
+    assert_eq!(json["asset"]["version"], "2.0");
+    let primitive = &json["meshes"][0]["primitives"][0];
+    // no normals, so flat shaded with 3 vertices per triangle
+    let positions = read_accessor(&json, &buffer, &primitive["attributes"]["POSITION"]);
+    assert_eq!(positions.len(), mesh.triangles.len() * 9);
+    assert!(primitive["attributes"]["COLOR_0"].is_null());
+    let position =
+        &json["accessors"][primitive["attributes"]["POSITION"].as_u64().unwrap() as usize];
+    assert_eq!(position["min"], serde_json::json!([-0.5, -0.5, -0.5]));
+    assert_eq!(position["max"], serde_json::json!([0.5, 0.5, 0.5]));
+
+    // normals and colors give an indexed mesh on the shared vertices
+    let normals = mesh.vertices().map(|v| v.normalize()).collect::<Vec<_>>();
+    let colors = vec![Vec4::new(1., 0.5, 0., 1.); mesh.vertices.len()];
+    mesh.set_normals(normals.clone()).unwrap();
+    mesh.set_colors(colors).unwrap();
+    let mut writer = Cursor::new(Vec::new());
+    mesh.to_glb(&mut writer).unwrap();
+    let (json, buffer) = read_glb(&writer.into_inner());
+    let primitive = &json["meshes"][0]["primitives"][0];
+    let positions = read_accessor(&json, &buffer, &primitive["attributes"]["POSITION"]);
+    let expected = mesh
+        .vertices()
+        .flat_map(|v| v.to_array())
+        .collect::<Vec<_>>();
+    assert_eq!(positions, expected);
+    let normals_read = read_accessor(&json, &buffer, &primitive["attributes"]["NORMAL"]);
+    assert_eq!(
+        normals_read,
+        normals
+            .iter()
+            .flat_map(|n| n.to_array())
+            .collect::<Vec<_>>()
+    );
+    let colors = read_accessor(&json, &buffer, &primitive["attributes"]["COLOR_0"]);
+    assert_eq!(&colors[..3], &[1., 0.5, 0.]);
+    let indices = read_accessor(&json, &buffer, &primitive["indices"]);
+    let expected = mesh
+        .triangles()
+        .flat_map(|t| [t.0 as f32, t.1 as f32, t.2 as f32])
+        .collect::<Vec<_>>();
+    assert_eq!(indices, expected);
+}
+
+#[test]
+fn test_to_gltf() {
+    let mesh = crate::Box { size: 2. }.to_mesh().to_renderable_mesh();
+    let mut writer = Cursor::new(Vec::new());
+    mesh.to_gltf(&mut writer).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&writer.into_inner()).unwrap();
+    let uri = json["buffers"][0]["uri"].as_str().unwrap();
+    let encoded = uri
+        .strip_prefix("data:application/octet-stream;base64,")
+        .unwrap();
+    use base64::Engine;
+    let buffer = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .unwrap();
+    assert_eq!(json["buffers"][0]["byteLength"], buffer.len());
+    let primitive = &json["meshes"][0]["primitives"][0];
+    let positions = read_accessor(&json, &buffer, &primitive["attributes"]["POSITION"]);
+    assert_eq!(positions, mesh.positions.concat());
+    let indices = read_accessor(&json, &buffer, &primitive["indices"]);
+    assert_eq!(indices.len(), mesh.indices.len());
+
+    let broken = crate::RenderableMesh {
+        indices: vec![0, 1, 100],
+        ..mesh
+    };
+    assert!(broken.to_gltf(&mut Cursor::new(Vec::new())).is_err());
+}
+
+#[test]
+fn test_gltf_round_trip() {
+    let mut mesh = crate::Box { size: 1. }.to_mesh();
+    let normals = mesh.vertices().map(|v| v.normalize()).collect::<Vec<_>>();
+    let colors = vec![Vec4::new(0.25, 0.5, 1., 1.); mesh.vertices.len()];
+    mesh.set_normals(normals.clone()).unwrap();
+    mesh.set_colors(colors.clone()).unwrap();
+    let mut glb = Cursor::new(Vec::new());
+    mesh.to_glb(&mut glb).unwrap();
+    let mut gltf = Cursor::new(Vec::new());
+    mesh.to_gltf(&mut gltf).unwrap();
+    for data in [glb.into_inner(), gltf.into_inner()] {
+        let read = IndexMesh::from_gltf(&mut Cursor::new(data)).unwrap();
+        assert_eq!(read.vertices, mesh.vertices);
+        let triangles = |m: &IndexMesh| m.triangles().map(|t| [t.0, t.1, t.2]).collect::<Vec<_>>();
+        assert_eq!(triangles(&read), triangles(&mesh));
+        assert_eq!(read.normals().unwrap(), &normals[..]);
+        assert_eq!(read.colors().unwrap(), &colors[..]);
+    }
+}
+
+#[test]
+fn test_from_gltf_nodes() {
+    use base64::Engine;
+    // a unit quad as triangle strip with u8 indices
+    let mut buffer = vec![];
+    for v in [[0f32, 0., 0.], [1., 0., 0.], [0., 1., 0.], [1., 1., 0.]] {
+        v.iter().for_each(|x| buffer.extend(x.to_le_bytes()));
+    }
+    buffer.extend([0u8, 1, 2, 3]);
+    let uri = format!(
+        "data:application/octet-stream;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(&buffer)
+    );
+    let json = serde_json::json!({
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0, 2] }],
+        "nodes": [
+            { "name": "parent", "translation": [10., 0., 0.], "children": [1] },
+            { "name": "child", "mesh": 0, "scale": [2., 2., 2.] },
+            { "name": "mirror", "mesh": 0, "matrix": [-1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1.] },
+            { "name": "not in scene", "mesh": 0 },
+        ],
+        "meshes": [{ "primitives": [
+            { "attributes": { "POSITION": 0 }, "indices": 1, "mode": 5 },
+            { "attributes": { "POSITION": 0 }, "mode": 1 },
+        ] }],
+        "buffers": [{ "byteLength": buffer.len(), "uri": uri }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 48 },
+            { "buffer": 0, "byteOffset": 48, "byteLength": 4 },
+        ],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3" },
+            { "bufferView": 1, "componentType": 5121, "count": 4, "type": "SCALAR" },
+        ],
+    });
+    let data = serde_json::to_vec(&json).unwrap();
+    let nodes = IndexMesh::from_gltf_nodes(&mut Cursor::new(data.clone())).unwrap();
+    let names = nodes
+        .iter()
+        .map(|n| n.name.as_deref().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["child", "mirror"]);
+    let triangles = |m: &IndexMesh| m.triangles().map(|t| [t.0, t.1, t.2]).collect::<Vec<_>>();
+
+    let child = &nodes[0].mesh;
+    assert_eq!(child.vertices[3], Vec3::new(12., 2., 0.));
+    assert_eq!(triangles(child), vec![[0, 1, 2], [2, 1, 3]]);
+    assert_eq!(
+        nodes[0].transform.transform_point3(Vec3::ONE),
+        Vec3::new(12., 2., 2.)
+    );
+    let mirror = &nodes[1].mesh;
+    assert_eq!(mirror.vertices[3], Vec3::new(-1., 1., 0.));
+    assert_eq!(triangles(mirror), vec![[0, 2, 1], [2, 3, 1]]);
+
+    let merged = IndexMesh::from_gltf(&mut Cursor::new(data)).unwrap();
+    assert_eq!(merged.vertices.len(), 8);
+    assert_eq!(triangles(&merged)[2], [4, 6, 5]);
+}
+
+#[test]
+fn test_from_gltf_invalid() {
+    let read = |json: serde_json::Value| {
+        IndexMesh::from_gltf(&mut Cursor::new(serde_json::to_vec(&json).unwrap()))
+    };
+    assert!(IndexMesh::from_gltf(&mut Cursor::new(b"glTF\x01\0\0\0".to_vec())).is_err());
+    let external = serde_json::json!({
+        "asset": { "version": "2.0" },
+        "buffers": [{ "byteLength": 4, "uri": "mesh.bin" }],
+    });
+    assert!(read(external).is_err());
+    let out_of_buffer = serde_json::json!({
+        "asset": { "version": "2.0" },
+        "nodes": [{ "mesh": 0 }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 } }] }],
+        "buffers": [{ "byteLength": 4, "uri": "data:application/octet-stream;base64,AAAAAA==" }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 4 }],
+        "accessors": [{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }],
+    });
+    assert!(read(out_of_buffer).is_err());
+}
+
+#[test]
+fn test_from_gltf_untrusted_accessor() {
+    use base64::Engine;
+    // a triangle with u8 indices, the last one is out of the vertices
+    let mut buffer = vec![];
+    for v in [[0f32, 0., 0.], [1., 0., 0.], [0., 1., 0.]] {
+        v.iter().for_each(|x| buffer.extend(x.to_le_bytes()));
+    }
+    buffer.extend([0u8, 1, 3, 0]);
+    let uri = format!(
+        "data:application/octet-stream;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(&buffer)
+    );
+    let read = |position_count: u64, index_count: u64| {
+        let json = serde_json::json!({
+            "asset": { "version": "2.0" },
+            "nodes": [{ "mesh": 0 }],
+            "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1 }] }],
+            "buffers": [{ "byteLength": buffer.len(), "uri": uri }],
+            "bufferViews": [
+                { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+                { "buffer": 0, "byteOffset": 36, "byteLength": 4 },
+            ],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": position_count, "type": "VEC3" },
+                { "bufferView": 1, "componentType": 5121, "count": index_count, "type": "SCALAR" },
+            ],
+        });
+        IndexMesh::from_gltf(&mut Cursor::new(serde_json::to_vec(&json).unwrap()))
+    };
+    assert_eq!(read(3, 2).unwrap().triangles.len(), 0);
+    // index 3 of 3 vertices
+    assert!(read(3, 3).is_err());
+    // counts beyond the buffer view, and ones that overflow the byte length
+    assert!(read(4, 2).is_err());
+    assert!(read(u64::MAX / 2, 2).is_err());
+    assert!(read(3, u64::MAX).is_err());
+}
+
+#[test]
+fn test_from_heightfield() {
+    let heights = [0., 1., 2., 1., 2., 3.];
+    let mesh =
+        IndexMesh::from_heightfield(&heights, 3, Vec2::new(2., 1.), &SurfaceOption::default())
+            .unwrap();
+    assert_eq!(mesh.vertices.len(), 6);
+    assert_eq!(mesh.triangles.len(), 4);
+    assert_eq!(mesh.vertices[0], Vec3::new(-1., -0.5, 0.));
+    assert_eq!(mesh.vertices[5], Vec3::new(1., 0.5, 3.));
+    assert!(!mesh.to_halfedge_mesh().is_mesh_watertight());
+
+    // a plane resampled bilinearly stays the same plane, and the base makes a solid
+    let option = SurfaceOption {
+        resolution: Some([8, 5]),
+        base: Some(-1.),
+        ..SurfaceOption::default()
+    };
+    let mesh = IndexMesh::from_heightfield(&heights, 3, Vec2::new(2., 1.), &option).unwrap();
+    assert_eq!(mesh.vertices.len(), 2 * 9 * 6);
+    assert!(mesh.vertices[..54]
+        .iter()
+        .all(|v| (v.z - (v.x + 1. + v.y + 0.5)).abs() < 1e-5));
+    assert_closed(&mesh);
+    // mean height 1.5 above the base at -1 over an area of 2
+    assert!((volume(&mesh) - 5.).abs() < 1e-4);
+
+    assert!(IndexMesh::from_heightfield(&heights, 4, Vec2::ONE, &option).is_err());
+    assert!(IndexMesh::from_heightfield(&heights, 6, Vec2::ONE, &option).is_err());
+    assert!(IndexMesh::from_heightfield(&[], 0, Vec2::ONE, &option).is_err());
+}
+
+#[test]
+fn test_from_parametric() {
+    use std::f32::consts::TAU;
+
+    let torus = |u: f32, v: f32| {
+        let (u, v) = (u * TAU, v * TAU);
+        let r = 2. + 0.5 * v.cos();
+        Vec3::new(r * u.cos(), r * u.sin(), 0.5 * v.sin())
+    };
+    let option = SurfaceOption {
+        resolution: Some([48, 24]),
+        wrap_u: true,
+        wrap_v: true,
+        base: Some(0.),
+    };
+    let mesh = IndexMesh::from_parametric(torus, &option);
+    assert_eq!(mesh.vertices.len(), 48 * 24);
+    assert_eq!(mesh.triangles.len(), 2 * 48 * 24);
+    assert_closed(&mesh);
+    // 2 pi^2 R r^2
+    let exact = 2. * (TAU / 2.).powi(2) * 2. * 0.25;
+    assert!((volume(&mesh) - exact).abs() / exact < 0.02);
+
+    // an open tube wrapped around z
+    let tube = |u: f32, v: f32| Vec3::new((u * TAU).cos(), (u * TAU).sin(), v);
+    let option = SurfaceOption {
+        resolution: Some([16, 2]),
+        wrap_u: true,
+        ..SurfaceOption::default()
+    };
+    let mesh = IndexMesh::from_parametric(tube, &option);
+    assert_eq!(mesh.vertices.len(), 16 * 3);
+    assert_eq!(mesh.triangles.len(), 2 * 16 * 2);
+    assert!(!mesh.to_halfedge_mesh().is_mesh_watertight());
+
+    // a wavy sheet on a base is a solid with the base below
+    let sheet = |u: f32, v: f32| Vec3::new(u, v, 1. + 0.2 * (u * TAU).sin());
+    let mesh = IndexMesh::from_parametric(
+        sheet,
+        &SurfaceOption {
+            base: Some(0.),
+            ..SurfaceOption::default()
+        },
+    );
+    assert_eq!(mesh.vertices.len(), 2 * 33 * 33);
+    assert_closed(&mesh);
+    assert!((volume(&mesh) - 1.).abs() < 1e-3);
+}
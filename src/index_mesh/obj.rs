@@ -0,0 +1,249 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+};
+
+use anyhow::{bail, Context};
+use glam::{Vec2, Vec3};
+
+use super::{triangulate_corners, IndexMesh, IndexTriangle, Material, TriangleGroup};
+
+/// name the obj crate gives to objects and groups without one
+const DEFAULT_NAME: &str = "default";
+
+impl IndexMesh {
+    /// read an obj file, quads and other polygons are triangulated by ear clipping.
+    /// texture coordinates and normals are kept when every face corner has them, a position used
+    /// with different ones is split into several vertices
+    pub fn from_obj<F: Read>(f: &mut F) -> anyhow::Result<Self> {
+        let data = ::obj::ObjData::load_buf(f)?;
+        let mut vertices = data
+            .position
+            .iter()
+            .copied()
+            .map(Vec3::from_array)
+            .collect::<Vec<_>>();
+        let position_count = vertices.len();
+        // texture and normal index of every vertex, positions take the first pair they are used with
+        let mut vertex_uvs: Vec<Option<usize>> = vec![None; position_count];
+        let mut vertex_normals: Vec<Option<usize>> = vec![None; position_count];
+        let mut claimed = vec![false; position_count];
+        let mut splits = HashMap::new();
+        let (mut all_uvs, mut all_normals, mut any_corner) = (true, true, false);
+
+        let mut triangles = vec![];
+        let mut groups: Vec<TriangleGroup> = vec![];
+        let mut face = vec![];
+        let mut index = 0;
+        for object in data.objects.iter() {
+            for group in object.groups.iter() {
+                let start = triangles.len();
+                for poly in group.polys.iter() {
+                    index += 1;
+                    face.clear();
+                    face.extend(poly.0.iter().map(|t| t.0));
+                    let corners = triangulate_corners(&vertices[..position_count], &face)
+                        .with_context(|| format!("invalid obj face {}", index))?;
+                    let mut face_vertices = Vec::with_capacity(poly.0.len());
+                    for &::obj::IndexTuple(p, t, n) in poly.0.iter() {
+                        if t.is_some_and(|t| t >= data.texture.len())
+                            || n.is_some_and(|n| n >= data.normal.len())
+                        {
+                            bail!("invalid obj face {}: index out of range", index);
+                        }
+                        any_corner = true;
+                        all_uvs &= t.is_some();
+                        all_normals &= n.is_some();
+                        let v = *splits.entry((p, t, n)).or_insert_with(|| {
+                            if !claimed[p] {
+                                claimed[p] = true;
+                                vertex_uvs[p] = t;
+                                vertex_normals[p] = n;
+                                p
+                            } else {
+                                vertices.push(vertices[p]);
+                                vertex_uvs.push(t);
+                                vertex_normals.push(n);
+                                vertices.len() - 1
+                            }
+                        });
+                        face_vertices.push(v);
+                    }
+                    triangles.extend(corners.into_iter().map(|[a, b, c]| {
+                        IndexTriangle(face_vertices[a], face_vertices[b], face_vertices[c])
+                    }));
+                }
+                if triangles.len() == start {
+                    continue;
+                }
+                let material = group.material.as_ref().map(|m| match m {
+                    ::obj::ObjMaterial::Ref(name) => name.clone(),
+                    ::obj::ObjMaterial::Mtl(m) => m.name.clone(),
+                });
+                match groups.last_mut() {
+                    Some(last)
+                        if last.triangles.end == start
+                            && last.object == object.name
+                            && last.name == group.name
+                            && last.material == material =>
+                    {
+                        last.triangles.end = triangles.len();
+                    }
+                    _ => groups.push(TriangleGroup {
+                        object: object.name.clone(),
+                        name: group.name.clone(),
+                        material,
+                        triangles: start..triangles.len(),
+                    }),
+                }
+            }
+        }
+
+        let uvs = (any_corner && all_uvs).then(|| {
+            vertex_uvs
+                .iter()
+                .map(|t| t.map_or(Vec2::ZERO, |t| Vec2::from_array(data.texture[t])))
+                .collect()
+        });
+        let normals = (any_corner && all_normals).then(|| {
+            vertex_normals
+                .iter()
+                .map(|n| n.map_or(Vec3::ZERO, |n| Vec3::from_array(data.normal[n])))
+                .collect()
+        });
+        Ok(IndexMesh {
+            vertices,
+            triangles,
+            normals,
+            uvs,
+            groups,
+            ..IndexMesh::new()
+        })
+    }
+
+    /// read an obj file and the mtl file with its materials
+    pub fn from_obj_with_mtl<F: Read, M: Read>(f: &mut F, mtl: &mut M) -> anyhow::Result<Self> {
+        let mut mesh = Self::from_obj(f)?;
+        mesh.materials = read_mtl(mtl)?;
+        Ok(mesh)
+    }
+
+    /// write an obj file with texture coordinates, normals and groups when the mesh has them
+    pub fn to_obj<F: Write>(&self, f: &mut F) -> anyhow::Result<()> {
+        self.write_obj(f, None)
+    }
+
+    /// write an obj file that refers to `mtl_name`, and the mtl file with every material of the
+    /// mesh. materials used by a group without a definition are written empty
+    pub fn to_obj_with_mtl<F: Write, M: Write>(
+        &self,
+        f: &mut F,
+        mtl_name: &str,
+        mtl: &mut M,
+    ) -> anyhow::Result<()> {
+        self.write_obj(f, Some(mtl_name))?;
+        for (i, material) in self.materials.iter().enumerate() {
+            if i > 0 {
+                writeln!(mtl)?;
+            }
+            writeln!(mtl, "newmtl {}", material.name)?;
+            for (keyword, value) in material.properties.iter() {
+                writeln!(mtl, "{} {}", keyword, value)?;
+            }
+        }
+        let mut written = self
+            .materials
+            .iter()
+            .map(|m| m.name.as_str())
+            .collect::<Vec<_>>();
+        for name in self.groups.iter().filter_map(|g| g.material.as_deref()) {
+            if !written.contains(&name) {
+                if !written.is_empty() {
+                    writeln!(mtl)?;
+                }
+                writeln!(mtl, "newmtl {}", name)?;
+                written.push(name);
+            }
+        }
+        Ok(())
+    }
+
+    fn write_obj<F: Write>(&self, f: &mut F, mtllib: Option<&str>) -> anyhow::Result<()> {
+        if let Some(name) = mtllib {
+            writeln!(f, "mtllib {}", name)?;
+        }
+        for v in self.vertices.iter() {
+            writeln!(f, "v {} {} {}", v.x, v.y, v.z)?;
+        }
+        for t in self.uvs.iter().flatten() {
+            writeln!(f, "vt {} {}", t.x, t.y)?;
+        }
+        for n in self.normals.iter().flatten() {
+            writeln!(f, "vn {} {} {}", n.x, n.y, n.z)?;
+        }
+
+        let corner = |i: usize| match (self.uvs.is_some(), self.normals.is_some()) {
+            (false, false) => format!("{}", i + 1),
+            (true, false) => format!("{}/{}", i + 1, i + 1),
+            (false, true) => format!("{}//{}", i + 1, i + 1),
+            (true, true) => format!("{}/{}/{}", i + 1, i + 1, i + 1),
+        };
+        let mut object = DEFAULT_NAME;
+        // empty groups have no face to start at and would hold back the ones after them
+        let mut groups = self
+            .groups
+            .iter()
+            .filter(|g| !g.triangles.is_empty())
+            .peekable();
+        let mut group_end = None;
+        for (i, t) in self.triangles.iter().enumerate() {
+            if let Some(group) = groups.next_if(|g| g.triangles.start == i) {
+                if group.object != object {
+                    writeln!(f, "o {}", group.object)?;
+                    object = &group.object;
+                }
+                writeln!(f, "g {}", group.name)?;
+                if let Some(material) = &group.material {
+                    writeln!(f, "usemtl {}", material)?;
+                }
+                group_end = Some(group.triangles.end);
+            } else if group_end == Some(i) {
+                writeln!(f, "g {}", DEFAULT_NAME)?;
+                group_end = None;
+            }
+            writeln!(f, "f {} {} {}", corner(t.0), corner(t.1), corner(t.2))?;
+        }
+        Ok(())
+    }
+}
+
+/// materials of an mtl file, every statement after `newmtl` is kept as a property
+fn read_mtl<M: Read>(mtl: &mut M) -> anyhow::Result<Vec<Material>> {
+    let mut materials: Vec<Material> = vec![];
+    for (i, line) in BufReader::new(mtl).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (keyword, value) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(k, v)| (k, v.trim()));
+        if keyword == "newmtl" {
+            if value.is_empty() {
+                bail!("mtl line {}: newmtl without a name", i + 1);
+            }
+            materials.push(Material {
+                name: value.to_string(),
+                properties: vec![],
+            });
+        } else if let Some(material) = materials.last_mut() {
+            material
+                .properties
+                .push((keyword.to_string(), value.to_string()));
+        } else {
+            bail!("mtl line {}: {} before newmtl", i + 1, keyword);
+        }
+    }
+    Ok(materials)
+}
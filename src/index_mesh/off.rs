@@ -0,0 +1,246 @@
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+
+use anyhow::{anyhow, bail, Context};
+use glam::{Vec2, Vec3, Vec4};
+
+use super::{triangulate_face, IndexMesh};
+
+/// which optional values follow the position of each vertex
+#[derive(Default)]
+struct Header {
+    uvs: bool,
+    colors: bool,
+    normals: bool,
+}
+
+impl Header {
+    /// parse a keyword like `OFF`, `COFF`, `NOFF` or `STCNOFF`. the vertex count may be glued to
+    /// the keyword as in ModelNet's `OFF490 518 0`, it is returned with the header
+    fn parse(token: &str) -> anyhow::Result<(Self, &str)> {
+        let end = token
+            .find("OFF")
+            .map(|i| i + 3)
+            .ok_or_else(|| anyhow!("not an off file"))?;
+        let (keyword, count) = token.split_at(end);
+        if !count.bytes().all(|b| b.is_ascii_digit()) {
+            bail!("not an off file");
+        }
+        let mut prefix = &keyword[..end - 3];
+        let mut header = Header::default();
+        if let Some(rest) = prefix.strip_prefix("ST") {
+            header.uvs = true;
+            prefix = rest;
+        }
+        if let Some(rest) = prefix.strip_prefix('C') {
+            header.colors = true;
+            prefix = rest;
+        }
+        if let Some(rest) = prefix.strip_prefix('N') {
+            header.normals = true;
+            prefix = rest;
+        }
+        if !prefix.is_empty() {
+            bail!("unsupported off variant {}", keyword);
+        }
+        Ok((header, count))
+    }
+
+    fn keyword(&self) -> String {
+        let mut keyword = String::new();
+        if self.uvs {
+            keyword.push_str("ST");
+        }
+        if self.colors {
+            keyword.push('C');
+        }
+        if self.normals {
+            keyword.push('N');
+        }
+        keyword.push_str("OFF");
+        keyword
+    }
+}
+
+/// whether the first keyword of `head` is an off keyword this reader supports
+pub(super) fn sniff_off(head: &[u8]) -> bool {
+    String::from_utf8_lossy(head)
+        .lines()
+        .map(|l| l.split('#').next().unwrap_or_default())
+        .find_map(|l| l.split_whitespace().next())
+        .is_some_and(|keyword| Header::parse(keyword).is_ok())
+}
+
+fn parse_values(tokens: &[&str]) -> anyhow::Result<Vec<f32>> {
+    tokens
+        .iter()
+        .map(|t| {
+            t.parse::<f32>()
+                .with_context(|| format!("invalid off number {}", t))
+        })
+        .collect()
+}
+
+impl IndexMesh {
+    /// read an ascii off file. normals (NOFF), colors (COFF) and texture coordinates (STOFF) are
+    /// kept, integer colors are in [0, 255] and decimal ones in [0, 1]. polygons are triangulated
+    /// by ear clipping
+    pub fn from_off<F: Read>(f: &mut F) -> anyhow::Result<Self> {
+        let mut lines = vec![];
+        for line in BufReader::new(f).lines() {
+            let mut line = line?;
+            if let Some(i) = line.find('#') {
+                line.truncate(i);
+            }
+            if !line.trim().is_empty() {
+                lines.push(line);
+            }
+        }
+        let mut lines = lines
+            .iter()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>());
+
+        let mut first = lines.next().ok_or_else(|| anyhow!("empty off file"))?;
+        let (header, count) = Header::parse(first[0])?;
+        // the counts may follow the keyword on the same line, the first one even without a space
+        if count.is_empty() {
+            first.remove(0);
+        } else {
+            first[0] = count;
+        }
+        let counts = match first.as_slice() {
+            [] => lines
+                .next()
+                .ok_or_else(|| anyhow!("off file without counts"))?,
+            [format, ..] if *format == "BINARY" => bail!("binary off is not supported"),
+            rest => rest.to_vec(),
+        };
+        let counts = counts
+            .iter()
+            .take(2)
+            .map(|c| c.parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .context("invalid off counts")?;
+        let &[vertex_count, face_count] = counts.as_slice() else {
+            bail!("off file without counts");
+        };
+
+        let mut mesh = IndexMesh::new();
+        let (mut normals, mut colors, mut uvs) = (vec![], vec![], vec![]);
+        for i in 0..vertex_count {
+            let tokens = lines
+                .next()
+                .ok_or_else(|| anyhow!("off file ends at vertex {}", i + 1))?;
+            let values =
+                parse_values(&tokens).with_context(|| format!("invalid off vertex {}", i + 1))?;
+            // rgb or rgba, followed by the texture coordinates if any
+            let color_len = values.len().saturating_sub(
+                3 + if header.normals { 3 } else { 0 } + if header.uvs { 2 } else { 0 },
+            );
+            let mut rest = values.as_slice();
+            let mut take = |n: usize| {
+                let (head, tail) = rest.split_at(n.min(rest.len()));
+                rest = tail;
+                (head.len() == n).then_some(head)
+            };
+            let short = || anyhow!("off vertex {} has too few values", i + 1);
+            mesh.vertices
+                .push(Vec3::from_slice(take(3).ok_or_else(short)?));
+            if header.normals {
+                normals.push(Vec3::from_slice(take(3).ok_or_else(short)?));
+            }
+            if header.colors {
+                // integer colors are in [0, 255], ones with a point or an exponent in [0, 1]
+                let start = 3 + if header.normals { 3 } else { 0 };
+                let color = take(color_len).map(|color| {
+                    color
+                        .iter()
+                        .zip(&tokens[start..])
+                        .map(|(value, token)| match token.contains(['.', 'e', 'E']) {
+                            true => *value,
+                            false => value / 255.,
+                        })
+                        .collect::<Vec<_>>()
+                });
+                // alpha defaults to 1 for rgb colors
+                let color = match color.as_deref() {
+                    Some(&[r, g, b]) => Vec4::new(r, g, b, 1.),
+                    Some(&[r, g, b, a]) => Vec4::new(r, g, b, a),
+                    _ => bail!("off vertex {} needs 3 or 4 color values", i + 1),
+                };
+                colors.push(color);
+            }
+            if header.uvs {
+                uvs.push(Vec2::from_slice(take(2).ok_or_else(short)?));
+            }
+        }
+
+        let mut face = vec![];
+        for i in 0..face_count {
+            let tokens = lines
+                .next()
+                .ok_or_else(|| anyhow!("off file ends at face {}", i + 1))?;
+            let mut indices = tokens.iter().map(|t| t.parse::<usize>());
+            let count = indices
+                .next()
+                .and_then(Result::ok)
+                .ok_or_else(|| anyhow!("invalid off face {}", i + 1))?;
+            face.clear();
+            // a face color may follow the indices, it is ignored
+            for index in indices.take(count) {
+                face.push(index.with_context(|| format!("invalid off face {}", i + 1))?);
+            }
+            if face.len() != count {
+                bail!("off face {} has too few indices", i + 1);
+            }
+            let triangles = triangulate_face(&mesh.vertices, &face)
+                .with_context(|| format!("invalid off face {}", i + 1))?;
+            mesh.triangles.extend(triangles);
+        }
+
+        if header.normals {
+            mesh.normals = Some(normals);
+        }
+        if header.colors {
+            mesh.colors = Some(colors);
+        }
+        if header.uvs {
+            mesh.uvs = Some(uvs);
+        }
+        Ok(mesh)
+    }
+
+    /// write an ascii off file, the keyword tells which of normals, colors and texture coordinates
+    /// the mesh has. colors are written as rgba in [0, 1]
+    pub fn to_off<F: Write>(&self, f: &mut F) -> anyhow::Result<()> {
+        let header = Header {
+            uvs: self.uvs.is_some(),
+            colors: self.colors.is_some(),
+            normals: self.normals.is_some(),
+        };
+        let mut w = BufWriter::new(f);
+        writeln!(w, "{}", header.keyword())?;
+        writeln!(w, "{} {} 0", self.vertices.len(), self.triangles.len())?;
+        for (i, v) in self.vertices.iter().enumerate() {
+            write!(w, "{} {} {}", v.x, v.y, v.z)?;
+            if let Some(normals) = self.normals.as_ref() {
+                let n = normals[i];
+                write!(w, " {} {} {}", n.x, n.y, n.z)?;
+            }
+            if let Some(colors) = self.colors.as_ref() {
+                let c = colors[i];
+                // debug formatting keeps the point that marks colors in [0, 1]
+                write!(w, " {:?} {:?} {:?} {:?}", c.x, c.y, c.z, c.w)?;
+            }
+            if let Some(uvs) = self.uvs.as_ref() {
+                let t = uvs[i];
+                write!(w, " {} {}", t.x, t.y)?;
+            }
+            writeln!(w)?;
+        }
+        for t in self.triangles.iter() {
+            writeln!(w, "3 {} {} {}", t.0, t.1, t.2)?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+}
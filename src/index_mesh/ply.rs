@@ -0,0 +1,494 @@
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+
+use anyhow::{anyhow, bail, Context};
+use glam::{Vec3, Vec4};
+
+use super::{triangulate_face, IndexMesh};
+
+/// encoding of the data after the ply header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlyFormat {
+    /// whitespace separated text
+    Ascii,
+    /// packed little-endian values
+    BinaryLittleEndian,
+    /// packed big-endian values
+    BinaryBigEndian,
+}
+
+impl PlyFormat {
+    fn name(self) -> &'static str {
+        match self {
+            PlyFormat::Ascii => "ascii",
+            PlyFormat::BinaryLittleEndian => "binary_little_endian",
+            PlyFormat::BinaryBigEndian => "binary_big_endian",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Scalar {
+    Char,
+    UChar,
+    Short,
+    UShort,
+    Int,
+    UInt,
+    Float,
+    Double,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> anyhow::Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Scalar::Char,
+            "uchar" | "uint8" => Scalar::UChar,
+            "short" | "int16" => Scalar::Short,
+            "ushort" | "uint16" => Scalar::UShort,
+            "int" | "int32" => Scalar::Int,
+            "uint" | "uint32" => Scalar::UInt,
+            "float" | "float32" => Scalar::Float,
+            "double" | "float64" => Scalar::Double,
+            _ => bail!("unknown ply type {}", name),
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Scalar::Char => "char",
+            Scalar::UChar => "uchar",
+            Scalar::Short => "short",
+            Scalar::UShort => "ushort",
+            Scalar::Int => "int",
+            Scalar::UInt => "uint",
+            Scalar::Float => "float",
+            Scalar::Double => "double",
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::Char | Scalar::UChar => 1,
+            Scalar::Short | Scalar::UShort => 2,
+            Scalar::Int | Scalar::UInt | Scalar::Float => 4,
+            Scalar::Double => 8,
+        }
+    }
+
+    /// colors stored as integers are divided by the largest value of their type
+    fn color_scale(self) -> f64 {
+        match self {
+            Scalar::Char => i8::MAX as f64,
+            Scalar::UChar => u8::MAX as f64,
+            Scalar::Short => i16::MAX as f64,
+            Scalar::UShort => u16::MAX as f64,
+            Scalar::Int => i32::MAX as f64,
+            Scalar::UInt => u32::MAX as f64,
+            Scalar::Float | Scalar::Double => 1.,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Property {
+    Scalar(Scalar),
+    /// type of the count, type of the items
+    List(Scalar, Scalar),
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<(String, Property)>,
+}
+
+impl Element {
+    fn scalar(&self, name: &str) -> Option<(usize, Scalar)> {
+        self.properties
+            .iter()
+            .enumerate()
+            .find_map(|(i, (n, p))| match p {
+                Property::Scalar(ty) if n == name => Some((i, *ty)),
+                _ => None,
+            })
+    }
+}
+
+fn read_header<R: BufRead>(reader: &mut R) -> anyhow::Result<(PlyFormat, Vec<Element>)> {
+    let mut line = String::new();
+    let mut next_line = |line: &mut String| -> anyhow::Result<()> {
+        line.clear();
+        if reader.read_line(line)? == 0 {
+            bail!("unexpected end of ply header");
+        }
+        Ok(())
+    };
+    next_line(&mut line)?;
+    if line.trim_end() != "ply" {
+        bail!("not a ply file");
+    }
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    loop {
+        next_line(&mut line)?;
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            ["end_header"] => break,
+            [] | ["comment", ..] | ["obj_info", ..] => {}
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                    _ => bail!("unknown ply format {}", name),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse()?,
+                properties: vec![],
+            }),
+            ["property", "list", count, item, name] => {
+                let property = Property::List(Scalar::parse(count)?, Scalar::parse(item)?);
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| anyhow!("ply property before any element"))?;
+                element.properties.push((name.to_string(), property));
+            }
+            ["property", ty, name] => {
+                let property = Property::Scalar(Scalar::parse(ty)?);
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| anyhow!("ply property before any element"))?;
+                element.properties.push((name.to_string(), property));
+            }
+            _ => bail!("invalid ply header line: {}", line.trim_end()),
+        }
+    }
+    let format = format.ok_or_else(|| anyhow!("ply header without format"))?;
+    Ok((format, elements))
+}
+
+enum Body<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary { data: &'a [u8], big_endian: bool },
+}
+
+impl Body<'_> {
+    fn read(&mut self, ty: Scalar) -> anyhow::Result<f64> {
+        match self {
+            Body::Ascii(tokens) => {
+                let token = tokens
+                    .next()
+                    .ok_or_else(|| anyhow!("unexpected end of ply data"))?;
+                Ok(token.parse::<f64>()?)
+            }
+            Body::Binary { data, big_endian } => {
+                if data.len() < ty.size() {
+                    bail!("unexpected end of ply data");
+                }
+                let (bytes, rest) = data.split_at(ty.size());
+                *data = rest;
+                macro_rules! decode {
+                    ($t:ty) => {{
+                        let bytes = bytes.try_into().unwrap();
+                        if *big_endian {
+                            <$t>::from_be_bytes(bytes) as f64
+                        } else {
+                            <$t>::from_le_bytes(bytes) as f64
+                        }
+                    }};
+                }
+                Ok(match ty {
+                    Scalar::Char => decode!(i8),
+                    Scalar::UChar => decode!(u8),
+                    Scalar::Short => decode!(i16),
+                    Scalar::UShort => decode!(u16),
+                    Scalar::Int => decode!(i32),
+                    Scalar::UInt => decode!(u32),
+                    Scalar::Float => decode!(f32),
+                    Scalar::Double => decode!(f64),
+                })
+            }
+        }
+    }
+
+    /// read one item of `element`. every property gives one value in `values`, the count for lists.
+    /// the items of the list property at `keep_list` go to `list`
+    fn read_item(
+        &mut self,
+        element: &Element,
+        keep_list: Option<usize>,
+        values: &mut Vec<f64>,
+        list: &mut Vec<f64>,
+    ) -> anyhow::Result<()> {
+        values.clear();
+        list.clear();
+        for (i, (_, property)) in element.properties.iter().enumerate() {
+            match *property {
+                Property::Scalar(ty) => values.push(self.read(ty)?),
+                Property::List(count_ty, ty) => {
+                    let count = self.read(count_ty)?;
+                    if count < 0. {
+                        bail!("negative ply list length");
+                    }
+                    for _ in 0..count as usize {
+                        let value = self.read(ty)?;
+                        if keep_list == Some(i) {
+                            list.push(value);
+                        }
+                    }
+                    values.push(count);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_vertices(mesh: &mut IndexMesh, element: &Element, body: &mut Body) -> anyhow::Result<()> {
+    let find = |names: &[&str]| -> Option<Vec<(usize, Scalar)>> {
+        names.iter().map(|name| element.scalar(name)).collect()
+    };
+    let position = find(&["x", "y", "z"]).ok_or_else(|| anyhow!("ply vertex without x, y, z"))?;
+    let normal = find(&["nx", "ny", "nz"]);
+    let color = find(&["red", "green", "blue"]);
+    let alpha = element.scalar("alpha");
+    let known = [
+        "x", "y", "z", "nx", "ny", "nz", "red", "green", "blue", "alpha",
+    ];
+    let extra = element
+        .properties
+        .iter()
+        .enumerate()
+        .filter(|(_, (name, p))| {
+            matches!(p, Property::Scalar(_)) && !known.contains(&name.as_str())
+        })
+        .map(|(i, (name, _))| (i, name.clone()))
+        .collect::<Vec<_>>();
+
+    let mut normals = vec![];
+    let mut colors = vec![];
+    let mut attributes = vec![vec![]; extra.len()];
+    let (mut values, mut list) = (vec![], vec![]);
+    for _ in 0..element.count {
+        body.read_item(element, None, &mut values, &mut list)?;
+        let vec3 = |p: &[(usize, Scalar)]| {
+            Vec3::new(
+                values[p[0].0] as f32,
+                values[p[1].0] as f32,
+                values[p[2].0] as f32,
+            )
+        };
+        mesh.vertices.push(vec3(&position));
+        if let Some(normal) = normal.as_ref() {
+            normals.push(vec3(normal));
+        }
+        if let Some(color) = color.as_ref() {
+            let channel = |(i, ty): (usize, Scalar)| (values[i] / ty.color_scale()) as f32;
+            let a = alpha.map_or(1., channel);
+            colors.push(Vec4::new(
+                channel(color[0]),
+                channel(color[1]),
+                channel(color[2]),
+                a,
+            ));
+        }
+        for (values_of, (i, _)) in attributes.iter_mut().zip(extra.iter()) {
+            values_of.push(values[*i]);
+        }
+    }
+    if normal.is_some() {
+        mesh.normals = Some(normals);
+    }
+    if color.is_some() {
+        mesh.colors = Some(colors);
+    }
+    mesh.attributes = extra
+        .into_iter()
+        .map(|(_, name)| name)
+        .zip(attributes)
+        .collect();
+    Ok(())
+}
+
+/// vertex indices of every face, they are triangulated once all vertices are read
+fn read_faces(
+    faces: &mut Vec<Vec<usize>>,
+    element: &Element,
+    body: &mut Body,
+) -> anyhow::Result<()> {
+    let indices = element
+        .properties
+        .iter()
+        .position(|(name, p)| {
+            matches!(p, Property::List(..)) && (name == "vertex_indices" || name == "vertex_index")
+        })
+        .ok_or_else(|| anyhow!("ply face without vertex_indices"))?;
+    let (mut values, mut list) = (vec![], vec![]);
+    for _ in 0..element.count {
+        body.read_item(element, Some(indices), &mut values, &mut list)?;
+        if list.len() < 3 {
+            bail!("ply face with less than 3 vertices");
+        }
+        if list.iter().any(|&i| i < 0.) {
+            bail!("negative ply vertex index");
+        }
+        faces.push(list.iter().map(|&i| i as usize).collect());
+    }
+    Ok(())
+}
+
+/// one line of values in ascii, packed bytes in binary
+fn write_row<W: Write>(w: &mut W, format: PlyFormat, row: &[(Scalar, f64)]) -> anyhow::Result<()> {
+    match format {
+        PlyFormat::Ascii => {
+            for (i, &(ty, value)) in row.iter().enumerate() {
+                if i > 0 {
+                    w.write_all(b" ")?;
+                }
+                match ty {
+                    Scalar::Float => write!(w, "{}", value as f32)?,
+                    Scalar::Double => write!(w, "{}", value)?,
+                    _ => write!(w, "{}", value as i64)?,
+                }
+            }
+            w.write_all(b"\n")?;
+        }
+        PlyFormat::BinaryLittleEndian | PlyFormat::BinaryBigEndian => {
+            let big_endian = format == PlyFormat::BinaryBigEndian;
+            for &(ty, value) in row {
+                macro_rules! encode {
+                    ($t:ty) => {{
+                        let value = value as $t;
+                        if big_endian {
+                            w.write_all(&value.to_be_bytes())?
+                        } else {
+                            w.write_all(&value.to_le_bytes())?
+                        }
+                    }};
+                }
+                match ty {
+                    Scalar::Char => encode!(i8),
+                    Scalar::UChar => encode!(u8),
+                    Scalar::Short => encode!(i16),
+                    Scalar::UShort => encode!(u16),
+                    Scalar::Int => encode!(i32),
+                    Scalar::UInt => encode!(u32),
+                    Scalar::Float => encode!(f32),
+                    Scalar::Double => encode!(f64),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+impl IndexMesh {
+    /// read an ascii or binary ply file. vertex normals (nx, ny, nz) and colors (red, green, blue, alpha)
+    /// are kept, other scalar vertex properties become attributes. polygons are triangulated as fans
+    pub fn from_ply<F: Read>(f: &mut F) -> anyhow::Result<Self> {
+        let mut reader = BufReader::new(f);
+        let (format, elements) = read_header(&mut reader)?;
+        let mut data = vec![];
+        reader.read_to_end(&mut data)?;
+        let mut body = match format {
+            PlyFormat::Ascii => Body::Ascii(std::str::from_utf8(&data)?.split_ascii_whitespace()),
+            PlyFormat::BinaryLittleEndian => Body::Binary {
+                data: &data,
+                big_endian: false,
+            },
+            PlyFormat::BinaryBigEndian => Body::Binary {
+                data: &data,
+                big_endian: true,
+            },
+        };
+        let mut mesh = IndexMesh::new();
+        let mut faces = vec![];
+        let (mut values, mut list) = (vec![], vec![]);
+        for element in elements.iter() {
+            match element.name.as_str() {
+                "vertex" => read_vertices(&mut mesh, element, &mut body)?,
+                "face" => read_faces(&mut faces, element, &mut body)?,
+                _ => {
+                    for _ in 0..element.count {
+                        body.read_item(element, None, &mut values, &mut list)?;
+                    }
+                }
+            }
+        }
+        // polygons are ear clipped like the faces of obj and off files
+        for (i, face) in faces.iter().enumerate() {
+            let triangles = triangulate_face(&mesh.vertices, face)
+                .with_context(|| format!("invalid ply face {}", i + 1))?;
+            mesh.triangles.extend(triangles);
+        }
+        Ok(mesh)
+    }
+
+    /// write a ply file with normals, colors as uchar and attributes as double when the mesh has them
+    pub fn to_ply<F: Write>(&self, f: &mut F, format: PlyFormat) -> anyhow::Result<()> {
+        if let Some((name, _)) = self
+            .attributes
+            .iter()
+            .find(|(name, _)| name.is_empty() || name.contains(char::is_whitespace))
+        {
+            bail!("attribute name {:?} can not be written to ply", name);
+        }
+        let mut w = BufWriter::new(f);
+        writeln!(w, "ply")?;
+        writeln!(w, "format {} 1.0", format.name())?;
+        writeln!(w, "element vertex {}", self.vertices.len())?;
+        let mut vertex_properties = vec![];
+        vertex_properties.extend(["x", "y", "z"].map(|name| (Scalar::Float, name)));
+        if self.normals.is_some() {
+            vertex_properties.extend(["nx", "ny", "nz"].map(|name| (Scalar::Float, name)));
+        }
+        if self.colors.is_some() {
+            vertex_properties
+                .extend(["red", "green", "blue", "alpha"].map(|name| (Scalar::UChar, name)));
+        }
+        vertex_properties.extend(
+            self.attributes
+                .iter()
+                .map(|(name, _)| (Scalar::Double, name.as_str())),
+        );
+        for (ty, name) in vertex_properties {
+            writeln!(w, "property {} {}", ty.name(), name)?;
+        }
+        writeln!(w, "element face {}", self.triangles.len())?;
+        writeln!(w, "property list uchar int vertex_indices")?;
+        writeln!(w, "end_header")?;
+
+        let mut row = vec![];
+        for (i, v) in self.vertices.iter().enumerate() {
+            row.clear();
+            row.extend(v.to_array().map(|x| (Scalar::Float, x as f64)));
+            if let Some(normals) = self.normals.as_ref() {
+                row.extend(normals[i].to_array().map(|x| (Scalar::Float, x as f64)));
+            }
+            if let Some(colors) = self.colors.as_ref() {
+                row.extend(
+                    colors[i]
+                        .to_array()
+                        .map(|x| (Scalar::UChar, (x.clamp(0., 1.) * 255.).round() as f64)),
+                );
+            }
+            row.extend(
+                self.attributes
+                    .iter()
+                    .map(|(_, values)| (Scalar::Double, values[i])),
+            );
+            write_row(&mut w, format, &row)?;
+        }
+        for t in self.triangles.iter() {
+            row.clear();
+            row.push((Scalar::UChar, 3.));
+            row.extend([t.0, t.1, t.2].map(|i| (Scalar::Int, i as f64)));
+            write_row(&mut w, format, &row)?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+
+use anyhow::bail;
+use glam::{Vec3, Vec4};
+
+use super::{IndexMesh, IndexTriangle};
+
+/// encoding of an stl file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum StlFormat {
+    /// `solid`, `facet` and `vertex` lines
+    Ascii,
+    /// 80 byte header, triangle count and 50 bytes per triangle
+    #[default]
+    Binary,
+}
+
+/// how [`IndexMesh::to_stl_with`] writes a file
+#[derive(Clone, Debug, Default)]
+pub struct StlOption {
+    /// ascii or binary
+    pub format: StlFormat,
+    /// name after `solid` and `endsolid` in ascii files
+    pub name: String,
+    /// up to 80 bytes at the start of binary files, padded with zeros. some readers take binary
+    /// files whose header starts with `solid` for ascii
+    pub header: Vec<u8>,
+    /// the attribute bytes of every triangle in binary files, zero when `None`
+    pub attributes: Option<Vec<u16>>,
+}
+
+/// binary stl files are 84 bytes plus 50 bytes per triangle, which an ascii file can hardly match
+pub(crate) fn is_binary_stl(head: &[u8], len: u64) -> bool {
+    head.len() >= 84
+        && 84 + 50 * u32::from_le_bytes([head[80], head[81], head[82], head[83]]) as u64 == len
+}
+
+/// a float like `%e` in c, which every ascii stl reader understands
+fn scientific(x: f32) -> String {
+    let s = format!("{:.6e}", x);
+    match s.split_once('e') {
+        Some((mantissa, exponent)) => {
+            let (sign, digits) = match exponent.strip_prefix('-') {
+                Some(digits) => ('-', digits),
+                None => ('+', exponent),
+            };
+            format!("{}e{}{:0>2}", mantissa, sign, digits)
+        }
+        None => s,
+    }
+}
+
+impl IndexMesh {
+    /// read an ascii or binary stl file, binary files whose header starts with `solid` included
+    pub fn from_stl<F: Read + Seek>(f: &mut F) -> anyhow::Result<Self> {
+        let len = f.seek(SeekFrom::End(0))?;
+        f.seek(SeekFrom::Start(0))?;
+        let mut head = vec![];
+        f.by_ref().take(84).read_to_end(&mut head)?;
+        f.seek(SeekFrom::Start(0))?;
+        let mut res = IndexMesh::new();
+        let mesh = if is_binary_stl(&head, len) {
+            stl_io::BinaryStlReader::create_triangle_iterator(f)?.as_indexed_triangles()?
+        } else {
+            stl_io::read_stl(f)?
+        };
+        mesh.vertices.into_iter().for_each(|vertex| {
+            res.vertices
+                .push(Vec3::new(vertex[0], vertex[1], vertex[2]));
+        });
+        mesh.faces.into_iter().for_each(|face| {
+            res.triangles.push(IndexTriangle(
+                face.vertices[0],
+                face.vertices[1],
+                face.vertices[2],
+            ));
+        });
+        Ok(res)
+    }
+
+    /// write a binary stl file with a zero header
+    pub fn to_stl<F: Write>(&self, f: &mut F) -> anyhow::Result<()> {
+        self.to_stl_with(f, &StlOption::default())
+    }
+
+    /// write an ascii or binary stl file
+    pub fn to_stl_with<F: Write>(&self, f: &mut F, option: &StlOption) -> anyhow::Result<()> {
+        let mut w = BufWriter::new(f);
+        let facets = self.triangles.iter().map(|t| {
+            let [a, b, c] = [t.0, t.1, t.2].map(|i| self.vertices[i]);
+            ((b - a).cross(c - a).normalize_or_zero(), [a, b, c])
+        });
+        match option.format {
+            StlFormat::Ascii => {
+                if option.name.contains(['\n', '\r']) {
+                    bail!("stl solid name can not span lines");
+                }
+                let vector = |v: Vec3| {
+                    format!(
+                        "{} {} {}",
+                        scientific(v.x),
+                        scientific(v.y),
+                        scientific(v.z)
+                    )
+                };
+                writeln!(w, "solid {}", option.name)?;
+                for (normal, vertices) in facets {
+                    writeln!(w, "  facet normal {}", vector(normal))?;
+                    writeln!(w, "    outer loop")?;
+                    for v in vertices {
+                        writeln!(w, "      vertex {}", vector(v))?;
+                    }
+                    writeln!(w, "    endloop")?;
+                    writeln!(w, "  endfacet")?;
+                }
+                writeln!(w, "endsolid {}", option.name)?;
+            }
+            StlFormat::Binary => {
+                if option.header.len() > 80 {
+                    bail!("stl header is {} bytes, at most 80", option.header.len());
+                }
+                if let Some(attributes) = option.attributes.as_ref() {
+                    if attributes.len() != self.triangles.len() {
+                        bail!(
+                            "expect {} stl attributes, one per triangle, got {}",
+                            self.triangles.len(),
+                            attributes.len()
+                        );
+                    }
+                }
+                let mut header = [0u8; 80];
+                header[..option.header.len()].copy_from_slice(&option.header);
+                w.write_all(&header)?;
+                w.write_all(&(self.triangles.len() as u32).to_le_bytes())?;
+                for (i, (normal, vertices)) in facets.enumerate() {
+                    for v in [normal].iter().chain(vertices.iter()) {
+                        for x in v.to_array() {
+                            w.write_all(&x.to_le_bytes())?;
+                        }
+                    }
+                    let attribute = option.attributes.as_ref().map_or(0, |a| a[i]);
+                    w.write_all(&attribute.to_le_bytes())?;
+                }
+            }
+        }
+        w.flush()?;
+        Ok(())
+    }
+
+    /// triangle colors as stl attributes, the average of the vertex colors with 5 bits per
+    /// channel, blue in the lowest bits and bit 15 set like VisCAM and SolidView
+    pub fn stl_color_attributes(&self) -> Option<Vec<u16>> {
+        let colors = self.colors.as_ref()?;
+        Some(
+            self.triangles
+                .iter()
+                .map(|t| {
+                    let color: Vec4 = (colors[t.0] + colors[t.1] + colors[t.2]) / 3.;
+                    let [r, g, b] =
+                        [color.x, color.y, color.z].map(|x| (x.clamp(0., 1.) * 31.).round() as u16);
+                    0x8000 | r << 10 | g << 5 | b
+                })
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+
+use anyhow::bail;
+use glam::{Vec2, Vec3};
+
+use super::{IndexMesh, IndexTriangle};
+
+/// cells along u and v of parametric surfaces without a resolution
+const DEFAULT_RESOLUTION: [usize; 2] = [32, 32];
+
+/// how [`IndexMesh::from_parametric`] and [`IndexMesh::from_heightfield`] sample a surface
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SurfaceOption {
+    /// cells along u and v, at least 1 each or 3 when wrapped. `None` is the grid of a heightfield and 32 by 32 for
+    /// parametric surfaces
+    pub resolution: Option<[usize; 2]>,
+    /// join the end of u to its start, for surfaces where `f(0, v)` and `f(1, v)` meet
+    pub wrap_u: bool,
+    /// join the end of v to its start, for surfaces where `f(u, 0)` and `f(u, 1)` meet
+    pub wrap_v: bool,
+    /// close the surface with a copy flattened to this z and walls along the open edges, unless it
+    /// wraps both ways. the surface should face +z and stay above the base
+    pub base: Option<f32>,
+}
+
+impl IndexMesh {
+    /// mesh the surface `f(u, v)` with u and v in [0, 1]. the triangles face the direction of
+    /// df/du x df/dv
+    pub fn from_parametric<F: Fn(f32, f32) -> Vec3>(f: F, option: &SurfaceOption) -> Self {
+        let [nu, nv] = option.resolution.unwrap_or(DEFAULT_RESOLUTION);
+        let nu = nu.max(if option.wrap_u { 3 } else { 1 });
+        let nv = nv.max(if option.wrap_v { 3 } else { 1 });
+        // a wrapped direction does not repeat its first sample at the end
+        let (cu, cv) = (
+            if option.wrap_u { nu } else { nu + 1 },
+            if option.wrap_v { nv } else { nv + 1 },
+        );
+        let mut mesh = IndexMesh::new();
+        for j in 0..cv {
+            for i in 0..cu {
+                mesh.vertices
+                    .push(f(i as f32 / nu as f32, j as f32 / nv as f32));
+            }
+        }
+        let index = |i: usize, j: usize| (j % cv) * cu + i % cu;
+        for j in 0..nv {
+            for i in 0..nu {
+                let (a, b) = (index(i, j), index(i + 1, j));
+                let (c, d) = (index(i, j + 1), index(i + 1, j + 1));
+                mesh.triangles.push(IndexTriangle(a, b, d));
+                mesh.triangles.push(IndexTriangle(a, d, c));
+            }
+        }
+        if let Some(base) = option.base {
+            mesh.close_with_base(base);
+        }
+        mesh
+    }
+
+    /// mesh a grid of heights in rows of `columns` values, row after row along y. the grid spans
+    /// `size` centred at the origin and is sampled bilinearly at other resolutions
+    pub fn from_heightfield(
+        heights: &[f32],
+        columns: usize,
+        size: Vec2,
+        option: &SurfaceOption,
+    ) -> anyhow::Result<Self> {
+        if columns < 2 || !heights.len().is_multiple_of(columns) || heights.len() / columns < 2 {
+            bail!(
+                "expect a heightfield of at least 2 by 2 values in rows of {}, got {} values",
+                columns,
+                heights.len()
+            );
+        }
+        let rows = heights.len() / columns;
+        let height = |u: f32, v: f32| {
+            let (x, y) = (u * (columns - 1) as f32, v * (rows - 1) as f32);
+            let (i, j) = (
+                (x.floor() as usize).min(columns - 2),
+                (y.floor() as usize).min(rows - 2),
+            );
+            let (s, t) = (x - i as f32, y - j as f32);
+            let at = |i: usize, j: usize| heights[j * columns + i];
+            let bottom = at(i, j) * (1. - s) + at(i + 1, j) * s;
+            let top = at(i, j + 1) * (1. - s) + at(i + 1, j + 1) * s;
+            bottom * (1. - t) + top * t
+        };
+        let option = SurfaceOption {
+            resolution: Some(option.resolution.unwrap_or([columns - 1, rows - 1])),
+            ..*option
+        };
+        Ok(Self::from_parametric(
+            |u, v| ((Vec2::new(u, v) - 0.5) * size).extend(height(u, v)),
+            &option,
+        ))
+    }
+
+    /// add a copy of the mesh flattened to z = `base` facing the other way, and walls joining
+    /// the open edges of both
+    fn close_with_base(&mut self, base: f32) {
+        let n = self.vertices.len();
+        let edges = self
+            .triangles
+            .iter()
+            .flat_map(|t| [(t.0, t.1), (t.1, t.2), (t.2, t.0)])
+            .collect::<HashSet<_>>();
+        let mut walls = vec![];
+        for &(a, b) in edges.iter() {
+            if !edges.contains(&(b, a)) {
+                walls.push(IndexTriangle(b, a, a + n));
+                walls.push(IndexTriangle(b, a + n, b + n));
+            }
+        }
+        // a closed surface needs no base
+        if walls.is_empty() {
+            return;
+        }
+        // a stable order no matter how the set iterates
+        walls.sort_by_key(|t| (t.0, t.1, t.2));
+        self.vertices.extend_from_within(..n);
+        for v in self.vertices[n..].iter_mut() {
+            v.z = base;
+        }
+        let bottom = self
+            .triangles
+            .iter()
+            .map(|t| IndexTriangle(t.0 + n, t.2 + n, t.1 + n))
+            .collect::<Vec<_>>();
+        self.triangles.extend(bottom);
+        self.triangles.extend(walls);
+    }
+}
@@ -0,0 +1,510 @@
+use std::io::{Read, Seek, Write};
+
+use anyhow::{anyhow, bail, Context};
+use glam::{Mat4, Vec3};
+use quick_xml::{
+    escape::escape,
+    events::{BytesStart, Event},
+    Reader,
+};
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use super::{IndexMesh, IndexTriangle};
+
+const CORE_NAMESPACE: &str = "http://schemas.microsoft.com/3dmanufacturing/core/2015/02";
+const MODEL_RELATIONSHIP: &str = "http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel";
+const MODEL_PATH: &str = "3D/3dmodel.model";
+
+/// unit of the coordinates in a 3mf model
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ThreeMfUnit {
+    /// 0.000001 meter
+    Micron,
+    /// 0.001 meter
+    #[default]
+    Millimeter,
+    /// 0.01 meter
+    Centimeter,
+    /// 0.0254 meter
+    Inch,
+    /// 0.3048 meter
+    Foot,
+    /// 1 meter
+    Meter,
+}
+
+impl ThreeMfUnit {
+    const ALL: [ThreeMfUnit; 6] = [
+        ThreeMfUnit::Micron,
+        ThreeMfUnit::Millimeter,
+        ThreeMfUnit::Centimeter,
+        ThreeMfUnit::Inch,
+        ThreeMfUnit::Foot,
+        ThreeMfUnit::Meter,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            ThreeMfUnit::Micron => "micron",
+            ThreeMfUnit::Millimeter => "millimeter",
+            ThreeMfUnit::Centimeter => "centimeter",
+            ThreeMfUnit::Inch => "inch",
+            ThreeMfUnit::Foot => "foot",
+            ThreeMfUnit::Meter => "meter",
+        }
+    }
+
+    /// length of one unit in meters
+    pub fn meters(self) -> f32 {
+        match self {
+            ThreeMfUnit::Micron => 1e-6,
+            ThreeMfUnit::Millimeter => 1e-3,
+            ThreeMfUnit::Centimeter => 1e-2,
+            ThreeMfUnit::Inch => 0.0254,
+            ThreeMfUnit::Foot => 0.3048,
+            ThreeMfUnit::Meter => 1.,
+        }
+    }
+}
+
+/// an object placed with a transform, by the build or by another object
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ThreeMfItem {
+    /// id of the placed object
+    pub object: usize,
+    /// object to parent transform
+    pub transform: Mat4,
+}
+
+/// a 3mf object, a mesh and components that place other objects
+#[derive(Clone, Debug)]
+pub struct ThreeMfObject {
+    /// id referred to by items and components
+    pub id: usize,
+    /// name of the object
+    pub name: Option<String>,
+    /// triangles of the object, empty for objects made of components only
+    pub mesh: IndexMesh,
+    /// other objects that are part of this one
+    pub components: Vec<ThreeMfItem>,
+}
+
+/// the model of a 3mf package
+#[derive(Clone, Debug, Default)]
+pub struct ThreeMfModel {
+    /// unit of all coordinates
+    pub unit: ThreeMfUnit,
+    /// model metadata like `Title` or `Designer`, as (name, value)
+    pub metadata: Vec<(String, String)>,
+    /// objects in the resources
+    pub objects: Vec<ThreeMfObject>,
+    /// objects to print
+    pub items: Vec<ThreeMfItem>,
+}
+
+/// 3mf transforms are 4x3 matrices for row vectors, their rows are glam columns
+fn parse_transform(value: &str) -> anyhow::Result<Mat4> {
+    let values = value
+        .split_whitespace()
+        .map(|v| v.parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("invalid 3mf transform {}", value))?;
+    if values.len() != 12 {
+        bail!("3mf transform needs 12 values, got {}", values.len());
+    }
+    let column = |i: usize| Vec3::from_slice(&values[i * 3..]);
+    Ok(Mat4::from_cols(
+        column(0).extend(0.),
+        column(1).extend(0.),
+        column(2).extend(0.),
+        column(3).extend(1.),
+    ))
+}
+
+fn write_transform(transform: &Mat4) -> String {
+    let cols = transform.to_cols_array_2d();
+    cols.iter()
+        .flat_map(|c| c[..3].iter())
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn attribute(e: &BytesStart, name: &str) -> anyhow::Result<Option<String>> {
+    for attr in e.attributes() {
+        let attr = attr?;
+        if attr.key.local_name().as_ref() == name.as_bytes() {
+            return Ok(Some(attr.unescape_value()?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+fn required<T: std::str::FromStr>(e: &BytesStart, name: &str) -> anyhow::Result<T> {
+    let value = attribute(e, name)?.ok_or_else(|| {
+        anyhow!(
+            "3mf {} without {}",
+            String::from_utf8_lossy(e.local_name().as_ref()),
+            name
+        )
+    })?;
+    value
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("invalid 3mf {} {}", name, value))
+}
+
+fn item(e: &BytesStart) -> anyhow::Result<ThreeMfItem> {
+    Ok(ThreeMfItem {
+        object: required(e, "objectid")?,
+        transform: match attribute(e, "transform")? {
+            Some(value) => parse_transform(&value)?,
+            None => Mat4::IDENTITY,
+        },
+    })
+}
+
+/// path of the model part in the package relationships
+fn model_path(relationships: &str) -> anyhow::Result<Option<String>> {
+    let mut reader = Reader::from_str(relationships);
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e)
+                if e.local_name().as_ref() == b"Relationship"
+                    && attribute(&e, "Type")?.as_deref() == Some(MODEL_RELATIONSHIP) =>
+            {
+                let target = required::<String>(&e, "Target")?;
+                return Ok(Some(target.trim_start_matches('/').to_string()));
+            }
+            Event::Eof => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+impl ThreeMfModel {
+    /// read the model of a 3mf package. materials, colors and extensions are ignored
+    pub fn read<F: Read + Seek>(f: &mut F) -> anyhow::Result<Self> {
+        let mut archive = ZipArchive::new(f).context("3mf package is not a zip archive")?;
+        let mut text = String::new();
+        let path = match archive.by_name("_rels/.rels") {
+            Ok(mut rels) => {
+                rels.read_to_string(&mut text)?;
+                model_path(&text)?
+            }
+            Err(_) => None,
+        };
+        let path = path.unwrap_or_else(|| MODEL_PATH.to_string());
+        text.clear();
+        archive
+            .by_name(&path)
+            .with_context(|| format!("3mf package without model {}", path))?
+            .read_to_string(&mut text)?;
+        Self::parse(&text)
+    }
+
+    fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut model = ThreeMfModel::default();
+        let mut reader = Reader::from_str(text);
+        reader.trim_text(true);
+        let mut metadata = None;
+        let mut object: Option<ThreeMfObject> = None;
+        loop {
+            let (e, empty) = match reader.read_event()? {
+                Event::Start(e) => (e, false),
+                Event::Empty(e) => (e, true),
+                Event::Text(t) => {
+                    if let Some((_, value)) = metadata.as_mut() {
+                        *value = t.unescape()?.into_owned();
+                    }
+                    continue;
+                }
+                Event::End(e) => {
+                    match e.local_name().as_ref() {
+                        b"metadata" => model.metadata.extend(metadata.take()),
+                        b"object" => model.objects.extend(object.take()),
+                        _ => {}
+                    }
+                    continue;
+                }
+                Event::Eof => break,
+                _ => continue,
+            };
+            match e.local_name().as_ref() {
+                b"model" => {
+                    if let Some(unit) = attribute(&e, "unit")? {
+                        model.unit = *ThreeMfUnit::ALL
+                            .iter()
+                            .find(|u| u.name() == unit)
+                            .ok_or_else(|| anyhow!("unknown 3mf unit {}", unit))?;
+                    }
+                }
+                b"metadata" if object.is_none() => {
+                    let entry = (required(&e, "name")?, String::new());
+                    if empty {
+                        model.metadata.push(entry);
+                    } else {
+                        metadata = Some(entry);
+                    }
+                }
+                b"object" => {
+                    let new = ThreeMfObject {
+                        id: required(&e, "id")?,
+                        name: attribute(&e, "name")?,
+                        mesh: IndexMesh::new(),
+                        components: vec![],
+                    };
+                    if empty {
+                        model.objects.push(new);
+                    } else {
+                        object = Some(new);
+                    }
+                }
+                b"vertex" => {
+                    let object = object
+                        .as_mut()
+                        .ok_or_else(|| anyhow!("3mf vertex outside an object"))?;
+                    object.mesh.vertices.push(Vec3::new(
+                        required(&e, "x")?,
+                        required(&e, "y")?,
+                        required(&e, "z")?,
+                    ));
+                }
+                b"triangle" => {
+                    let object = object
+                        .as_mut()
+                        .ok_or_else(|| anyhow!("3mf triangle outside an object"))?;
+                    object.mesh.triangles.push(IndexTriangle(
+                        required(&e, "v1")?,
+                        required(&e, "v2")?,
+                        required(&e, "v3")?,
+                    ));
+                }
+                b"component" => {
+                    let object = object
+                        .as_mut()
+                        .ok_or_else(|| anyhow!("3mf component outside an object"))?;
+                    object.components.push(item(&e)?);
+                }
+                b"item" => model.items.push(item(&e)?),
+                _ => {}
+            }
+        }
+        model.validate()?;
+        Ok(model)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        for (i, object) in self.objects.iter().enumerate() {
+            if self.objects[..i].iter().any(|o| o.id == object.id) {
+                bail!("duplicate 3mf object id {}", object.id);
+            }
+            let count = object.mesh.vertices.len();
+            if let Some(t) = object
+                .mesh
+                .triangles
+                .iter()
+                .find(|t| t.0 >= count || t.1 >= count || t.2 >= count)
+            {
+                bail!(
+                    "3mf object {} triangle {:?} refers to a missing vertex",
+                    object.id,
+                    t
+                );
+            }
+        }
+        for item in self
+            .items
+            .iter()
+            .chain(self.objects.iter().flat_map(|o| o.components.iter()))
+        {
+            self.object(item.object)?;
+        }
+        Ok(())
+    }
+
+    /// the object with `id`
+    pub fn object(&self, id: usize) -> anyhow::Result<&ThreeMfObject> {
+        self.objects
+            .iter()
+            .find(|o| o.id == id)
+            .ok_or_else(|| anyhow!("missing 3mf object {}", id))
+    }
+
+    /// every build item with its components resolved, in build coordinates
+    pub fn to_mesh(&self) -> anyhow::Result<IndexMesh> {
+        let mut result = IndexMesh::new();
+        let mut stack = self
+            .items
+            .iter()
+            .rev()
+            .map(|item| (*item, 0))
+            .collect::<Vec<_>>();
+        while let Some((item, depth)) = stack.pop() {
+            // components can not refer to their ancestors, so a longer path has a cycle
+            if depth > self.objects.len() {
+                bail!("3mf object {} refers to itself", item.object);
+            }
+            let object = self.object(item.object)?;
+            let mut mesh = IndexMesh {
+                vertices: object.mesh.vertices.clone(),
+                triangles: object.mesh.triangles.clone(),
+                ..IndexMesh::new()
+            };
+            if item.transform != Mat4::IDENTITY {
+                mesh.transfrom(item.transform);
+            }
+            // a mirroring transform turns the triangles inside out
+            if item.transform.determinant() < 0. {
+                mesh.triangles
+                    .iter_mut()
+                    .for_each(|t| std::mem::swap(&mut t.1, &mut t.2));
+            }
+            result.merge(mesh);
+            for component in object.components.iter().rev() {
+                let transform = item.transform * component.transform;
+                stack.push((
+                    ThreeMfItem {
+                        object: component.object,
+                        transform,
+                    },
+                    depth + 1,
+                ));
+            }
+        }
+        Ok(result)
+    }
+
+    /// write a 3mf package with a single model part
+    pub fn write<F: Write + Seek>(&self, f: &mut F) -> anyhow::Result<()> {
+        self.validate()?;
+        let mut zip = ZipWriter::new(f);
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        zip.start_file("[Content_Types].xml", options)?;
+        write!(
+            zip,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+ <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+ <Default Extension="model" ContentType="application/vnd.ms-package.3dmanufacturing-3dmodel+xml"/>
+</Types>
+"#
+        )?;
+        zip.start_file("_rels/.rels", options)?;
+        write!(
+            zip,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+ <Relationship Target="/{}" Id="rel0" Type="{}"/>
+</Relationships>
+"#,
+            MODEL_PATH, MODEL_RELATIONSHIP
+        )?;
+        zip.start_file(MODEL_PATH, options)?;
+        self.write_model(&mut zip)?;
+        zip.finish()?;
+        Ok(())
+    }
+
+    fn write_model<W: Write>(&self, w: &mut W) -> anyhow::Result<()> {
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            w,
+            r#"<model unit="{}" xml:lang="en-US" xmlns="{}">"#,
+            self.unit.name(),
+            CORE_NAMESPACE
+        )?;
+        for (name, value) in self.metadata.iter() {
+            writeln!(
+                w,
+                r#" <metadata name="{}">{}</metadata>"#,
+                escape(name),
+                escape(value)
+            )?;
+        }
+        writeln!(w, " <resources>")?;
+        for object in self.objects.iter() {
+            write!(w, r#"  <object id="{}" type="model""#, object.id)?;
+            if let Some(name) = object.name.as_ref() {
+                write!(w, r#" name="{}""#, escape(name))?;
+            }
+            writeln!(w, ">")?;
+            if !object.mesh.triangles.is_empty() || object.components.is_empty() {
+                writeln!(w, "   <mesh>")?;
+                writeln!(w, "    <vertices>")?;
+                for v in object.mesh.vertices.iter() {
+                    writeln!(w, r#"     <vertex x="{}" y="{}" z="{}"/>"#, v.x, v.y, v.z)?;
+                }
+                writeln!(w, "    </vertices>")?;
+                writeln!(w, "    <triangles>")?;
+                for t in object.mesh.triangles.iter() {
+                    writeln!(
+                        w,
+                        r#"     <triangle v1="{}" v2="{}" v3="{}"/>"#,
+                        t.0, t.1, t.2
+                    )?;
+                }
+                writeln!(w, "    </triangles>")?;
+                writeln!(w, "   </mesh>")?;
+            }
+            if !object.components.is_empty() {
+                writeln!(w, "   <components>")?;
+                for component in object.components.iter() {
+                    writeln!(w, "    <component {}/>", item_attributes(component))?;
+                }
+                writeln!(w, "   </components>")?;
+            }
+            writeln!(w, "  </object>")?;
+        }
+        writeln!(w, " </resources>")?;
+        writeln!(w, " <build>")?;
+        for item in self.items.iter() {
+            writeln!(w, "  <item {}/>", item_attributes(item))?;
+        }
+        writeln!(w, " </build>")?;
+        writeln!(w, "</model>")?;
+        Ok(())
+    }
+}
+
+fn item_attributes(item: &ThreeMfItem) -> String {
+    if item.transform == Mat4::IDENTITY {
+        format!(r#"objectid="{}""#, item.object)
+    } else {
+        format!(
+            r#"objectid="{}" transform="{}""#,
+            item.object,
+            write_transform(&item.transform)
+        )
+    }
+}
+
+impl IndexMesh {
+    /// read a 3mf package, every build item is placed with its transform and merged.
+    /// coordinates stay in the model unit, see [`ThreeMfModel`] for units, objects and items
+    pub fn from_3mf<F: Read + Seek>(f: &mut F) -> anyhow::Result<Self> {
+        ThreeMfModel::read(f)?.to_mesh()
+    }
+
+    /// write a 3mf package with the mesh as a single object in millimeters
+    pub fn to_3mf<F: Write + Seek>(&self, f: &mut F) -> anyhow::Result<()> {
+        ThreeMfModel {
+            objects: vec![ThreeMfObject {
+                id: 1,
+                name: None,
+                mesh: IndexMesh {
+                    vertices: self.vertices.clone(),
+                    triangles: self.triangles.clone(),
+                    ..IndexMesh::new()
+                },
+                components: vec![],
+            }],
+            items: vec![ThreeMfItem {
+                object: 1,
+                transform: Mat4::IDENTITY,
+            }],
+            ..ThreeMfModel::default()
+        }
+        .write(f)
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use glam::Vec3;
+
+use super::{IndexMesh, IndexTriangle, TriangleGroup};
+
+/// what [`IndexMesh::weld_vertices`] changed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WeldReport {
+    /// vertices merged into another vertex and removed
+    pub merged_vertices: usize,
+    /// triangles removed because two of their corners were merged
+    pub removed_triangles: usize,
+}
+
+/// cell of a grid with `size` spacing, exact positions when `size` is zero
+fn cell(p: Vec3, size: f32) -> [i64; 3] {
+    if size > 0. {
+        (p / size).floor().to_array().map(|x| x as i64)
+    } else {
+        // adding zero turns -0 into 0
+        p.to_array().map(|x| (x + 0.).to_bits() as i64)
+    }
+}
+
+impl IndexMesh {
+    /// merge vertices closer than `tolerance` into the first of them, using a spatial hash.
+    /// merged vertices keep the normal, color, texture coordinate and attributes of the first one,
+    /// triangles that lose a corner are removed
+    pub fn weld_vertices(&mut self, tolerance: f32) -> WeldReport {
+        let tolerance = tolerance.max(0.);
+        let mut grid: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+        // new index of every vertex, and the old index of every kept vertex
+        let mut remap = Vec::with_capacity(self.vertices.len());
+        let mut kept: Vec<usize> = vec![];
+        for (i, &p) in self.vertices.iter().enumerate() {
+            let [x, y, z] = cell(p, tolerance);
+            let range = if tolerance > 0. { -1..=1 } else { 0..=0 };
+            let mut found = None;
+            'search: for dx in range.clone() {
+                for dy in range.clone() {
+                    for dz in range.clone() {
+                        // cells saturate when the tolerance is tiny next to the coordinates
+                        let neighbour = [
+                            x.saturating_add(dx),
+                            y.saturating_add(dy),
+                            z.saturating_add(dz),
+                        ];
+                        let Some(candidates) = grid.get(&neighbour) else {
+                            continue;
+                        };
+                        if let Some(&j) = candidates
+                            .iter()
+                            .find(|&&j| self.vertices[kept[j]].distance(p) <= tolerance)
+                        {
+                            found = Some(j);
+                            break 'search;
+                        }
+                    }
+                }
+            }
+            remap.push(found.unwrap_or_else(|| {
+                grid.entry([x, y, z]).or_default().push(kept.len());
+                kept.push(i);
+                kept.len() - 1
+            }));
+        }
+
+        let merged_vertices = self.vertices.len() - kept.len();
+        if merged_vertices == 0 {
+            return WeldReport::default();
+        }
+        fn select<T: Copy>(values: &[T], kept: &[usize]) -> Vec<T> {
+            kept.iter().map(|&i| values[i]).collect()
+        }
+        self.vertices = select(&self.vertices, &kept);
+        if let Some(normals) = self.normals.as_mut() {
+            *normals = select(normals, &kept);
+        }
+        if let Some(colors) = self.colors.as_mut() {
+            *colors = select(colors, &kept);
+        }
+        if let Some(uvs) = self.uvs.as_mut() {
+            *uvs = select(uvs, &kept);
+        }
+        for (_, values) in self.attributes.iter_mut() {
+            *values = select(values, &kept);
+        }
+
+        // number of triangles kept before each triangle, to move the group ranges
+        let mut before = Vec::with_capacity(self.triangles.len() + 1);
+        let mut triangles = Vec::with_capacity(self.triangles.len());
+        for t in self.triangles.iter() {
+            before.push(triangles.len());
+            let t = IndexTriangle(remap[t.0], remap[t.1], remap[t.2]);
+            if t.0 != t.1 && t.1 != t.2 && t.2 != t.0 {
+                triangles.push(t);
+            }
+        }
+        before.push(triangles.len());
+        let removed_triangles = self.triangles.len() - triangles.len();
+        self.triangles = triangles;
+        self.groups = self
+            .groups
+            .drain(..)
+            .map(|g| TriangleGroup {
+                triangles: before[g.triangles.start]..before[g.triangles.end],
+                ..g
+            })
+            .filter(|g| !g.triangles.is_empty())
+            .collect();
+
+        WeldReport {
+            merged_vertices,
+            removed_triangles,
+        }
+    }
+
+    /// read an stl file and weld its vertices within `tolerance`, see [`IndexMesh::weld_vertices`]
+    pub fn from_stl_welded<F: std::io::Read + std::io::Seek>(
+        f: &mut F,
+        tolerance: f32,
+    ) -> anyhow::Result<(Self, WeldReport)> {
+        let mut mesh = Self::from_stl(f)?;
+        let report = mesh.weld_vertices(tolerance);
+        Ok((mesh, report))
+    }
+}
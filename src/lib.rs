@@ -4,13 +4,30 @@ mod bv;
 mod bvh;
 mod half_edge;
 mod index_mesh;
+mod polygon;
 mod primitives;
 mod traits;
 mod tree;
 mod csg;
 mod mesh;
 mod algorithms;
+mod query;
+#[cfg(test)]
+mod test_utils;
 
-pub use index_mesh::{IndexMesh, RenderableMesh};
-pub use primitives::Box;
-pub use bv::AABB;
\ No newline at end of file
+pub use index_mesh::{
+    FormatRegistry, GltfNode, IndexMesh, Material, MeshBvh, MeshFormat, PlyFormat, ReadSeek,
+    RenderableMesh, StlFormat, StlOption, SurfaceOption, ThreeMfItem, ThreeMfModel, ThreeMfObject,
+    ThreeMfUnit, TriangleGroup, WeldReport, WriteSeek,
+};
+pub use polygon::{Polygon, TriangulationMode};
+pub use primitives::{
+    Box, Capsule, Cone, Cylinder, Extrusion, Icosphere, Plane, Revolution, RoundedBox, Sphere,
+    Torus,
+};
+pub use bv::AABB;
+pub use bvh::build_options::{BuildBvhOption, DepthControl, SahOption, SplitMethod};
+pub use bvh::{Bvh, BvhNode};
+pub use csg::{CsgOperation, CsgSolid};
+pub use half_edge::{HalfEdgeMesh, SelfIntersection};
+pub use query::{ClosedMesh, ClosestPoint, Ray, RayHit};
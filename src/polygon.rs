@@ -0,0 +1,107 @@
+use anyhow::{anyhow, bail};
+use glam::Vec2;
+
+use crate::algorithms::{ear_clip_with_holes, triangulate_with_holes};
+
+#[cfg(test)]
+mod polygon_tests;
+
+/// how [`Polygon::triangulate`] splits a polygon
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TriangulationMode {
+    /// cut off ears one by one, holes are joined to the outline by bridges first
+    #[default]
+    EarClipping,
+    /// constrained delaunay triangulation, slower but avoids thin triangles where it can
+    ConstrainedDelaunay,
+}
+
+/// simple polygon with holes in the plane
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Polygon {
+    /// outer boundary in either orientation, without self intersections
+    pub outline: Vec<Vec2>,
+    /// boundaries of the holes in either orientation, inside the outline and apart from each other
+    pub holes: Vec<Vec<Vec2>>,
+}
+
+impl Polygon {
+    /// polygon without holes
+    pub fn new(outline: Vec<Vec2>) -> Self {
+        Polygon {
+            outline,
+            holes: vec![],
+        }
+    }
+
+    /// area inside the outline and outside the holes
+    pub fn area(&self) -> f32 {
+        let area = |polygon: &[Vec2]| {
+            (0..polygon.len())
+                .map(|i| polygon[i].perp_dot(polygon[(i + 1) % polygon.len()]))
+                .sum::<f32>()
+                .abs()
+                / 2.
+        };
+        area(&self.outline) - self.holes.iter().map(|h| area(h)).sum::<f32>()
+    }
+
+    /// split the polygon into counter clockwise triangles indexing the outline followed by the
+    /// holes. self intersecting polygons give overlapping triangles or an error
+    pub fn triangulate(&self, mode: TriangulationMode) -> anyhow::Result<Vec<[usize; 3]>> {
+        if self.outline.len() < 3 {
+            bail!(
+                "polygon outline has {} points, at least 3",
+                self.outline.len()
+            );
+        }
+        if let Some(i) = self.holes.iter().position(|h| h.len() < 3) {
+            bail!(
+                "polygon hole {} has {} points, at least 3",
+                i,
+                self.holes[i].len()
+            );
+        }
+        let loops = std::iter::once(&self.outline).chain(self.holes.iter());
+        if loops.flatten().any(|p| !p.is_finite()) {
+            bail!("polygon has a point that is not finite");
+        }
+        if let Some(i) = self
+            .holes
+            .iter()
+            .position(|h| !h.iter().all(|&p| contains(&self.outline, p)))
+        {
+            bail!("polygon hole {} is not inside the outline", i);
+        }
+        if self.area() <= 0. {
+            bail!("polygon has no area");
+        }
+        let outline = self
+            .outline
+            .iter()
+            .map(|p| p.as_dvec2())
+            .collect::<Vec<_>>();
+        let holes = self
+            .holes
+            .iter()
+            .map(|h| h.iter().map(|p| p.as_dvec2()).collect())
+            .collect::<Vec<_>>();
+        match mode {
+            TriangulationMode::EarClipping => Ok(ear_clip_with_holes(&outline, &holes)),
+            TriangulationMode::ConstrainedDelaunay => triangulate_with_holes(&outline, &holes)
+                .ok_or_else(|| anyhow!("polygon edges cross each other")),
+        }
+    }
+}
+
+/// whether a point is inside a polygon by the even odd rule
+fn contains(polygon: &[Vec2], p: Vec2) -> bool {
+    let mut inside = false;
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+    inside
+}
@@ -0,0 +1,170 @@
+use glam::Vec2;
+
+use super::{Polygon, TriangulationMode};
+
+const MODES: [TriangulationMode; 2] = [
+    TriangulationMode::EarClipping,
+    TriangulationMode::ConstrainedDelaunay,
+];
+
+fn points(polygon: &Polygon) -> Vec<Vec2> {
+    let mut points = polygon.outline.clone();
+    points.extend(polygon.holes.concat());
+    points
+}
+
+fn rectangle(min: Vec2, max: Vec2) -> Vec<Vec2> {
+    vec![min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)]
+}
+
+/// counter clockwise triangles covering exactly the area of the polygon
+fn assert_covers(polygon: &Polygon, triangles: &[[usize; 3]]) {
+    let points = points(polygon);
+    let mut area = 0.;
+    for t in triangles {
+        let [a, b, c] = t.map(|i| points[i]);
+        let twice = (b - a).perp_dot(c - a);
+        assert!(twice > 0., "{:?} is not counter clockwise", t);
+        area += twice / 2.;
+    }
+    assert!((area - polygon.area()).abs() < 1e-4);
+}
+
+fn min_angle(points: &[Vec2], triangles: &[[usize; 3]]) -> f32 {
+    triangles
+        .iter()
+        .flat_map(|t| {
+            (0..3).map(move |k| {
+                let [a, b, c] = [t[k], t[(k + 1) % 3], t[(k + 2) % 3]].map(|i| points[i]);
+                (b - a).angle_between(c - a)
+            })
+        })
+        .fold(f32::MAX, f32::min)
+}
+
+#[test]
+fn test_triangulate_concave() {
+    // a comb with three teeth, clockwise
+    let mut outline = [
+        [0., 0.],
+        [5., 0.],
+        [5., 3.],
+        [4., 3.],
+        [4., 1.],
+        [3., 1.],
+        [3., 3.],
+        [2., 3.],
+        [2., 1.],
+        [1., 1.],
+        [1., 3.],
+        [0., 3.],
+    ]
+    .map(Vec2::from)
+    .to_vec();
+    outline.reverse();
+    let polygon = Polygon::new(outline);
+    assert_eq!(polygon.area(), 11.);
+    for mode in MODES {
+        let triangles = polygon.triangulate(mode).unwrap();
+        assert_eq!(triangles.len(), 10);
+        assert_covers(&polygon, &triangles);
+    }
+}
+
+#[test]
+fn test_triangulate_holes() {
+    let polygon = Polygon {
+        outline: rectangle(Vec2::ZERO, Vec2::new(10., 4.)),
+        holes: vec![
+            rectangle(Vec2::new(1., 1.), Vec2::new(3., 3.))
+                .into_iter()
+                .rev()
+                .collect(),
+            // counter clockwise holes work as well
+            rectangle(Vec2::new(4., 1.), Vec2::new(6., 3.)),
+            // a hole on the same height as the one to its left
+            vec![Vec2::new(7., 2.), Vec2::new(9., 1.), Vec2::new(9., 3.)],
+        ],
+    };
+    assert_eq!(polygon.area(), 40. - 4. - 4. - 2.);
+    for mode in MODES {
+        let triangles = polygon.triangulate(mode).unwrap();
+        // n + 2h - 2 triangles for n vertices and h holes
+        assert_eq!(triangles.len(), 15 + 2 * 3 - 2);
+        assert_covers(&polygon, &triangles);
+    }
+
+    // a hole whose rightmost vertex sees a reflex vertex of the outline first
+    let polygon = Polygon {
+        outline: [[0., 0.], [6., 0.], [6., 6.], [4., 1.5], [3., 6.], [0., 6.]]
+            .map(Vec2::from)
+            .to_vec(),
+        holes: vec![rectangle(Vec2::new(1., 1.), Vec2::new(2., 2.))],
+    };
+    for mode in MODES {
+        let triangles = polygon.triangulate(mode).unwrap();
+        assert_eq!(triangles.len(), 10 + 2 - 2);
+        assert_covers(&polygon, &triangles);
+    }
+}
+
+#[test]
+fn test_triangulate_quality() {
+    // points on an ellipse, ear clipping fans them out from a corner
+    let outline = (0..64)
+        .map(|i| {
+            let angle = std::f32::consts::TAU * i as f32 / 64.;
+            Vec2::new(4. * angle.cos(), angle.sin())
+        })
+        .collect::<Vec<_>>();
+    let polygon = Polygon::new(outline);
+    let ear = polygon.triangulate(TriangulationMode::EarClipping).unwrap();
+    let delaunay = polygon
+        .triangulate(TriangulationMode::ConstrainedDelaunay)
+        .unwrap();
+    assert_covers(&polygon, &ear);
+    assert_covers(&polygon, &delaunay);
+    assert!(min_angle(&polygon.outline, &delaunay) > min_angle(&polygon.outline, &ear));
+}
+
+#[test]
+fn test_triangulate_invalid() {
+    let square = rectangle(Vec2::ZERO, Vec2::ONE);
+    for mode in MODES {
+        assert!(Polygon::new(square[..2].to_vec())
+            .triangulate(mode)
+            .is_err());
+        let polygon = Polygon {
+            outline: square.clone(),
+            holes: vec![vec![Vec2::ZERO, Vec2::ONE]],
+        };
+        assert!(polygon.triangulate(mode).is_err());
+        let collinear = [0., 1., 2.].map(Vec2::splat).to_vec();
+        assert!(Polygon::new(collinear).triangulate(mode).is_err());
+        let mut nan = square.clone();
+        nan[1].x = f32::NAN;
+        assert!(Polygon::new(nan).triangulate(mode).is_err());
+        // a hole beside the outline, and one crossing it
+        for min in [Vec2::new(2., 0.), Vec2::new(0.75, 0.25)] {
+            let polygon = Polygon {
+                outline: square.clone(),
+                holes: vec![rectangle(min, min + Vec2::splat(0.5))],
+            };
+            assert!(polygon.triangulate(mode).is_err());
+        }
+    }
+}
+
+#[test]
+fn test_triangulate_crossing() {
+    let bowtie = [[0., 0.], [3., 2.], [3., 0.], [0., 1.]]
+        .map(Vec2::from)
+        .to_vec();
+    let delaunay = TriangulationMode::ConstrainedDelaunay;
+    assert!(Polygon::new(bowtie.clone()).triangulate(delaunay).is_err());
+    let polygon = Polygon {
+        outline: rectangle(Vec2::splat(-1.), Vec2::new(4., 3.)),
+        holes: vec![bowtie],
+    };
+    assert!(polygon.triangulate(delaunay).is_err());
+}
@@ -1,6 +1,16 @@
-use glam::Vec3;
+use std::collections::HashMap;
 
-use crate::{IndexMesh, index_mesh::IndexTriangle};
+use anyhow::bail;
+use glam::{Vec2, Vec3};
+
+use crate::{
+    index_mesh::IndexTriangle,
+    polygon::{Polygon, TriangulationMode},
+    IndexMesh,
+};
+
+#[cfg(test)]
+mod primitives_tests;
 
 pub struct Box {
     pub size: f32
@@ -38,14 +48,760 @@ impl Box {
     }
 }
 
-// #[derive(Debug, PartialEq)]
-// struct Cylinder {
-//     height: f64,
-//     radius: f64,
-// }
+/// error unless a size is finite and greater than 0
+fn check_positive(value: f32, what: &str) -> anyhow::Result<()> {
+    if !(value.is_finite() && value > 0.) {
+        bail!("{} must be positive, got {}", what, value);
+    }
+    Ok(())
+}
+
+/// error unless a count of segments or rings is at least `min`
+fn check_count(count: usize, min: usize, what: &str) -> anyhow::Result<()> {
+    if count < min {
+        bail!("{} must be at least {}, got {}", what, min, count);
+    }
+    Ok(())
+}
+
+/// revolve a profile of (radius, z) points around the z axis by `angle`, starting at +x. points
+/// with a zero radius become a single vertex, the others one vertex per column in a row. the solid
+/// is on the right of the profile when looking at it with radius to the right and z up, so profiles
+/// going down the outside of a solid face outwards. sweeps short of a full turn are left open
+fn lathe(profile: &[(f32, f32)], segments: usize, closed: bool, angle: f32) -> IndexMesh {
+    let full = angle >= std::f32::consts::TAU;
+    let segments = segments.max(if full { 3 } else { 1 });
+    let columns = if full { segments } else { segments + 1 };
+    let mut mesh = IndexMesh::new();
+    // first vertex of every profile point, and whether it is a pole
+    let mut rings = Vec::with_capacity(profile.len());
+    for &(radius, z) in profile {
+        rings.push((mesh.vertices.len(), radius == 0.));
+        if radius == 0. {
+            mesh.vertices.push(Vec3::new(0., 0., z));
+        } else {
+            mesh.vertices.extend((0..columns).map(|j| {
+                let theta = angle.min(std::f32::consts::TAU) * j as f32 / segments as f32;
+                Vec3::new(radius * theta.cos(), radius * theta.sin(), z)
+            }));
+        }
+    }
+    let count = if closed { rings.len() } else { rings.len() - 1 };
+    for k in 0..count {
+        let (top, top_pole) = rings[k];
+        let (bottom, bottom_pole) = rings[(k + 1) % rings.len()];
+        for j in 0..segments {
+            let next = (j + 1) % columns;
+            match (top_pole, bottom_pole) {
+                (true, true) => {}
+                (true, false) => mesh
+                    .triangles
+                    .push(IndexTriangle(top, bottom + j, bottom + next)),
+                (false, true) => mesh
+                    .triangles
+                    .push(IndexTriangle(bottom, top + next, top + j)),
+                (false, false) => {
+                    let (a, b) = (top + j, top + next);
+                    let (c, d) = (bottom + j, bottom + next);
+                    mesh.triangles.push(IndexTriangle(a, c, d));
+                    mesh.triangles.push(IndexTriangle(a, d, b));
+                }
+            }
+        }
+    }
+    mesh
+}
+
+/// uv sphere centred at the origin, with a single vertex at each pole
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sphere {
+    /// distance from the centre to the surface
+    pub radius: f32,
+    /// vertices around the z axis, at least 3
+    pub segments: usize,
+    /// bands from pole to pole, at least 2
+    pub rings: usize,
+}
+
+impl Default for Sphere {
+    fn default() -> Self {
+        Sphere {
+            radius: 1.,
+            segments: 32,
+            rings: 16,
+        }
+    }
+}
+
+impl Sphere {
+    /// triangles facing outwards
+    pub fn to_mesh(&self) -> anyhow::Result<IndexMesh> {
+        check_positive(self.radius, "sphere radius")?;
+        check_count(self.segments, 3, "sphere segments")?;
+        check_count(self.rings, 2, "sphere rings")?;
+        let rings = self.rings;
+        let profile = (0..=rings)
+            .map(|k| {
+                let theta = std::f32::consts::PI * k as f32 / rings as f32;
+                let radius = if k == 0 || k == rings {
+                    0.
+                } else {
+                    self.radius * theta.sin()
+                };
+                (radius, self.radius * theta.cos())
+            })
+            .collect::<Vec<_>>();
+        Ok(lathe(&profile, self.segments, false, std::f32::consts::TAU))
+    }
+}
+
+/// capped cylinder along the z axis, centred at the origin
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cylinder {
+    /// radius of the caps
+    pub radius: f32,
+    /// distance between the caps
+    pub height: f32,
+    /// vertices around the z axis, at least 3
+    pub segments: usize,
+}
+
+impl Default for Cylinder {
+    fn default() -> Self {
+        Cylinder {
+            radius: 1.,
+            height: 1.,
+            segments: 32,
+        }
+    }
+}
+
+impl Cylinder {
+    /// triangles facing outwards
+    pub fn to_mesh(&self) -> anyhow::Result<IndexMesh> {
+        check_positive(self.radius, "cylinder radius")?;
+        check_positive(self.height, "cylinder height")?;
+        check_count(self.segments, 3, "cylinder segments")?;
+        let h = self.height / 2.;
+        let profile = [(0., h), (self.radius, h), (self.radius, -h), (0., -h)];
+        Ok(lathe(&profile, self.segments, false, std::f32::consts::TAU))
+    }
+}
+
+/// capped cone along the z axis, centred at the origin with the apex at the top
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cone {
+    /// radius of the base
+    pub radius: f32,
+    /// distance from the base to the apex
+    pub height: f32,
+    /// vertices around the z axis, at least 3
+    pub segments: usize,
+}
+
+impl Default for Cone {
+    fn default() -> Self {
+        Cone {
+            radius: 1.,
+            height: 1.,
+            segments: 32,
+        }
+    }
+}
+
+impl Cone {
+    /// triangles facing outwards
+    pub fn to_mesh(&self) -> anyhow::Result<IndexMesh> {
+        check_positive(self.radius, "cone radius")?;
+        check_positive(self.height, "cone height")?;
+        check_count(self.segments, 3, "cone segments")?;
+        let h = self.height / 2.;
+        let profile = [(0., h), (self.radius, -h), (0., -h)];
+        Ok(lathe(&profile, self.segments, false, std::f32::consts::TAU))
+    }
+}
+
+/// torus around the z axis, centred at the origin
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Torus {
+    /// distance from the centre to the centre of the tube
+    pub major_radius: f32,
+    /// radius of the tube, less than the major radius
+    pub minor_radius: f32,
+    /// vertices around the z axis, at least 3
+    pub major_segments: usize,
+    /// vertices around the tube, at least 3
+    pub minor_segments: usize,
+}
+
+impl Default for Torus {
+    fn default() -> Self {
+        Torus {
+            major_radius: 1.,
+            minor_radius: 0.25,
+            major_segments: 32,
+            minor_segments: 16,
+        }
+    }
+}
+
+impl Torus {
+    /// triangles facing outwards
+    pub fn to_mesh(&self) -> anyhow::Result<IndexMesh> {
+        check_positive(self.minor_radius, "torus minor radius")?;
+        if !(self.major_radius.is_finite() && self.major_radius > self.minor_radius) {
+            bail!(
+                "torus major radius {} must be greater than the minor radius {}",
+                self.major_radius,
+                self.minor_radius
+            );
+        }
+        check_count(self.major_segments, 3, "torus major segments")?;
+        check_count(self.minor_segments, 3, "torus minor segments")?;
+        let segments = self.minor_segments;
+        // around the tube downwards on the outside
+        let profile = (0..segments)
+            .map(|k| {
+                let angle = -std::f32::consts::TAU * k as f32 / segments as f32;
+                (
+                    self.major_radius + self.minor_radius * angle.cos(),
+                    self.minor_radius * angle.sin(),
+                )
+            })
+            .collect::<Vec<_>>();
+        Ok(lathe(
+            &profile,
+            self.major_segments,
+            true,
+            std::f32::consts::TAU,
+        ))
+    }
+}
+
+/// flat grid in the xy plane facing +z, centred at the origin. it is open, unlike the solids
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plane {
+    /// size along x
+    pub width: f32,
+    /// size along y
+    pub depth: f32,
+    /// cells along x, at least 1
+    pub width_segments: usize,
+    /// cells along y, at least 1
+    pub depth_segments: usize,
+}
+
+impl Default for Plane {
+    fn default() -> Self {
+        Plane {
+            width: 1.,
+            depth: 1.,
+            width_segments: 1,
+            depth_segments: 1,
+        }
+    }
+}
+
+impl Plane {
+    /// triangles facing +z
+    pub fn to_mesh(&self) -> anyhow::Result<IndexMesh> {
+        check_positive(self.width, "plane width")?;
+        check_positive(self.depth, "plane depth")?;
+        check_count(self.width_segments, 1, "plane width segments")?;
+        check_count(self.depth_segments, 1, "plane depth segments")?;
+        let (nx, ny) = (self.width_segments, self.depth_segments);
+        let mut mesh = IndexMesh::new();
+        for j in 0..=ny {
+            for i in 0..=nx {
+                mesh.vertices.push(Vec3::new(
+                    self.width * (i as f32 / nx as f32 - 0.5),
+                    self.depth * (j as f32 / ny as f32 - 0.5),
+                    0.,
+                ));
+            }
+        }
+        let index = |i: usize, j: usize| j * (nx + 1) + i;
+        for j in 0..ny {
+            for i in 0..nx {
+                let (a, b) = (index(i, j), index(i + 1, j));
+                let (c, d) = (index(i, j + 1), index(i + 1, j + 1));
+                mesh.triangles.push(IndexTriangle(a, b, d));
+                mesh.triangles.push(IndexTriangle(a, d, c));
+            }
+        }
+        Ok(mesh)
+    }
+}
 
-// impl Cylinder {
-//     fn to_mesh(&self, radius_seg: usize, ) -> IndexMesh {
-//         todo!()
-//     }
-// }
+/// sphere made by subdividing an icosahedron, with triangles of nearly equal size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Icosphere {
+    /// radius along each axis, unequal radii give an ellipsoid
+    pub radius: Vec3,
+    /// times every triangle is split in 4, 0 is the icosahedron. at most
+    /// [`Icosphere::MAX_SUBDIVISIONS`]
+    pub subdivisions: usize,
+}
+
+impl Default for Icosphere {
+    fn default() -> Self {
+        Icosphere {
+            radius: Vec3::ONE,
+            subdivisions: 3,
+        }
+    }
+}
+
+impl Icosphere {
+    /// subdivisions that give 20 971 520 triangles, each one more multiplies them by 4
+    pub const MAX_SUBDIVISIONS: usize = 10;
+
+    /// triangles facing outwards
+    pub fn to_mesh(&self) -> anyhow::Result<IndexMesh> {
+        if self.subdivisions > Self::MAX_SUBDIVISIONS {
+            bail!(
+                "icosphere subdivisions must be at most {}, got {}",
+                Self::MAX_SUBDIVISIONS,
+                self.subdivisions
+            );
+        }
+        check_positive(self.radius.min_element(), "icosphere radius")?;
+        if !self.radius.is_finite() {
+            bail!("icosphere radius must be finite, got {}", self.radius);
+        }
+        let t = (1. + 5f32.sqrt()) / 2.;
+        let mut vertices = [
+            [-1., t, 0.],
+            [1., t, 0.],
+            [-1., -t, 0.],
+            [1., -t, 0.],
+            [0., -1., t],
+            [0., 1., t],
+            [0., -1., -t],
+            [0., 1., -t],
+            [t, 0., -1.],
+            [t, 0., 1.],
+            [-t, 0., -1.],
+            [-t, 0., 1.],
+        ]
+        .map(|v| Vec3::from_array(v).normalize())
+        .to_vec();
+        let mut triangles = [
+            [0, 11, 5],
+            [0, 5, 1],
+            [0, 1, 7],
+            [0, 7, 10],
+            [0, 10, 11],
+            [1, 5, 9],
+            [5, 11, 4],
+            [11, 10, 2],
+            [10, 7, 6],
+            [7, 1, 8],
+            [3, 9, 4],
+            [3, 4, 2],
+            [3, 2, 6],
+            [3, 6, 8],
+            [3, 8, 9],
+            [4, 9, 5],
+            [2, 4, 11],
+            [6, 2, 10],
+            [8, 6, 7],
+            [9, 8, 1],
+        ]
+        .to_vec();
+        for _ in 0..self.subdivisions {
+            let mut midpoints = HashMap::new();
+            let mut midpoint = |a: usize, b: usize| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    vertices.push(((vertices[a] + vertices[b]) / 2.).normalize());
+                    vertices.len() - 1
+                })
+            };
+            triangles = triangles
+                .into_iter()
+                .flat_map(|[a, b, c]| {
+                    let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                    [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+                })
+                .collect();
+        }
+        let mut mesh = IndexMesh::new();
+        mesh.vertices = vertices.into_iter().map(|v| v * self.radius).collect();
+        mesh.triangles = triangles
+            .into_iter()
+            .map(|[a, b, c]| IndexTriangle(a, b, c))
+            .collect();
+        Ok(mesh)
+    }
+}
+
+/// cylinder with hemispherical ends along the z axis, centred at the origin
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capsule {
+    /// radius of the cylinder and the hemispheres
+    pub radius: f32,
+    /// length of the cylinder between the hemisphere centres, may be 0 for a sphere
+    pub height: f32,
+    /// vertices around the z axis, at least 3
+    pub segments: usize,
+    /// bands of each hemisphere, at least 1
+    pub rings: usize,
+}
+
+impl Default for Capsule {
+    fn default() -> Self {
+        Capsule {
+            radius: 0.5,
+            height: 1.,
+            segments: 32,
+            rings: 8,
+        }
+    }
+}
+
+impl Capsule {
+    /// triangles facing outwards
+    pub fn to_mesh(&self) -> anyhow::Result<IndexMesh> {
+        check_positive(self.radius, "capsule radius")?;
+        if !(self.height.is_finite() && self.height >= 0.) {
+            bail!("capsule height must not be negative, got {}", self.height);
+        }
+        check_count(self.segments, 3, "capsule segments")?;
+        check_count(self.rings, 1, "capsule rings")?;
+        let rings = self.rings;
+        let h = self.height / 2.;
+        let hemisphere = |k: usize| {
+            let theta = std::f32::consts::FRAC_PI_2 * k as f32 / rings as f32;
+            let radius = if k == 0 {
+                0.
+            } else {
+                self.radius * theta.sin()
+            };
+            (radius, self.radius * theta.cos())
+        };
+        let mut profile = (0..=rings)
+            .map(|k| {
+                let (radius, z) = hemisphere(k);
+                (radius, h + z)
+            })
+            .collect::<Vec<_>>();
+        // the equators are the same ring without a cylinder
+        let first = if h > 0. { rings } else { rings - 1 };
+        profile.extend((0..=first).rev().map(|k| {
+            let (radius, z) = hemisphere(k);
+            (radius, -h - z)
+        }));
+        Ok(lathe(&profile, self.segments, false, std::f32::consts::TAU))
+    }
+}
+
+/// box with edges and corners rounded to quarter cylinders and spheres, centred at the origin
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoundedBox {
+    /// size along each axis
+    pub size: Vec3,
+    /// radius of the rounding, at most half the smallest size. 0 gives a sharp box
+    pub radius: f32,
+    /// bands across each rounded edge, at least 1
+    pub segments: usize,
+}
+
+impl Default for RoundedBox {
+    fn default() -> Self {
+        RoundedBox {
+            size: Vec3::ONE,
+            radius: 0.1,
+            segments: 4,
+        }
+    }
+}
+
+impl RoundedBox {
+    /// triangles facing outwards
+    pub fn to_mesh(&self) -> anyhow::Result<IndexMesh> {
+        check_positive(self.size.min_element(), "rounded box size")?;
+        if !self.size.is_finite() {
+            bail!("rounded box size must be finite, got {}", self.size);
+        }
+        let half = self.size / 2.;
+        if !(0. ..=half.min_element()).contains(&self.radius) {
+            bail!(
+                "rounded box radius {} is not in [0, {}]",
+                self.radius,
+                half.min_element()
+            );
+        }
+        let radius = self.radius;
+        if radius > 0. {
+            check_count(self.segments, 1, "rounded box segments")?;
+        }
+        let segments = if radius > 0. { self.segments } else { 0 };
+        let inner = half - Vec3::splat(radius);
+        // grid lines of every axis as (centre, direction) along that axis. the faces of a cube
+        // with these grid lines are moved to centre + radius * normalized direction
+        let lines = [0, 1, 2].map(|axis| {
+            let angle = |k: usize| std::f32::consts::FRAC_PI_2 * k as f32 / segments.max(1) as f32;
+            let mut lines = (0..=segments)
+                .map(|k| (-inner[axis], -angle(k).cos()))
+                .collect::<Vec<_>>();
+            // the two lines meet without a flat part
+            let first = if inner[axis] > 0. { 0 } else { 1 };
+            lines.extend((first..=segments).map(|k| (inner[axis], angle(k).sin())));
+            lines
+        });
+        let mut mesh = IndexMesh::new();
+        let mut indices = HashMap::new();
+        let mut vertex = |index: [usize; 3]| {
+            *indices.entry(index).or_insert_with(|| {
+                let (centre, direction): (Vec3, Vec3) = (
+                    Vec3::from_array([0, 1, 2].map(|a| lines[a][index[a]].0)),
+                    Vec3::from_array([0, 1, 2].map(|a| lines[a][index[a]].1)),
+                );
+                mesh.vertices
+                    .push(centre + radius * direction.normalize_or_zero());
+                mesh.vertices.len() - 1
+            })
+        };
+        let mut triangles = vec![];
+        for a in 0..3 {
+            let (b, c) = ((a + 1) % 3, (a + 2) % 3);
+            for high in [false, true] {
+                let fixed = if high { lines[a].len() - 1 } else { 0 };
+                for i in 0..lines[b].len() - 1 {
+                    for j in 0..lines[c].len() - 1 {
+                        let mut corner = |di: usize, dj: usize| {
+                            let mut index = [0; 3];
+                            index[a] = fixed;
+                            index[b] = i + di;
+                            index[c] = j + dj;
+                            vertex(index)
+                        };
+                        // b cross c is a, so this order faces +a
+                        let mut quad = [corner(0, 0), corner(1, 0), corner(1, 1), corner(0, 1)];
+                        if !high {
+                            quad.reverse();
+                        }
+                        triangles.push(IndexTriangle(quad[0], quad[1], quad[2]));
+                        triangles.push(IndexTriangle(quad[0], quad[2], quad[3]));
+                    }
+                }
+            }
+        }
+        mesh.triangles = triangles;
+        Ok(mesh)
+    }
+}
+
+/// twice the signed area of a closed polygon, positive when counter clockwise
+fn signed_area(polygon: &[Vec2]) -> f32 {
+    (0..polygon.len()).fold(0., |area, i| {
+        area + polygon[i].perp_dot(polygon[(i + 1) % polygon.len()])
+    })
+}
+
+/// cap triangles of a polygon with holes, counter clockwise and indexing the outline followed by
+/// the holes. an error when the loops cross or touch, the walls would not close around the caps
+fn cap(outline: &[Vec2], holes: &[Vec<Vec2>]) -> anyhow::Result<Vec<[usize; 3]>> {
+    let polygon = Polygon {
+        outline: outline.to_vec(),
+        holes: holes.to_vec(),
+    };
+    let triangles = polygon.triangulate(TriangulationMode::ConstrainedDelaunay)?;
+    // a polygon with n points and h holes has n + 2h - 2 triangles
+    let points = outline.len() + holes.iter().map(|h| h.len()).sum::<usize>();
+    if triangles.len() + 2 != points + 2 * holes.len() {
+        bail!("polygon loops cross or touch each other");
+    }
+    Ok(triangles)
+}
+
+/// polygon with holes in the xy plane extruded along +z, like `linear_extrude` in openscad
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extrusion {
+    /// outer boundary, a simple polygon in either orientation
+    pub outline: Vec<Vec2>,
+    /// boundaries of the holes, inside the outline and apart from each other
+    pub holes: Vec<Vec<Vec2>>,
+    /// distance between the caps, the bottom cap is at z = 0
+    pub height: f32,
+    /// counter clockwise rotation of the top cap around the z axis, in radians
+    pub twist: f32,
+    /// size of the top cap relative to the bottom cap along x and y, positive
+    pub scale: Vec2,
+    /// layers along z, at least 1. twisted extrusions need several to follow the twist
+    pub slices: usize,
+}
+
+impl Default for Extrusion {
+    /// unit square centred at the origin, extruded by 1
+    fn default() -> Self {
+        Extrusion {
+            outline: vec![
+                Vec2::new(-0.5, -0.5),
+                Vec2::new(0.5, -0.5),
+                Vec2::new(0.5, 0.5),
+                Vec2::new(-0.5, 0.5),
+            ],
+            holes: vec![],
+            height: 1.,
+            twist: 0.,
+            scale: Vec2::ONE,
+            slices: 1,
+        }
+    }
+}
+
+impl Extrusion {
+    /// capped solid with triangles facing outwards, an error when the loops do not bound an area
+    pub fn to_mesh(&self) -> anyhow::Result<IndexMesh> {
+        if let Some(polygon) = std::iter::once(&self.outline)
+            .chain(self.holes.iter())
+            .find(|polygon| polygon.len() < 3)
+        {
+            bail!("extrusion loop with {} points", polygon.len());
+        }
+        check_positive(self.height, "extrusion height")?;
+        check_positive(self.scale.min_element(), "extrusion scale")?;
+        if !(self.scale.is_finite() && self.twist.is_finite()) {
+            bail!("extrusion scale and twist must be finite");
+        }
+        check_count(self.slices, 1, "extrusion slices")?;
+        let mut mesh = IndexMesh::new();
+        // the solid is on the left of every loop, so the outline goes counter clockwise and the
+        // holes clockwise
+        let mut loops = vec![self.outline.clone()];
+        loops.extend(self.holes.iter().cloned());
+        for (i, polygon) in loops.iter_mut().enumerate() {
+            if (signed_area(polygon) > 0.) != (i == 0) {
+                polygon.reverse();
+            }
+        }
+        let triangles = cap(&loops[0], &loops[1..])?;
+        let points = loops.concat();
+
+        let slices = self.slices;
+        let n = points.len();
+        for k in 0..=slices {
+            let t = k as f32 / slices as f32;
+            let rotation = Vec2::from_angle(self.twist * t);
+            let scale = Vec2::ONE.lerp(self.scale, t);
+            mesh.vertices.extend(
+                points
+                    .iter()
+                    .map(|p| rotation.rotate(*p * scale).extend(self.height * t)),
+            );
+        }
+        let vertex = |k: usize, i: usize| k * n + i;
+
+        let mut start = 0;
+        for polygon in loops.iter() {
+            let len = polygon.len();
+            for i in 0..len {
+                let (p, q) = (start + i, start + (i + 1) % len);
+                for k in 0..slices {
+                    let (a, b) = (vertex(k, p), vertex(k, q));
+                    let (c, d) = (vertex(k + 1, q), vertex(k + 1, p));
+                    mesh.triangles.push(IndexTriangle(a, b, c));
+                    mesh.triangles.push(IndexTriangle(a, c, d));
+                }
+            }
+            start += len;
+        }
+        for [a, b, c] in triangles {
+            mesh.triangles
+                .push(IndexTriangle(vertex(0, a), vertex(0, c), vertex(0, b)));
+            mesh.triangles.push(IndexTriangle(
+                vertex(slices, a),
+                vertex(slices, b),
+                vertex(slices, c),
+            ));
+        }
+        Ok(mesh)
+    }
+}
+
+/// closed profile revolved around the z axis, like `rotate_extrude` in openscad
+#[derive(Debug, Clone, PartialEq)]
+pub struct Revolution {
+    /// simple polygon of (radius, z) points in either orientation, with no negative radius.
+    /// points on the axis become a single vertex
+    pub profile: Vec<Vec2>,
+    /// counter clockwise sweep from the xz plane, in radians, positive. a full turn or more gives
+    /// a ring without caps
+    pub angle: f32,
+    /// bands around the z axis, at least 3 for a full turn and 1 otherwise
+    pub segments: usize,
+}
+
+impl Default for Revolution {
+    /// full turn of a unit square from radius 0.5 to 1.5, centred at z = 0
+    fn default() -> Self {
+        Revolution {
+            profile: vec![
+                Vec2::new(0.5, -0.5),
+                Vec2::new(1.5, -0.5),
+                Vec2::new(1.5, 0.5),
+                Vec2::new(0.5, 0.5),
+            ],
+            angle: std::f32::consts::TAU,
+            segments: 32,
+        }
+    }
+}
+
+impl Revolution {
+    /// capped solid with triangles facing outwards, an error when the profile does not bound an
+    /// area
+    pub fn to_mesh(&self) -> anyhow::Result<IndexMesh> {
+        if self.profile.len() < 3 {
+            bail!("revolution profile with {} points", self.profile.len());
+        }
+        if let Some(p) = self.profile.iter().find(|p| !p.is_finite() || p.x < 0.) {
+            bail!("invalid revolution profile point {}", p);
+        }
+        check_positive(self.angle, "revolution angle")?;
+        let full = self.angle >= std::f32::consts::TAU;
+        let min_segments = if full { 3 } else { 1 };
+        check_count(self.segments, min_segments, "revolution segments")?;
+        // lathe wants the solid on the right of the profile
+        let mut profile = self.profile.clone();
+        if signed_area(&profile) > 0. {
+            profile.reverse();
+        }
+        // the caps of a full turn are not used, but a profile without them has no area
+        let triangles = cap(&profile, &[])?;
+        let points = profile.iter().map(|p| (p.x, p.y)).collect::<Vec<_>>();
+        let mut mesh = lathe(&points, self.segments, true, self.angle);
+        if full {
+            return Ok(mesh);
+        }
+
+        // first vertex of every profile point, the columns follow unless it is a pole
+        let mut rows = Vec::with_capacity(points.len());
+        let last = self.segments;
+        let mut next = 0;
+        for (radius, _) in points.iter() {
+            rows.push((next, *radius == 0.));
+            next += if *radius == 0. { 1 } else { last + 1 };
+        }
+        let vertex = |k: usize, column: usize| {
+            let (row, pole) = rows[k];
+            if pole {
+                row
+            } else {
+                row + column
+            }
+        };
+        // counter clockwise in the xz plane faces -y, away from the sweep
+        for [a, b, c] in triangles {
+            mesh.triangles
+                .push(IndexTriangle(vertex(a, 0), vertex(b, 0), vertex(c, 0)));
+            mesh.triangles.push(IndexTriangle(
+                vertex(a, last),
+                vertex(c, last),
+                vertex(b, last),
+            ));
+        }
+        Ok(mesh)
+    }
+}
@@ -0,0 +1,541 @@
+use std::f32::consts::PI;
+
+use glam::{Vec2, Vec3};
+
+use crate::{
+    test_utils::{assert_closed, volume},
+    IndexMesh,
+};
+
+use super::{
+    Capsule, Cone, Cylinder, Extrusion, Icosphere, Plane, Revolution, RoundedBox, Sphere, Torus,
+};
+
+/// closed, facing outwards and without holes through it
+fn assert_solid(mesh: &IndexMesh) {
+    assert_closed(mesh);
+    // euler characteristic of a sphere, v - e + f with e = 3f / 2
+    let euler = mesh.vertices.len() as i64 - mesh.triangles.len() as i64 / 2;
+    assert_eq!(euler, 2);
+}
+
+#[test]
+fn test_sphere() {
+    let sphere = Sphere {
+        radius: 2.,
+        segments: 8,
+        rings: 4,
+    };
+    let mesh = sphere.to_mesh().unwrap();
+    assert_eq!(mesh.vertices.len(), 2 + 8 * 3);
+    assert_eq!(mesh.triangles.len(), 2 * 8 + 2 * 8 * 2);
+    assert_solid(&mesh);
+    assert!(mesh.vertices.iter().all(|v| (v.length() - 2.).abs() < 1e-5));
+
+    let mesh = Sphere {
+        radius: 2.,
+        ..Sphere::default()
+    }
+    .to_mesh()
+    .unwrap();
+    assert_solid(&mesh);
+    let exact = 4. / 3. * PI * 8.;
+    assert!((volume(&mesh) - exact).abs() / exact < 0.02);
+
+    let sphere = Sphere::default();
+    assert!(Sphere {
+        segments: 2,
+        ..sphere
+    }
+    .to_mesh()
+    .is_err());
+    assert!(Sphere {
+        radius: f32::NAN,
+        ..sphere
+    }
+    .to_mesh()
+    .is_err());
+}
+
+#[test]
+fn test_cylinder_and_cone() {
+    let cylinder = Cylinder {
+        radius: 0.5,
+        height: 3.,
+        segments: 64,
+    };
+    let mesh = cylinder.to_mesh().unwrap();
+    assert_eq!(mesh.vertices.len(), 2 + 64 * 2);
+    assert_solid(&mesh);
+    let exact = PI * 0.25 * 3.;
+    assert!((volume(&mesh) - exact).abs() / exact < 0.01);
+    assert!(mesh.vertices.iter().all(|v| v.z.abs() == 1.5));
+
+    let cone = Cone {
+        radius: 1.,
+        height: 2.,
+        segments: 64,
+    };
+    let mesh = cone.to_mesh().unwrap();
+    assert_eq!(mesh.vertices.len(), 2 + 64);
+    assert_solid(&mesh);
+    let exact = PI * 2. / 3.;
+    assert!((volume(&mesh) - exact).abs() / exact < 0.01);
+    assert_solid(&Cone::default().to_mesh().unwrap());
+
+    // a cylinder without segments used to come out as a triangular prism
+    let cylinder = Cylinder::default();
+    assert!(Cylinder {
+        segments: 0,
+        ..cylinder
+    }
+    .to_mesh()
+    .is_err());
+    assert!(Cylinder {
+        height: 0.,
+        ..cylinder
+    }
+    .to_mesh()
+    .is_err());
+    assert!(Cone {
+        radius: -1.,
+        ..Cone::default()
+    }
+    .to_mesh()
+    .is_err());
+}
+
+#[test]
+fn test_torus() {
+    let torus = Torus {
+        major_radius: 2.,
+        minor_radius: 0.5,
+        major_segments: 64,
+        minor_segments: 32,
+    };
+    let mesh = torus.to_mesh().unwrap();
+    assert_eq!(mesh.vertices.len(), 64 * 32);
+    assert_closed(&mesh);
+    // a torus has euler characteristic 0
+    assert_eq!(mesh.vertices.len() * 2, mesh.triangles.len());
+    let exact = 2. * PI * PI * 2. * 0.25;
+    assert!((volume(&mesh) - exact).abs() / exact < 0.01);
+
+    // a tube as thick as the ring has no hole and crosses itself
+    assert!(Torus {
+        minor_radius: 2.,
+        ..torus
+    }
+    .to_mesh()
+    .is_err());
+    assert!(Torus {
+        minor_segments: 2,
+        ..torus
+    }
+    .to_mesh()
+    .is_err());
+}
+
+#[test]
+fn test_plane() {
+    let plane = Plane {
+        width: 2.,
+        depth: 4.,
+        width_segments: 2,
+        depth_segments: 3,
+    };
+    let mesh = plane.to_mesh().unwrap();
+    assert_eq!(mesh.vertices.len(), 3 * 4);
+    assert_eq!(mesh.triangles.len(), 2 * 2 * 3);
+    let mut area = 0.;
+    for t in mesh.triangles() {
+        let (a, b, c) = (mesh.vertices[t.0], mesh.vertices[t.1], mesh.vertices[t.2]);
+        let normal = (b - a).cross(c - a);
+        assert!(normal.z > 0.);
+        area += normal.length() / 2.;
+    }
+    assert!((area - 8.).abs() < 1e-5);
+    assert!(!mesh.to_halfedge_mesh().is_mesh_watertight());
+
+    assert!(Plane { width: 0., ..plane }.to_mesh().is_err());
+    assert!(Plane {
+        depth_segments: 0,
+        ..plane
+    }
+    .to_mesh()
+    .is_err());
+}
+
+fn bounds(mesh: &IndexMesh) -> (Vec3, Vec3) {
+    mesh.vertices.iter().fold(
+        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+        |(min, max), v| (min.min(*v), max.max(*v)),
+    )
+}
+
+#[test]
+fn test_icosphere() {
+    let mesh = Icosphere {
+        radius: Vec3::ONE,
+        subdivisions: 0,
+    }
+    .to_mesh()
+    .unwrap();
+    assert_eq!(mesh.vertices.len(), 12);
+    assert_eq!(mesh.triangles.len(), 20);
+    assert_solid(&mesh);
+
+    let mesh = Icosphere::default().to_mesh().unwrap();
+    assert_eq!(mesh.vertices.len(), 642);
+    assert_eq!(mesh.triangles.len(), 1280);
+    assert_solid(&mesh);
+    assert!(mesh.vertices.iter().all(|v| (v.length() - 1.).abs() < 1e-5));
+
+    let radius = Vec3::new(1., 2., 3.);
+    let mesh = Icosphere {
+        radius,
+        subdivisions: 4,
+    }
+    .to_mesh()
+    .unwrap();
+    assert_solid(&mesh);
+    let exact = 4. / 3. * PI * 6.;
+    assert!((volume(&mesh) - exact).abs() / exact < 0.01);
+    let (min, max) = bounds(&mesh);
+    assert!(max.abs_diff_eq(radius, 1e-5) && min.abs_diff_eq(-radius, 1e-5));
+
+    assert!(Icosphere {
+        radius: Vec3::ONE,
+        subdivisions: 15,
+    }
+    .to_mesh()
+    .is_err());
+    assert!(Icosphere {
+        radius: Vec3::new(1., 0., 1.),
+        subdivisions: 1,
+    }
+    .to_mesh()
+    .is_err());
+}
+
+#[test]
+fn test_capsule() {
+    let capsule = Capsule {
+        radius: 0.5,
+        height: 2.,
+        segments: 64,
+        rings: 16,
+    };
+    let mesh = capsule.to_mesh().unwrap();
+    assert_solid(&mesh);
+    let exact = PI * 0.25 * 2. + 4. / 3. * PI * 0.125;
+    assert!((volume(&mesh) - exact).abs() / exact < 0.01);
+    let (min, max) = bounds(&mesh);
+    assert!((max.z - 1.5).abs() < 1e-6 && (min.z + 1.5).abs() < 1e-6);
+    assert!((max.x - 0.5).abs() < 1e-6);
+
+    // without a cylinder it is a sphere
+    let sphere = Capsule {
+        height: 0.,
+        ..capsule
+    }
+    .to_mesh()
+    .unwrap();
+    assert_solid(&sphere);
+    assert_eq!(sphere.vertices.len(), 2 + 64 * 31);
+    assert!(Capsule {
+        rings: 0,
+        ..capsule
+    }
+    .to_mesh()
+    .is_err());
+    assert!(Capsule {
+        height: -1.,
+        ..capsule
+    }
+    .to_mesh()
+    .is_err());
+}
+
+#[test]
+fn test_rounded_box() {
+    let size = Vec3::new(2., 3., 4.);
+    for (radius, segments) in [(0., 4), (0.25, 1), (0.25, 6), (1., 6)] {
+        let mesh = RoundedBox {
+            size,
+            radius,
+            segments,
+        }
+        .to_mesh()
+        .unwrap();
+        assert_solid(&mesh);
+        let (min, max) = bounds(&mesh);
+        assert!(max.abs_diff_eq(size / 2., 1e-5), "{} {}", radius, max);
+        assert!(min.abs_diff_eq(-size / 2., 1e-5));
+        // every triangle has an area
+        for t in mesh.triangles() {
+            let (a, b, c) = (mesh.vertices[t.0], mesh.vertices[t.1], mesh.vertices[t.2]);
+            assert!((b - a).cross(c - a).length() > 1e-8);
+        }
+    }
+
+    let mesh = RoundedBox {
+        size,
+        radius: 0.,
+        segments: 4,
+    }
+    .to_mesh()
+    .unwrap();
+    assert_eq!(mesh.vertices.len(), 8);
+    assert!((volume(&mesh) - 24.).abs() < 1e-5);
+
+    // a box minus its edges and corners, plus quarter cylinders and a sphere
+    let r = 0.5;
+    let mesh = RoundedBox {
+        size,
+        radius: r,
+        segments: 16,
+    }
+    .to_mesh()
+    .unwrap();
+    let inner = size - Vec3::splat(2. * r);
+    let exact = inner.x * inner.y * inner.z
+        + 2. * r * (inner.x * inner.y + inner.y * inner.z + inner.z * inner.x)
+        + PI * r * r * (inner.x + inner.y + inner.z)
+        + 4. / 3. * PI * r * r * r;
+    assert!((volume(&mesh) - exact).abs() / exact < 0.01);
+
+    // the rounding does not fit in the smallest size
+    assert!(RoundedBox {
+        size,
+        radius: 1.5,
+        segments: 4,
+    }
+    .to_mesh()
+    .is_err());
+    assert!(RoundedBox {
+        size,
+        radius: 0.5,
+        segments: 0,
+    }
+    .to_mesh()
+    .is_err());
+}
+
+fn square(half: f32) -> Vec<Vec2> {
+    vec![
+        Vec2::new(-half, -half),
+        Vec2::new(half, -half),
+        Vec2::new(half, half),
+        Vec2::new(-half, half),
+    ]
+}
+
+#[test]
+fn test_extrusion() {
+    // clockwise l shape
+    let outline = vec![
+        Vec2::new(0., 0.),
+        Vec2::new(0., 2.),
+        Vec2::new(1., 2.),
+        Vec2::new(1., 1.),
+        Vec2::new(2., 1.),
+        Vec2::new(2., 0.),
+    ];
+    let mesh = Extrusion {
+        outline,
+        height: 2.,
+        ..Extrusion::default()
+    }
+    .to_mesh()
+    .unwrap();
+    assert_solid(&mesh);
+    assert!((volume(&mesh) - 6.).abs() < 1e-5);
+    let (min, max) = bounds(&mesh);
+    assert_eq!(min, Vec3::ZERO);
+    assert_eq!(max, Vec3::new(2., 2., 2.));
+
+    let mesh = Extrusion {
+        outline: square(1.),
+        holes: vec![square(0.5)],
+        ..Extrusion::default()
+    }
+    .to_mesh()
+    .unwrap();
+    assert_closed(&mesh);
+    // a hole through the solid gives euler characteristic 0
+    assert_eq!(mesh.vertices.len() * 2, mesh.triangles.len());
+    assert!((volume(&mesh) - 3.).abs() < 1e-5);
+
+    // a frustum of a pyramid
+    let mesh = Extrusion {
+        outline: square(1.),
+        scale: Vec2::splat(0.5),
+        slices: 4,
+        ..Extrusion::default()
+    }
+    .to_mesh()
+    .unwrap();
+    assert_solid(&mesh);
+    assert!((volume(&mesh) - (4. + 2. + 1.) / 3.).abs() < 1e-5);
+
+    let mesh = Extrusion {
+        outline: square(1.),
+        holes: vec![square(0.5)],
+        twist: PI / 2.,
+        slices: 16,
+        ..Extrusion::default()
+    }
+    .to_mesh()
+    .unwrap();
+    assert_closed(&mesh);
+    // the straight walls between the slices cut into the twisted surface
+    assert!((volume(&mesh) - 3.).abs() < 0.2);
+    // the corner at (1, 1) turns to (-1, 1) at the top
+    assert!(mesh
+        .vertices
+        .iter()
+        .any(|v| v.distance(Vec3::new(-1., 1., 1.)) < 1e-5));
+
+    assert!(Extrusion {
+        outline: vec![],
+        ..Extrusion::default()
+    }
+    .to_mesh()
+    .is_err());
+    assert!(Extrusion {
+        holes: vec![vec![Vec2::ZERO]],
+        ..Extrusion::default()
+    }
+    .to_mesh()
+    .is_err());
+    // a self crossing outline used to give walls without caps
+    assert!(Extrusion {
+        outline: vec![
+            Vec2::ZERO,
+            Vec2::new(3., 2.),
+            Vec2::new(3., 0.),
+            Vec2::new(0., 1.)
+        ],
+        ..Extrusion::default()
+    }
+    .to_mesh()
+    .is_err());
+    // a cap without area used to leave the extrusion open
+    assert!(Extrusion {
+        outline: vec![Vec2::ZERO, Vec2::X, Vec2::new(2., 0.)],
+        ..Extrusion::default()
+    }
+    .to_mesh()
+    .is_err());
+    assert!(Extrusion {
+        holes: vec![square(0.25)
+            .into_iter()
+            .map(|p| p + Vec2::new(2., 0.))
+            .collect()],
+        ..Extrusion::default()
+    }
+    .to_mesh()
+    .is_err());
+    assert!(Extrusion {
+        scale: Vec2::new(1., 0.),
+        ..Extrusion::default()
+    }
+    .to_mesh()
+    .is_err());
+}
+
+#[test]
+fn test_revolution() {
+    let mesh = Revolution {
+        segments: 64,
+        ..Revolution::default()
+    }
+    .to_mesh()
+    .unwrap();
+    assert_closed(&mesh);
+    assert_eq!(mesh.vertices.len() * 2, mesh.triangles.len());
+    let exact = PI * (1.5 * 1.5 - 0.5 * 0.5);
+    assert!((volume(&mesh) - exact).abs() / exact < 0.01);
+
+    // half a turn of the same ring, the polygon has the area of 8 triangles per radius
+    let mesh = Revolution {
+        angle: PI,
+        segments: 8,
+        ..Revolution::default()
+    }
+    .to_mesh()
+    .unwrap();
+    assert_solid(&mesh);
+    let exact = 8. * 0.5 * (PI / 8.).sin() * (1.5 * 1.5 - 0.5 * 0.5);
+    assert!((volume(&mesh) - exact).abs() < 1e-4);
+    assert!(mesh.vertices.iter().all(|v| v.y >= -1e-6));
+
+    // a quarter of a cylinder, the profile touches the axis
+    let mesh = Revolution {
+        profile: vec![
+            Vec2::new(0., 0.),
+            Vec2::new(0., 1.),
+            Vec2::new(1., 1.),
+            Vec2::new(1., 0.),
+        ],
+        angle: PI / 2.,
+        segments: 1,
+    }
+    .to_mesh()
+    .unwrap();
+    assert_solid(&mesh);
+    assert_eq!(mesh.vertices.len(), 6);
+    assert!((volume(&mesh) - 0.5).abs() < 1e-5);
+
+    // a full turn touching the axis is a cylinder
+    let mesh = Revolution {
+        profile: vec![
+            Vec2::new(0., 0.),
+            Vec2::new(1., 0.),
+            Vec2::new(1., 1.),
+            Vec2::new(0., 1.),
+        ],
+        angle: 2. * PI,
+        segments: 16,
+    }
+    .to_mesh()
+    .unwrap();
+    assert_solid(&mesh);
+    assert_eq!(mesh.vertices.len(), 2 + 2 * 16);
+    // no sweep, or one that is not a number
+    for angle in [0., -PI, f32::NAN, f32::INFINITY] {
+        let revolution = Revolution {
+            angle,
+            ..Revolution::default()
+        };
+        assert!(revolution.to_mesh().is_err());
+    }
+
+    // a self crossing profile, and one without area
+    for angle in [PI, 2. * PI] {
+        let revolution = Revolution {
+            profile: vec![
+                Vec2::new(1., 0.),
+                Vec2::new(2., 1.),
+                Vec2::new(2., 0.),
+                Vec2::new(1., 1.),
+            ],
+            angle,
+            ..Revolution::default()
+        };
+        assert!(revolution.to_mesh().is_err());
+        let revolution = Revolution {
+            profile: vec![Vec2::new(1., 0.), Vec2::new(1., 1.), Vec2::new(1., 2.)],
+            angle,
+            ..Revolution::default()
+        };
+        assert!(revolution.to_mesh().is_err());
+    }
+    assert!(Revolution {
+        profile: vec![Vec2::new(-1., 0.), Vec2::new(1., 0.), Vec2::new(1., 1.)],
+        ..Revolution::default()
+    }
+    .to_mesh()
+    .is_err());
+}
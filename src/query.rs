@@ -0,0 +1,428 @@
+use anyhow::bail;
+use glam::{Mat4, Vec3};
+
+use crate::{
+    algorithms::{tri_tri_intersect, winding_number, TriTriIntersectStatus},
+    IndexMesh, MeshBvh, AABB,
+};
+
+#[cfg(test)]
+mod query_tests;
+
+/// half line starting at `origin`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    /// start point of the ray
+    pub origin: Vec3,
+    /// direction of the ray, distances along the ray are measured in its length
+    pub direction: Vec3,
+}
+
+impl Ray {
+    /// new ray from origin towards direction
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Ray { origin, direction }
+    }
+
+    /// point at distance t along the ray
+    pub fn at(&self, t: f32) -> Vec3 {
+        self.origin + self.direction * t
+    }
+}
+
+/// where a ray hits a triangle of a mesh
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    /// index of the hit triangle in the mesh
+    pub triangle: usize,
+    /// distance along the ray, the hit point is `ray.at(t)`
+    pub t: f32,
+    /// barycentric coordinates of the hit point, weights of the three triangle vertices
+    pub barycentric: Vec3,
+    /// unit normal of the hit triangle, following its winding order
+    pub normal: Vec3,
+}
+
+/// nearest point of a mesh to a query point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClosestPoint {
+    /// index of the nearest triangle in the mesh
+    pub triangle: usize,
+    /// nearest point on the mesh surface
+    pub point: Vec3,
+    /// barycentric coordinates of the point, weights of the three triangle vertices
+    pub barycentric: Vec3,
+    /// distance between the query point and the nearest point
+    pub distance: f32,
+}
+
+impl IndexMesh {
+    /// closest hit of the ray, both sides of the triangles are hit
+    ///
+    /// `bvh` must be built from this mesh by [`IndexMesh::build_aabb_bvh_indexed`]
+    pub fn ray_cast(&self, bvh: &MeshBvh, ray: &Ray) -> Option<RayHit> {
+        let inv_dir = ray.direction.recip();
+        let mut closest: Option<RayHit> = None;
+        // nodes with their entry distance, the nearer child is visited first
+        let mut stack = vec![(0, 0.)];
+        while let Some((index, entry)) = stack.pop() {
+            if closest.is_some_and(|hit| hit.t < entry) {
+                continue;
+            }
+            let Some(node) = bvh.get_node(index) else {
+                continue;
+            };
+            if let Some(primitives) = node.primitives {
+                for t in primitives.iter() {
+                    let Some(hit) = self.ray_triangle(*t, ray) else {
+                        continue;
+                    };
+                    if closest.is_none_or(|closest| hit.t < closest.t) {
+                        closest = Some(hit);
+                    }
+                }
+                continue;
+            }
+            let mut children = [node.left, node.right]
+                .into_iter()
+                .flatten()
+                .filter_map(|child| {
+                    let bv = bvh.get_node(child)?.bv;
+                    ray_aabb(ray, inv_dir, &bv).map(|entry| (child, entry))
+                })
+                .collect::<Vec<_>>();
+            children.sort_by(|a, b| b.1.total_cmp(&a.1));
+            stack.extend(children);
+        }
+        closest
+    }
+
+    /// every hit of the ray sorted by distance, both sides of the triangles are hit
+    ///
+    /// a ray passing through an edge or a vertex hits every triangle around it.
+    /// `bvh` must be built from this mesh by [`IndexMesh::build_aabb_bvh_indexed`]
+    pub fn ray_cast_all(&self, bvh: &MeshBvh, ray: &Ray) -> Vec<RayHit> {
+        let inv_dir = ray.direction.recip();
+        let mut hits = vec![];
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let Some(node) = bvh.get_node(index) else {
+                continue;
+            };
+            if ray_aabb(ray, inv_dir, &node.bv).is_none() {
+                continue;
+            }
+            match node.primitives {
+                Some(primitives) => {
+                    hits.extend(primitives.iter().filter_map(|t| self.ray_triangle(*t, ray)))
+                }
+                None => stack.extend([node.right, node.left].into_iter().flatten()),
+            }
+        }
+        hits.sort_by(|a, b| a.t.total_cmp(&b.t).then(a.triangle.cmp(&b.triangle)));
+        hits
+    }
+
+    /// nearest point of the mesh surface to `p`, none if the mesh is empty
+    ///
+    /// `bvh` must be built from this mesh by [`IndexMesh::build_aabb_bvh_indexed`]
+    pub fn closest_point(&self, bvh: &MeshBvh, p: Vec3) -> Option<ClosestPoint> {
+        let mut closest: Option<ClosestPoint> = None;
+        // nodes with their squared distance to p, the nearer child is visited first
+        let mut stack = vec![(0, 0.)];
+        while let Some((index, bound)) = stack.pop() {
+            // nothing in this node can be nearer than what is found
+            if closest.is_some_and(|c| c.distance * c.distance < bound) {
+                continue;
+            }
+            let Some(node) = bvh.get_node(index) else {
+                continue;
+            };
+            if let Some(primitives) = node.primitives {
+                for t in primitives.iter() {
+                    let tri = self.triangle_vertices(*t);
+                    let barycentric = closest_point_on_triangle(p, &tri);
+                    let point =
+                        tri[0] * barycentric.x + tri[1] * barycentric.y + tri[2] * barycentric.z;
+                    let distance = point.distance(p);
+                    if closest.is_none_or(|c| distance < c.distance) {
+                        closest = Some(ClosestPoint {
+                            triangle: *t,
+                            point,
+                            barycentric,
+                            distance,
+                        });
+                    }
+                }
+                continue;
+            }
+            let mut children = [node.left, node.right]
+                .into_iter()
+                .flatten()
+                .filter_map(|child| {
+                    let bv = bvh.get_node(child)?.bv;
+                    Some((child, aabb_distance_squared(p, &bv)))
+                })
+                .collect::<Vec<_>>();
+            children.sort_by(|a, b| b.1.total_cmp(&a.1));
+            stack.extend(children);
+        }
+        closest
+    }
+
+    /// distance from `p` to the mesh surface, none if the mesh is empty
+    ///
+    /// `bvh` must be built from this mesh by [`IndexMesh::build_aabb_bvh_indexed`]
+    pub fn distance(&self, bvh: &MeshBvh, p: Vec3) -> Option<f32> {
+        self.closest_point(bvh, p).map(|c| c.distance)
+    }
+
+    /// pairs of crossing or touching triangles, the first index is from this mesh
+    ///
+    /// each mesh is placed by its own transform, `bvh` and `other_bvh` must be built
+    /// from the meshes by [`IndexMesh::build_aabb_bvh_indexed`]
+    pub fn find_intersect(
+        &self,
+        bvh: &MeshBvh,
+        transform: Mat4,
+        other: &IndexMesh,
+        other_bvh: &MeshBvh,
+        other_transform: Mat4,
+    ) -> Vec<(usize, usize)> {
+        let relative = transform.inverse() * other_transform;
+        let err = tolerance(&bvh.get_root().bv);
+        let pairs = bvh.intersect_bvh_by(transform, other_bvh, other_transform, |a, b| {
+            let b = other.triangle_vertices(*b);
+            triangles_intersect(
+                self.triangle_vertices(*a),
+                b.map(|v| relative.transform_point3(v)),
+                err,
+            )
+        });
+        let mut res = pairs.into_iter().map(|(a, b)| (*a, *b)).collect::<Vec<_>>();
+        res.sort();
+        res
+    }
+
+    fn ray_triangle(&self, triangle: usize, ray: &Ray) -> Option<RayHit> {
+        let tri = self.triangle_vertices(triangle);
+        let (t, u, v) = ray_triangle(ray, &tri)?;
+        Some(RayHit {
+            triangle,
+            t,
+            barycentric: Vec3::new(1. - u - v, u, v),
+            normal: (tri[1] - tri[0]).cross(tri[2] - tri[0]).normalize(),
+        })
+    }
+}
+
+/// a closed mesh with its bvh, answers inside tests and signed distances
+///
+/// ## Example
+///
+/// ```ignore
+/// let mesh = Box { size: 1. }.to_mesh();
+/// let solid = ClosedMesh::new(&mesh)?;
+/// assert!(solid.contains(Vec3::ZERO));
+/// assert_eq!(solid.signed_distance(Vec3::ZERO), -0.5);
+/// ```
+pub struct ClosedMesh<'a> {
+    mesh: &'a IndexMesh,
+    bvh: MeshBvh,
+}
+
+impl<'a> ClosedMesh<'a> {
+    /// directions tried one after another until a ray misses every edge and vertex
+    const RAY_DIRECTIONS: [Vec3; 3] = [
+        Vec3::new(0.352_546_3, 0.580_298_9, 0.734_224_5),
+        Vec3::new(-0.771_383_1, 0.236_718_4, 0.590_715_6),
+        Vec3::new(0.193_285_7, -0.845_519_2, 0.497_762_9),
+    ];
+
+    /// fails if the mesh is not watertight
+    pub fn new(mesh: &'a IndexMesh) -> anyhow::Result<Self> {
+        if mesh.triangles.is_empty() || !mesh.to_halfedge_mesh().is_mesh_watertight() {
+            bail!("mesh is not closed");
+        }
+        Ok(ClosedMesh {
+            mesh,
+            bvh: mesh.build_aabb_bvh_indexed(Default::default()),
+        })
+    }
+
+    /// whether p is inside the mesh, points on the surface may go either way
+    ///
+    /// counts the crossings of a ray, and falls back to the winding number
+    /// when every ray hits the surface too close to an edge
+    pub fn contains(&self, p: Vec3) -> bool {
+        for direction in Self::RAY_DIRECTIONS {
+            if let Some(crossings) = self.ray_parity(&Ray::new(p, direction)) {
+                return crossings % 2 == 1;
+            }
+        }
+        self.winding_number(p) > 0.5
+    }
+
+    /// generalized winding number of p, 1 inside and 0 outside
+    pub fn winding_number(&self, p: Vec3) -> f32 {
+        let mesh = self.mesh;
+        let triangles = mesh.triangles().map(|t| {
+            [mesh.vertices[t.0], mesh.vertices[t.1], mesh.vertices[t.2]].map(|v| v.as_dvec3())
+        });
+        winding_number(p.as_dvec3(), triangles) as f32
+    }
+
+    /// distance from p to the surface, negative inside the mesh
+    pub fn signed_distance(&self, p: Vec3) -> f32 {
+        let distance = self.mesh.distance(&self.bvh, p).unwrap();
+        if self.contains(p) {
+            -distance
+        } else {
+            distance
+        }
+    }
+
+    /// the underlying mesh
+    pub fn mesh(&self) -> &IndexMesh {
+        self.mesh
+    }
+
+    /// number of crossings, none if the ray touches an edge, grazes a face
+    /// or starts on the surface
+    fn ray_parity(&self, ray: &Ray) -> Option<usize> {
+        const EPS: f32 = 1e-5;
+        let hits = self.mesh.ray_cast_all(&self.bvh, ray);
+        for hit in hits.iter() {
+            let grazing = hit.normal.dot(ray.direction).abs() < EPS;
+            let near_edge = hit.barycentric.min_element() < EPS;
+            if grazing || near_edge || hit.t < EPS {
+                return None;
+            }
+        }
+        Some(hits.len())
+    }
+}
+
+/// whether two triangles cross or touch each other
+pub(crate) fn triangles_intersect(a: [Vec3; 3], b: [Vec3; 3], err: f64) -> bool {
+    let status = tri_tri_intersect(a.map(|v| v.as_dvec3()), b.map(|v| v.as_dvec3()), err);
+    !matches!(status, TriTriIntersectStatus::NotIntersect)
+}
+
+/// tolerance of triangle tests relative to the size of the box
+pub(crate) fn tolerance(aabb: &AABB<3>) -> f64 {
+    let size = Vec3::from(aabb.max) - Vec3::from(aabb.min);
+    (size.length() as f64).max(1.) * 1e-6
+}
+
+/// distance where the ray enters the box, 0 if it starts inside
+fn ray_aabb(ray: &Ray, inv_dir: Vec3, aabb: &AABB<3>) -> Option<f32> {
+    let mut near = 0f32;
+    let mut far = f32::INFINITY;
+    for i in 0..3 {
+        // parallel to the slab, 0 * inf would give nan on its planes
+        if ray.direction[i] == 0. {
+            if ray.origin[i] < aabb.min[i] || ray.origin[i] > aabb.max[i] {
+                return None;
+            }
+            continue;
+        }
+        let t1 = (aabb.min[i] - ray.origin[i]) * inv_dir[i];
+        let t2 = (aabb.max[i] - ray.origin[i]) * inv_dir[i];
+        near = near.max(t1.min(t2));
+        far = far.min(t1.max(t2));
+    }
+    (near <= far).then_some(near)
+}
+
+/// Möller–Trumbore, returns t and the barycentric coordinates of the second and third vertex
+fn ray_triangle(ray: &Ray, tri: &[Vec3; 3]) -> Option<(f32, f32, f32)> {
+    let e1 = tri[1] - tri[0];
+    let e2 = tri[2] - tri[0];
+    let p = ray.direction.cross(e2);
+    let det = e1.dot(p);
+    // parallel to the triangle or degenerated triangle
+    if det.abs() <= f32::EPSILON * e1.length() * e2.length() * ray.direction.length() {
+        return None;
+    }
+    let inv_det = det.recip();
+    let s = ray.origin - tri[0];
+    let u = s.dot(p) * inv_det;
+    if !(0. ..=1.).contains(&u) {
+        return None;
+    }
+    let q = s.cross(e1);
+    let v = ray.direction.dot(q) * inv_det;
+    if v < 0. || u + v > 1. {
+        return None;
+    }
+    let t = e2.dot(q) * inv_det;
+    (t >= 0.).then_some((t, u, v))
+}
+
+/// squared distance from p to the box, 0 if p is inside,
+/// infinity for an empty box
+fn aabb_distance_squared(p: Vec3, aabb: &AABB<3>) -> f32 {
+    let (min, max) = (Vec3::from(aabb.min), Vec3::from(aabb.max));
+    if min.cmpgt(max).any() {
+        return f32::INFINITY;
+    }
+    let d = (min - p).max(p - max).max(Vec3::ZERO);
+    d.length_squared()
+}
+
+/// barycentric coordinates of the point on the triangle nearest to p,
+/// from Real-Time Collision Detection 5.1.5
+pub(crate) fn closest_point_on_triangle(p: Vec3, tri: &[Vec3; 3]) -> Vec3 {
+    let [a, b, c] = *tri;
+    let ab = b - a;
+    let ac = c - a;
+    let ap = p - a;
+    let d1 = ab.dot(ap);
+    let d2 = ac.dot(ap);
+    // vertex region a
+    if d1 <= 0. && d2 <= 0. {
+        return Vec3::X;
+    }
+    let bp = p - b;
+    let d3 = ab.dot(bp);
+    let d4 = ac.dot(bp);
+    // vertex region b
+    if d3 >= 0. && d4 <= d3 {
+        return Vec3::Y;
+    }
+    // edge region ab
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0. && d1 >= 0. && d3 <= 0. {
+        let v = d1 / (d1 - d3);
+        return Vec3::new(1. - v, v, 0.);
+    }
+    let cp = p - c;
+    let d5 = ab.dot(cp);
+    let d6 = ac.dot(cp);
+    // vertex region c
+    if d6 >= 0. && d5 <= d6 {
+        return Vec3::Z;
+    }
+    // edge region ac
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0. && d2 >= 0. && d6 <= 0. {
+        let w = d2 / (d2 - d6);
+        return Vec3::new(1. - w, 0., w);
+    }
+    // edge region bc
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0. && (d4 - d3) >= 0. && (d5 - d6) >= 0. {
+        let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return Vec3::new(0., 1. - w, w);
+    }
+    // face region
+    let denom = va + vb + vc;
+    if denom == 0. {
+        // degenerated triangle, fall back to its first vertex
+        return Vec3::X;
+    }
+    let v = vb / denom;
+    let w = vc / denom;
+    Vec3::new(1. - v - w, v, w)
+}
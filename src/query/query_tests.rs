@@ -0,0 +1,313 @@
+use glam::{Mat4, Quat, Vec3};
+use rand::{distributions::Uniform, prelude::Distribution};
+
+use crate::{index_mesh::IndexTriangle, Box, IndexMesh};
+
+use super::*;
+
+fn brute_force_hits(mesh: &IndexMesh, ray: &Ray) -> Vec<RayHit> {
+    let mut hits = (0..mesh.triangles.len())
+        .filter_map(|t| mesh.ray_triangle(t, ray))
+        .collect::<Vec<_>>();
+    hits.sort_by(|a, b| a.t.total_cmp(&b.t).then(a.triangle.cmp(&b.triangle)));
+    hits
+}
+
+#[test]
+fn test_ray_cast() {
+    let mesh = Box { size: 1. }.to_mesh();
+    let bvh = mesh.build_aabb_bvh_indexed(Default::default());
+    let ray = Ray::new(Vec3::new(-2., 0.1, 0.2), Vec3::X);
+    let hit = mesh.ray_cast(&bvh, &ray).unwrap();
+    assert!((hit.t - 1.5).abs() < 1e-6);
+    assert_eq!(hit.normal, Vec3::NEG_X);
+    let t = mesh.triangles[hit.triangle];
+    let p = mesh.vertices[t.0] * hit.barycentric.x
+        + mesh.vertices[t.1] * hit.barycentric.y
+        + mesh.vertices[t.2] * hit.barycentric.z;
+    assert!(p.distance(ray.at(hit.t)) < 1e-6);
+    assert!(p.distance(Vec3::new(-0.5, 0.1, 0.2)) < 1e-6);
+}
+
+#[test]
+fn test_ray_cast_miss() {
+    let mesh = Box { size: 1. }.to_mesh();
+    let bvh = mesh.build_aabb_bvh_indexed(Default::default());
+    // pointing away
+    let ray = Ray::new(Vec3::new(-2., 0.1, 0.2), Vec3::NEG_X);
+    assert!(mesh.ray_cast(&bvh, &ray).is_none());
+    // passing by
+    let ray = Ray::new(Vec3::new(-2., 0.6, 0.2), Vec3::X);
+    assert!(mesh.ray_cast(&bvh, &ray).is_none());
+    assert!(mesh.ray_cast_all(&bvh, &ray).is_empty());
+}
+
+#[test]
+fn test_ray_cast_from_inside() {
+    let mesh = Box { size: 1. }.to_mesh();
+    let bvh = mesh.build_aabb_bvh_indexed(Default::default());
+    let ray = Ray::new(Vec3::new(0.1, 0.2, 0.), Vec3::new(0., 0., 2.));
+    let hit = mesh.ray_cast(&bvh, &ray).unwrap();
+    // direction is not normalized
+    assert!((hit.t - 0.25).abs() < 1e-6);
+    assert_eq!(hit.normal, Vec3::Z);
+    assert_eq!(mesh.ray_cast_all(&bvh, &ray).len(), 1);
+}
+
+#[test]
+fn test_ray_cast_axis_aligned() {
+    // the ray runs on the planes splitting the bvh nodes
+    let mesh = crate::Sphere::default().to_mesh().unwrap();
+    let bvh = mesh.build_aabb_bvh_indexed(Default::default());
+    let ray = Ray::new(Vec3::new(0., 0., 5.), Vec3::NEG_Z);
+    let hit = mesh.ray_cast(&bvh, &ray).unwrap();
+    assert!((hit.t - 4.).abs() < 1e-5);
+    assert_eq!(
+        mesh.ray_cast_all(&bvh, &ray).len(),
+        brute_force_hits(&mesh, &ray).len()
+    );
+
+    let mesh = Box { size: 1. }.to_mesh();
+    let bvh = mesh.build_aabb_bvh_indexed(Default::default());
+    let ray = Ray::new(Vec3::new(0.5, 0.5, 5.), Vec3::NEG_Z);
+    let hit = mesh.ray_cast(&bvh, &ray).unwrap();
+    assert!((hit.t - 4.5).abs() < 1e-6);
+    // parallel to a slab and outside of it
+    let ray = Ray::new(Vec3::new(0.6, 0., 5.), Vec3::NEG_Z);
+    assert!(mesh.ray_cast(&bvh, &ray).is_none());
+}
+
+#[test]
+fn test_ray_cast_all() {
+    // wall thickness of a box
+    let mesh = Box { size: 1. }.to_mesh();
+    let bvh = mesh.build_aabb_bvh_indexed(Default::default());
+    let ray = Ray::new(Vec3::new(0.1, -3., 0.2), Vec3::Y);
+    let hits = mesh.ray_cast_all(&bvh, &ray);
+    assert_eq!(hits.len(), 2);
+    assert!((hits[1].t - hits[0].t - 1.).abs() < 1e-6);
+    assert_eq!(hits[0].normal, Vec3::NEG_Y);
+    assert_eq!(hits[1].normal, Vec3::Y);
+    assert_eq!(hits[0], mesh.ray_cast(&bvh, &ray).unwrap());
+}
+
+#[test]
+fn test_ray_cast_random() {
+    // bvh traversal finds the same hits as testing every triangle
+    let mesh = random_cubes(50);
+    let mut rng = rand::thread_rng();
+    let dist = Uniform::new(-1., 1.);
+    let bvh = mesh.build_aabb_bvh_indexed(Default::default());
+    for _ in 0..200 {
+        let origin = Vec3::new(dist.sample(&mut rng), dist.sample(&mut rng), 2.);
+        let target = Vec3::new(dist.sample(&mut rng), dist.sample(&mut rng), -2.);
+        let ray = Ray::new(origin, target - origin);
+        let expected = brute_force_hits(&mesh, &ray);
+        assert_eq!(mesh.ray_cast_all(&bvh, &ray), expected);
+        let closest = mesh.ray_cast(&bvh, &ray);
+        assert_eq!(closest.map(|hit| hit.t), expected.first().map(|hit| hit.t));
+    }
+}
+
+fn random_cubes(count: usize) -> IndexMesh {
+    let mut mesh = IndexMesh::new();
+    let mut rng = rand::thread_rng();
+    let dist = Uniform::new(-1., 1.);
+    for i in 0..count {
+        let mut cube = Box { size: 0.3 }.to_mesh();
+        cube.rotate(Quat::from_rotation_y(i as f32));
+        cube.translate(Vec3::new(
+            dist.sample(&mut rng),
+            dist.sample(&mut rng),
+            dist.sample(&mut rng),
+        ));
+        let n = mesh.vertices.len();
+        mesh.vertices.extend(cube.vertices.iter());
+        mesh.triangles.extend(
+            cube.triangles
+                .iter()
+                .map(|t| IndexTriangle(t.0 + n, t.1 + n, t.2 + n)),
+        );
+    }
+    mesh
+}
+
+#[test]
+fn test_closest_point_on_triangle() {
+    let tri = [Vec3::ZERO, Vec3::X, Vec3::Y];
+    let cases = [
+        (Vec3::new(0.2, 0.3, 1.), Vec3::new(0.5, 0.2, 0.3)),
+        (Vec3::new(-1., -1., 0.), Vec3::X),
+        (Vec3::new(2., -0.5, 0.), Vec3::Y),
+        (Vec3::new(-0.5, 3., 0.), Vec3::Z),
+        (Vec3::new(0.5, -1., 0.), Vec3::new(0.5, 0.5, 0.)),
+        (Vec3::new(-1., 0.5, 2.), Vec3::new(0.5, 0., 0.5)),
+        (Vec3::new(1., 1., 0.), Vec3::new(0., 0.5, 0.5)),
+    ];
+    for (p, expected) in cases {
+        let barycentric = closest_point_on_triangle(p, &tri);
+        assert!(barycentric.distance(expected) < 1e-6, "{p} {barycentric}");
+    }
+}
+
+#[test]
+fn test_closest_point() {
+    let mesh = Box { size: 1. }.to_mesh();
+    let bvh = mesh.build_aabb_bvh_indexed(Default::default());
+    // face, edge and corner of the box
+    let cases = [
+        (Vec3::new(2., 0.1, 0.2), Vec3::new(0.5, 0.1, 0.2)),
+        (Vec3::new(1., 1., 0.2), Vec3::new(0.5, 0.5, 0.2)),
+        (Vec3::new(-1., -1., -1.), Vec3::splat(-0.5)),
+        (Vec3::new(0.1, 0.3, 0.), Vec3::new(0.1, 0.5, 0.)),
+    ];
+    for (p, expected) in cases {
+        let closest = mesh.closest_point(&bvh, p).unwrap();
+        assert!(closest.point.distance(expected) < 1e-6);
+        assert!((closest.distance - p.distance(expected)).abs() < 1e-6);
+        assert!((mesh.distance(&bvh, p).unwrap() - closest.distance).abs() < 1e-6);
+        let t = mesh.triangles[closest.triangle];
+        let b = closest.barycentric;
+        let point = mesh.vertices[t.0] * b.x + mesh.vertices[t.1] * b.y + mesh.vertices[t.2] * b.z;
+        assert!(point.distance(closest.point) < 1e-6);
+    }
+}
+
+#[test]
+fn test_closest_point_random() {
+    let mesh = random_cubes(50);
+    let bvh = mesh.build_aabb_bvh_indexed(Default::default());
+    let mut rng = rand::thread_rng();
+    let dist = Uniform::new(-2., 2.);
+    for _ in 0..200 {
+        let p = Vec3::new(
+            dist.sample(&mut rng),
+            dist.sample(&mut rng),
+            dist.sample(&mut rng),
+        );
+        let expected = mesh
+            .triangles()
+            .map(|t| {
+                let tri = [mesh.vertices[t.0], mesh.vertices[t.1], mesh.vertices[t.2]];
+                let b = closest_point_on_triangle(p, &tri);
+                (tri[0] * b.x + tri[1] * b.y + tri[2] * b.z).distance(p)
+            })
+            .fold(f32::INFINITY, f32::min);
+        let closest = mesh.closest_point(&bvh, p).unwrap();
+        assert!((closest.distance - expected).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_closed_mesh_contains() {
+    let mesh = Box { size: 1. }.to_mesh();
+    let solid = ClosedMesh::new(&mesh).unwrap();
+    assert!(solid.contains(Vec3::ZERO));
+    assert!(solid.contains(Vec3::new(0.4, -0.4, 0.4)));
+    assert!(!solid.contains(Vec3::new(0.6, 0., 0.)));
+    assert!(!solid.contains(Vec3::new(2., 2., 2.)));
+    // the first ray of this point passes through a corner of the box
+    assert!(!solid.contains(Vec3::new(
+        -0.5 - 0.352_546_3,
+        -0.5 - 0.580_298_9,
+        -0.5 - 0.734_224_5
+    )));
+    assert!((solid.winding_number(Vec3::ZERO) - 1.).abs() < 1e-6);
+    assert!(solid.winding_number(Vec3::splat(2.)).abs() < 1e-6);
+}
+
+#[test]
+fn test_closed_mesh_signed_distance() {
+    let mesh = Box { size: 1. }.to_mesh();
+    let solid = ClosedMesh::new(&mesh).unwrap();
+    assert!((solid.signed_distance(Vec3::ZERO) + 0.5).abs() < 1e-6);
+    assert!((solid.signed_distance(Vec3::new(0.3, 0.1, 0.)) + 0.2).abs() < 1e-6);
+    assert!((solid.signed_distance(Vec3::new(1.5, 0.1, 0.)) - 1.).abs() < 1e-6);
+    assert!(solid.signed_distance(Vec3::new(0.5, 0.1, 0.)).abs() < 1e-6);
+}
+
+#[test]
+fn test_closed_mesh_cavity() {
+    // box with a closed cavity inside
+    let outer = Box { size: 2. }.to_mesh().to_halfedge_mesh();
+    let inner = Box { size: 1. }.to_mesh().to_halfedge_mesh();
+    let mesh = (crate::CsgSolid::new(outer) - crate::CsgSolid::new(inner))
+        .to_mesh()
+        .unwrap();
+    let solid = ClosedMesh::new(&mesh).unwrap();
+    assert!(!solid.contains(Vec3::ZERO));
+    assert!(solid.contains(Vec3::new(0.75, 0.1, 0.2)));
+    assert!(!solid.contains(Vec3::new(1.25, 0.1, 0.2)));
+    assert!((solid.signed_distance(Vec3::new(0.1, 0., 0.)) - 0.4).abs() < 1e-6);
+    assert!((solid.signed_distance(Vec3::new(0.7, 0., 0.)) + 0.2).abs() < 1e-6);
+}
+
+#[test]
+fn test_closed_mesh_random() {
+    let mut mesh = Box { size: 1. }.to_mesh();
+    mesh.rotate(Quat::from_euler(glam::EulerRot::XYZ, 0.3, 0.5, 0.7));
+    let solid = ClosedMesh::new(&mesh).unwrap();
+    let mut rng = rand::thread_rng();
+    let dist = Uniform::new(-1., 1.);
+    for _ in 0..1000 {
+        let p = Vec3::new(
+            dist.sample(&mut rng),
+            dist.sample(&mut rng),
+            dist.sample(&mut rng),
+        );
+        let inside = solid.winding_number(p) > 0.5;
+        assert_eq!(solid.contains(p), inside);
+        assert_eq!(solid.signed_distance(p) < 0., inside);
+    }
+}
+
+#[test]
+fn test_closed_mesh_open() {
+    let mut mesh = Box { size: 1. }.to_mesh();
+    mesh.triangles.pop();
+    assert!(ClosedMesh::new(&mesh).is_err());
+    assert!(ClosedMesh::new(&IndexMesh::new()).is_err());
+}
+
+#[test]
+fn test_find_intersect() {
+    let a = Box { size: 1. }.to_mesh();
+    let b = random_cubes(20);
+    let bvh_a = a.build_aabb_bvh_indexed(Default::default());
+    let bvh_b = b.build_aabb_bvh_indexed(Default::default());
+    let transform_a = Mat4::from_rotation_translation(Quat::from_rotation_z(0.3), Vec3::X * 0.2);
+    let transform_b = Mat4::from_rotation_x(0.4);
+    let pairs = a.find_intersect(&bvh_a, transform_a, &b, &bvh_b, transform_b);
+    let mut expected = vec![];
+    for (i, s) in a.triangles().enumerate() {
+        for (j, t) in b.triangles().enumerate() {
+            let s = [a.vertices[s.0], a.vertices[s.1], a.vertices[s.2]];
+            let t = [b.vertices[t.0], b.vertices[t.1], b.vertices[t.2]];
+            let s = s.map(|v| transform_a.transform_point3(v));
+            let t = t.map(|v| transform_b.transform_point3(v));
+            if triangles_intersect(s, t, 1e-6) {
+                expected.push((i, j));
+            }
+        }
+    }
+    assert_eq!(pairs, expected);
+}
+
+#[test]
+fn test_ray_cast_sah() {
+    let mesh = random_cubes(50);
+    // everything is reachable from the crate root
+    let option = crate::BuildBvhOption {
+        split_method: crate::SplitMethod::Sah(crate::SahOption::default()),
+        ..Default::default()
+    };
+    let bvh: crate::MeshBvh = mesh.build_aabb_bvh_indexed(option);
+    let mut rng = rand::thread_rng();
+    let dist = Uniform::new(-1., 1.);
+    for _ in 0..200 {
+        let origin = Vec3::new(dist.sample(&mut rng), dist.sample(&mut rng), 2.);
+        let target = Vec3::new(dist.sample(&mut rng), dist.sample(&mut rng), -2.);
+        let ray = Ray::new(origin, target - origin);
+        assert_eq!(mesh.ray_cast_all(&bvh, &ray), brute_force_hits(&mesh, &ray));
+    }
+}
@@ -0,0 +1,19 @@
+use crate::IndexMesh;
+
+/// signed volume enclosed by the triangles, positive when they face outwards
+pub(crate) fn volume(mesh: &IndexMesh) -> f32 {
+    mesh.triangles()
+        .map(|t| {
+            let (a, b, c) = (mesh.vertices[t.0], mesh.vertices[t.1], mesh.vertices[t.2]);
+            a.dot(b.cross(c)) / 6.
+        })
+        .sum()
+}
+
+/// closed, manifold and facing outwards
+pub(crate) fn assert_closed(mesh: &IndexMesh) {
+    let half_edge = mesh.to_halfedge_mesh();
+    assert!(half_edge.is_mesh_watertight());
+    assert!(half_edge.is_mesh_manifold());
+    assert!(volume(mesh) > 0.);
+}
@@ -1,6 +1,6 @@
 use glam::{mat2, Vec2, Vec3};
 
-use crate::bv::{BoundingVolume, AABB};
+use crate::bv::{BoundingVolume, Bsphere, AABB};
 
 #[cfg(test)]
 mod tests;
@@ -114,3 +114,9 @@ impl<const D: usize> Intersect<AABB<D>> for AABB<D> {
         self.intersect_aabb(p, err)
     }
 }
+
+impl<const D: usize> Intersect<Bsphere<D>> for Bsphere<D> {
+    fn intersect(&self, p: &Self, err: f32) -> bool {
+        self.center_distance(p) + err <= self.radius + p.radius
+    }
+}
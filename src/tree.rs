@@ -145,12 +145,15 @@ impl<T> Tree<T> {
         }
     }
 
-    // TODO test need
+    /// make a new tree with `root` as root node, self as left subtree and other as right subtree
     pub(crate) fn merge(self, other: Self, root: T) -> Self {
         let mut vec = Vec::with_capacity(1 + self.len() + other.len());
-        vec.push(Node::new(root, 1, 0));
-        let max_depth = self.max_depth.max(other.max_depth) + 1;
         let bias = self.len() + 1;
+        let mut root = Node::new(root, 1, 0);
+        root.left = Some(1);
+        root.right = Some(bias);
+        vec.push(root);
+        let max_depth = self.max_depth.max(other.max_depth) + 1;
         vec.extend(self.data.into_iter().map(|node| Node {
             data: node.data,
             depth: node.depth + 1,
@@ -210,6 +213,25 @@ mod tree_test {
         assert_eq!(tree.data[3].parent, 2);
     }
 
+    #[test]
+    fn test_tree_merge() {
+        let mut left = Tree::new_root(1);
+        left.add_child(0, ChildSide::Left, 2).unwrap();
+        left.add_child(0, ChildSide::Right, 3).unwrap();
+        let right = Tree::new_root(4);
+        let tree = left.merge(right, 0);
+        assert_eq!(tree.max_depth, 3);
+        assert_eq!(tree.data[0].left, Some(1));
+        assert_eq!(tree.data[0].right, Some(4));
+        assert_eq!(tree.data[1].parent, 0);
+        assert_eq!(tree.data[1].left, Some(2));
+        assert_eq!(tree.data[1].right, Some(3));
+        assert_eq!(tree.data[2].parent, 1);
+        assert_eq!(tree.data[2].depth, 3);
+        assert_eq!(tree.data[4].parent, 0);
+        assert_eq!(tree.data[4].data, 4);
+    }
+
     #[test]
     fn test_tree_iter() {
         let mut tree = Tree::new_root(3);